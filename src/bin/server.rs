@@ -0,0 +1,79 @@
+//! Runs a match headless in real time for clients joining over UDP with `snake --connect`.
+//!
+//! `server [--bind <address>] [--seed <n>] [--cols <n>] [--rows <n>] [--spectate <address>]`
+
+use bevy::{app::ScheduleRunnerSettings, log::LogPlugin, time::TimeUpdateStrategy};
+use snake::{
+  board::resources::GameBoard,
+  net::{resources::Server, ServerPlugin, DEFAULT_SERVER_ADDRESS},
+  simulation::{utils::headless_app, TICK},
+  spectator::SpectatorPlugin,
+};
+use std::process::ExitCode;
+
+struct Options {
+  address: String,
+  seed: u64,
+  cols: i32,
+  rows: i32,
+}
+
+impl Options {
+  fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+    let mut options = Self {
+      address: DEFAULT_SERVER_ADDRESS.to_string(),
+      seed: rand::random(),
+      cols: 40,
+      rows: 40,
+    };
+    while let Some(arg) = args.next() {
+      let value = args.next().ok_or(format!("{arg} needs a value"))?;
+      let invalid = |_| format!("invalid value for {arg}: {value}");
+      match arg.as_str() {
+        "--bind" => options.address = value,
+        "--seed" => options.seed = value.parse().map_err(invalid)?,
+        "--cols" => options.cols = value.parse().map_err(invalid)?,
+        "--rows" => options.rows = value.parse().map_err(invalid)?,
+        // Read by the spectator plugin.
+        "--spectate" => {}
+        _ => return Err(format!("unknown argument {arg}")),
+      }
+    }
+    Ok(options)
+  }
+}
+
+fn main() -> ExitCode {
+  let options = match Options::from_args(std::env::args().skip(1)) {
+    Ok(options) => options,
+    Err(err) => {
+      eprintln!("{err}");
+      return ExitCode::FAILURE;
+    }
+  };
+  let server = match Server::bind(&options.address) {
+    Ok(server) => server,
+    Err(err) => {
+      eprintln!("couldn't listen on {}: {err}", options.address);
+      return ExitCode::FAILURE;
+    }
+  };
+  if let Ok(address) = server.local_addr() {
+    println!("listening on {address}, seed {}", options.seed);
+  }
+
+  let mut app = headless_app(
+    options.seed,
+    GameBoard::with_cells(options.cols, options.rows),
+  );
+  // Clients play along in real time, rather than one step at a time.
+  app
+    .insert_resource(TimeUpdateStrategy::Automatic)
+    .insert_resource(ScheduleRunnerSettings::run_loop(TICK))
+    .insert_resource(server)
+    .add_plugin(LogPlugin::default())
+    .add_plugin(ServerPlugin)
+    .add_plugin(SpectatorPlugin)
+    .run();
+  ExitCode::SUCCESS
+}
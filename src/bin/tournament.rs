@@ -0,0 +1,131 @@
+//! Plays headless free for all matches between enemy brains and rates them.
+//!
+//! `tournament [--matches <n>] [--seed <n>] [--cols <n>] [--rows <n>] [--max-ticks <n>]
+//!   [--difficulty easy|normal|hard|insane] [--brains <name,name,..>] [--report <path>]
+//!   [--network <brain>=<weights file>]..`
+
+use snake::{
+  enemy::{resources::Personalities, utils::custom_personalities},
+  tournament::{run_tournament, Brain, MatchConfig, Report},
+};
+use std::{fs, process::ExitCode};
+
+pub const DEFAULT_REPORT: &str = "tournament.json";
+
+struct Options {
+  config: MatchConfig,
+  matches: u64,
+  seed: u64,
+  brains: Option<Vec<String>>,
+  networks: Vec<(String, String)>,
+  report: String,
+}
+
+impl Options {
+  fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+    let mut options = Self {
+      config: MatchConfig::default(),
+      matches: 20,
+      seed: 0,
+      brains: None,
+      networks: Vec::new(),
+      report: DEFAULT_REPORT.to_string(),
+    };
+    while let Some(arg) = args.next() {
+      let value = args.next().ok_or(format!("{arg} needs a value"))?;
+      let invalid = |_| format!("invalid value for {arg}: {value}");
+      match arg.as_str() {
+        "--matches" => options.matches = value.parse().map_err(invalid)?,
+        "--seed" => options.seed = value.parse().map_err(invalid)?,
+        "--cols" => options.config.cols = value.parse().map_err(invalid)?,
+        "--rows" => options.config.rows = value.parse().map_err(invalid)?,
+        "--max-ticks" => options.config.max_ticks = value.parse().map_err(invalid)?,
+        "--difficulty" => {
+          options.config.difficulty =
+            serde_json::from_value(serde_json::Value::String(value.clone()))
+              .map_err(|_| format!("invalid value for {arg}: {value}"))?
+        }
+        "--brains" => options.brains = Some(value.split(',').map(str::to_string).collect()),
+        "--network" => {
+          let (brain, network) = value
+            .split_once('=')
+            .ok_or(format!("invalid value for {arg}: {value}"))?;
+          options
+            .networks
+            .push((brain.to_string(), network.to_string()));
+        }
+        "--report" => options.report = value,
+        _ => return Err(format!("unknown argument {arg}")),
+      }
+    }
+    Ok(options)
+  }
+}
+
+fn print_table(report: &Report) {
+  println!(
+    "{:<16} {:>7} {:>7} {:>5} {:>6} {:>12} {:>10}",
+    "brain", "rating", "matches", "wins", "kills", "avg survival", "avg length"
+  );
+  for record in &report.records {
+    println!(
+      "{:<16} {:>7.0} {:>7} {:>5} {:>6} {:>12.0} {:>10.1}",
+      record.brain,
+      record.rating,
+      record.matches,
+      record.wins,
+      record.kills,
+      record.average_survival(),
+      record.average_length(),
+    );
+  }
+}
+
+fn main() -> ExitCode {
+  let options = match Options::from_args(std::env::args().skip(1)) {
+    Ok(options) => options,
+    Err(err) => {
+      eprintln!("{err}");
+      return ExitCode::FAILURE;
+    }
+  };
+
+  let mut personalities = Personalities::default();
+  personalities.extend(custom_personalities());
+  let names = options
+    .brains
+    .unwrap_or_else(|| personalities.keys().cloned().collect());
+  let mut brains = Vec::<Brain>::new();
+  for name in names {
+    let Some(personality) = personalities.get(&name) else {
+      eprintln!("unknown brain {name}");
+      return ExitCode::FAILURE;
+    };
+    // Ratings and records are kept by name, a brain entered twice would play against itself.
+    if brains.iter().any(|brain| brain.name == name) {
+      eprintln!("brain {name} is listed more than once");
+      return ExitCode::FAILURE;
+    }
+    let network = options
+      .networks
+      .iter()
+      .find(|(brain, _)| *brain == name)
+      .map(|(_, network)| network.clone());
+    brains.push(Brain {
+      name,
+      personality: *personality,
+      network,
+    });
+  }
+
+  let report = run_tournament(&options.config, &brains, options.seed, options.matches);
+  print_table(&report);
+  let written = serde_json::to_string_pretty(&report)
+    .map_err(|err| err.to_string())
+    .and_then(|json| fs::write(&options.report, json).map_err(|err| err.to_string()));
+  if let Err(err) = written {
+    eprintln!("couldn't write {}: {err}", options.report);
+    return ExitCode::FAILURE;
+  }
+  ExitCode::SUCCESS
+}
@@ -0,0 +1,101 @@
+//! Evolves enemy parameter sets in headless matches against the built-in brains and saves the
+//! best ones as personalities, for rosters to use by name.
+//!
+//! `tune [--population <n>] [--generations <n>] [--matches <n>] [--seed <n>] [--cols <n>]
+//!   [--rows <n>] [--max-ticks <n>] [--keep <n>] [--prefix <name>] [--output <path>]`
+
+use snake::{
+  config::{self, CONFIG_DIR},
+  enemy::{components::Personality, resources::Personalities, PERSONALITIES_FILE},
+  tournament::Brain,
+  tuning::{evolve, TuningConfig},
+};
+use std::{collections::BTreeMap, fs, path::Path, process::ExitCode};
+
+struct Options {
+  config: TuningConfig,
+  keep: usize,
+  prefix: String,
+  output: String,
+}
+
+impl Options {
+  fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+    let mut options = Self {
+      config: TuningConfig::default(),
+      keep: 3,
+      prefix: "tuned".to_string(),
+      output: Path::new(CONFIG_DIR)
+        .join(PERSONALITIES_FILE)
+        .to_string_lossy()
+        .into_owned(),
+    };
+    while let Some(arg) = args.next() {
+      let value = args.next().ok_or(format!("{arg} needs a value"))?;
+      let invalid = |_| format!("invalid value for {arg}: {value}");
+      let config = &mut options.config;
+      match arg.as_str() {
+        "--population" => config.population = value.parse().map_err(invalid)?,
+        "--generations" => config.generations = value.parse().map_err(invalid)?,
+        "--matches" => config.matches = value.parse().map_err(invalid)?,
+        "--seed" => config.seed = value.parse().map_err(invalid)?,
+        "--cols" => config.match_config.cols = value.parse().map_err(invalid)?,
+        "--rows" => config.match_config.rows = value.parse().map_err(invalid)?,
+        "--max-ticks" => config.match_config.max_ticks = value.parse().map_err(invalid)?,
+        "--keep" => options.keep = value.parse().map_err(invalid)?,
+        "--prefix" => options.prefix = value,
+        "--output" => options.output = value,
+        _ => return Err(format!("unknown argument {arg}")),
+      }
+    }
+    Ok(options)
+  }
+}
+
+fn main() -> ExitCode {
+  let options = match Options::from_args(std::env::args().skip(1)) {
+    Ok(options) => options,
+    Err(err) => {
+      eprintln!("{err}");
+      return ExitCode::FAILURE;
+    }
+  };
+
+  let opponents = Personalities::default()
+    .iter()
+    .map(|(name, personality)| Brain {
+      name: name.clone(),
+      personality: *personality,
+      network: None,
+    })
+    .collect::<Vec<_>>();
+  let best = evolve(&options.config, &opponents, |generation, rated| {
+    let average = rated.iter().map(|candidate| candidate.fitness).sum::<f32>() / rated.len() as f32;
+    println!(
+      "generation {:>3}: best {:.3}, average {:.3}",
+      generation + 1,
+      rated[0].fitness,
+      average
+    );
+  });
+
+  // Other personalities in the file are kept, only the tuned ones are replaced.
+  let mut personalities =
+    config::load::<BTreeMap<String, Personality>>(&options.output).unwrap_or_default();
+  for (i, candidate) in best.iter().take(options.keep).enumerate() {
+    let name = format!("{}-{}", options.prefix, i + 1);
+    println!(
+      "{name}: {:.3} {:?}",
+      candidate.fitness, candidate.personality
+    );
+    personalities.insert(name, candidate.personality);
+  }
+  let written = serde_json::to_string_pretty(&personalities)
+    .map_err(|err| err.to_string())
+    .and_then(|json| fs::write(&options.output, json).map_err(|err| err.to_string()));
+  if let Err(err) = written {
+    eprintln!("couldn't write {}: {err}", options.output);
+    return ExitCode::FAILURE;
+  }
+  ExitCode::SUCCESS
+}
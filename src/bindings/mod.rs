@@ -0,0 +1,473 @@
+mod systems;
+
+use crate::replay::resources::Playback;
+use bevy::{
+  ecs::schedule::common_conditions::not,
+  input::InputSystem,
+  prelude::{
+    resource_exists, App, CoreSet, IntoSystemConfig, IntoSystemConfigs, KeyCode, Plugin, StartupSet,
+  },
+};
+
+pub const BINDINGS_FILE: &str = "bindings.json";
+pub const SETTINGS_KEY: KeyCode = KeyCode::F1;
+/// How far a stick has to be pushed to count as pressed.
+pub const AXIS_THRESHOLD: f32 = 0.5;
+/// Most assists per life the settings screen goes up to.
+pub const MAX_ASSISTS: u32 = 9;
+
+/// Maps keys, gamepad buttons and sticks to actions, as kept in `BINDINGS_FILE`.
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<resources::InputMap>()
+      .init_resource::<resources::Actions>()
+      .add_startup_system(systems::load_bindings.in_base_set(StartupSet::PreStartup))
+      .add_system(
+        systems::update_actions
+          .in_base_set(CoreSet::PreUpdate)
+          .after(InputSystem),
+      );
+  }
+}
+
+/// Settings screen opened with `SETTINGS_KEY` to rebind the actions of the `ActionsPlugin`.
+pub struct BindingsPlugin;
+
+impl Plugin for BindingsPlugin {
+  fn build(&self, app: &mut App) {
+    let settings_open = resource_exists::<resources::SettingsScreen>;
+    app
+      // Rebinding the keys of a replay being played back would only get in its way.
+      .add_system(systems::toggle_settings.run_if(not(resource_exists::<Playback>())))
+      .add_systems(
+        (systems::edit_settings, systems::update_settings_hud)
+          .chain()
+          .after(systems::toggle_settings)
+          .distributive_run_if(settings_open()),
+      );
+  }
+}
+
+pub mod components {
+  use bevy::prelude::Component;
+
+  #[derive(Component)]
+  pub struct SettingsHud;
+}
+
+pub mod resources {
+  use crate::{player::components::KeyScheme, snake::components::Direction};
+  use bevy::{
+    input::gamepad::{GamepadAxisType, GamepadButtonType},
+    prelude::{KeyCode, Resource},
+  };
+  use serde::{Deserialize, Serialize};
+  use std::collections::{BTreeMap, HashSet};
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+  #[serde(rename_all = "snake_case")]
+  pub enum Action {
+    TurnUp,
+    TurnLeft,
+    TurnDown,
+    TurnRight,
+    Respawn,
+    Autopilot,
+    Pause,
+    Grow,
+    Shrink,
+    DebugInfo,
+    MoveBoardUp,
+    MoveBoardLeft,
+    MoveBoardDown,
+    MoveBoardRight,
+    Start,
+    Rewind,
+    Quicksave,
+    Quickload,
+    ToggleSquad,
+    EasyDifficulty,
+    NormalDifficulty,
+    HardDifficulty,
+    InsaneDifficulty,
+    ToggleDynamicDifficulty,
+  }
+
+  impl Action {
+    /// Actions each local player takes with their own bindings.
+    pub const PLAYER: [Self; 6] = [
+      Self::TurnUp,
+      Self::TurnLeft,
+      Self::TurnDown,
+      Self::TurnRight,
+      Self::Respawn,
+      Self::Autopilot,
+    ];
+    /// Actions that aren't any one player's.
+    pub const GLOBAL: [Self; 18] = [
+      Self::Pause,
+      Self::Grow,
+      Self::Shrink,
+      Self::DebugInfo,
+      Self::MoveBoardUp,
+      Self::MoveBoardLeft,
+      Self::MoveBoardDown,
+      Self::MoveBoardRight,
+      Self::Start,
+      Self::Rewind,
+      Self::Quicksave,
+      Self::Quickload,
+      Self::ToggleSquad,
+      Self::EasyDifficulty,
+      Self::NormalDifficulty,
+      Self::HardDifficulty,
+      Self::InsaneDifficulty,
+      Self::ToggleDynamicDifficulty,
+    ];
+    pub const TURNS: [(Self, Direction); 4] = [
+      (Self::TurnUp, Direction::Top),
+      (Self::TurnLeft, Direction::Left),
+      (Self::TurnDown, Direction::Bottom),
+      (Self::TurnRight, Direction::Right),
+    ];
+
+    pub fn label(&self) -> &'static str {
+      match self {
+        Self::TurnUp => "turn up",
+        Self::TurnLeft => "turn left",
+        Self::TurnDown => "turn down",
+        Self::TurnRight => "turn right",
+        Self::Respawn => "respawn",
+        Self::Autopilot => "autopilot",
+        Self::Pause => "pause",
+        Self::Grow => "grow",
+        Self::Shrink => "shrink",
+        Self::DebugInfo => "debug info",
+        Self::MoveBoardUp => "move board up",
+        Self::MoveBoardLeft => "move board left",
+        Self::MoveBoardDown => "move board down",
+        Self::MoveBoardRight => "move board right",
+        Self::Start => "start",
+        Self::Rewind => "rewind",
+        Self::Quicksave => "quicksave",
+        Self::Quickload => "quickload",
+        Self::ToggleSquad => "squad",
+        Self::EasyDifficulty => "easy",
+        Self::NormalDifficulty => "normal",
+        Self::HardDifficulty => "hard",
+        Self::InsaneDifficulty => "insane",
+        Self::ToggleDynamicDifficulty => "auto difficulty",
+      }
+    }
+  }
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+  #[serde(rename_all = "snake_case")]
+  pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+    /// A stick pushed one way along an axis.
+    Axis {
+      axis: GamepadAxisType,
+      positive: bool,
+    },
+    /// A click or a touch on the left or right half of the window.
+    Pointer {
+      left: bool,
+    },
+  }
+
+  impl Binding {
+    pub fn label(&self) -> String {
+      match self {
+        Self::Key(key) => format!("{key:?}").to_lowercase(),
+        Self::Button(button) => format!("pad {button:?}").to_lowercase(),
+        Self::Axis { axis, positive } => {
+          format!("pad {axis:?}{}", if *positive { "+" } else { "-" }).to_lowercase()
+        }
+        Self::Pointer { left } => format!("click {}", if *left { "left" } else { "right" }),
+      }
+    }
+  }
+
+  /// How the turns of a player are read.
+  #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+  #[serde(rename_all = "snake_case")]
+  pub enum SteeringMode {
+    /// Each turn goes the way it's named.
+    #[default]
+    Absolute,
+    /// Turning left or right rotates the snake from where it's headed, up and down do nothing.
+    Relative,
+    /// The snake heads for the cell under the cursor, or under the latest touch.
+    Pointer,
+  }
+
+  impl SteeringMode {
+    pub fn label(&self) -> &'static str {
+      match self {
+        Self::Absolute => "absolute",
+        Self::Relative => "relative",
+        Self::Pointer => "pointer",
+      }
+    }
+  }
+
+  pub type Bindings = BTreeMap<Action, Vec<Binding>>;
+
+  /// What triggers each action. Gamepads go with the key schemes in the order they connected,
+  /// the first one with `KeyScheme::Wasd`, while any of them takes the global actions.
+  #[derive(Debug, Clone, Resource, Serialize, Deserialize)]
+  #[serde(default)]
+  pub struct InputMap {
+    /// Bindings of the players joining with each key scheme.
+    pub players: BTreeMap<KeyScheme, Bindings>,
+    pub global: Bindings,
+    /// Steering of the players of each key scheme, absolute when left out.
+    pub steering: BTreeMap<KeyScheme, SteeringMode>,
+    /// Moves into a snake the players of each key scheme are kept from making per life, none
+    /// when left out.
+    pub assists: BTreeMap<KeyScheme, u32>,
+  }
+
+  impl InputMap {
+    /// Bindings of the players of a key scheme, or the global ones for `None`.
+    pub fn bindings(&self, keys: Option<KeyScheme>, action: Action) -> &[Binding] {
+      let bindings = match keys {
+        Some(keys) => self.players.get(&keys),
+        None => Some(&self.global),
+      };
+      bindings
+        .and_then(|bindings| bindings.get(&action))
+        .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn bindings_mut(&mut self, keys: Option<KeyScheme>, action: Action) -> &mut Vec<Binding> {
+      let bindings = match keys {
+        Some(keys) => self.players.entry(keys).or_default(),
+        None => &mut self.global,
+      };
+      bindings.entry(action).or_default()
+    }
+
+    /// Binds the actions this map leaves out as they are by default, ones added since it was
+    /// saved. Actions whose bindings were all cleared stay unbound.
+    pub fn with_defaults(mut self) -> Self {
+      let defaults = Self::default();
+      for (keys, bindings) in defaults.players {
+        let saved = self.players.entry(keys).or_default();
+        for (action, bindings) in bindings {
+          saved.entry(action).or_insert(bindings);
+        }
+      }
+      for (action, bindings) in defaults.global {
+        self.global.entry(action).or_insert(bindings);
+      }
+      self
+    }
+
+    pub fn steering(&self, keys: KeyScheme) -> SteeringMode {
+      self.steering.get(&keys).copied().unwrap_or_default()
+    }
+
+    pub fn assists(&self, keys: KeyScheme) -> u32 {
+      self.assists.get(&keys).copied().unwrap_or_default()
+    }
+
+    /// Bindings of an action as shown to players, `-` when it has none.
+    pub fn label(&self, keys: Option<KeyScheme>, action: Action) -> String {
+      let bindings = self.bindings(keys, action);
+      if bindings.is_empty() {
+        return "-".to_string();
+      }
+      bindings
+        .iter()
+        .map(Binding::label)
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+  }
+
+  impl Default for InputMap {
+    fn default() -> Self {
+      use GamepadAxisType::*;
+      use GamepadButtonType::*;
+      let players = KeyScheme::ALL
+        .into_iter()
+        .map(|keys| {
+          let [up, left, down, right, respawn, autopilot] = match keys {
+            KeyScheme::Wasd => [
+              KeyCode::W,
+              KeyCode::A,
+              KeyCode::S,
+              KeyCode::D,
+              KeyCode::R,
+              KeyCode::T,
+            ],
+            KeyScheme::Arrows => [
+              KeyCode::Up,
+              KeyCode::Left,
+              KeyCode::Down,
+              KeyCode::Right,
+              KeyCode::RShift,
+              KeyCode::RControl,
+            ],
+            KeyScheme::Ijkl => [
+              KeyCode::I,
+              KeyCode::J,
+              KeyCode::K,
+              KeyCode::L,
+              KeyCode::U,
+              KeyCode::Y,
+            ],
+            KeyScheme::Numpad => [
+              KeyCode::Numpad8,
+              KeyCode::Numpad4,
+              KeyCode::Numpad2,
+              KeyCode::Numpad6,
+              KeyCode::Numpad0,
+              KeyCode::NumpadDecimal,
+            ],
+          };
+          let stick = |axis, positive| Binding::Axis { axis, positive };
+          let bindings = Bindings::from([
+            (
+              Action::TurnUp,
+              vec![
+                Binding::Key(up),
+                Binding::Button(DPadUp),
+                stick(LeftStickY, true),
+              ],
+            ),
+            (
+              Action::TurnLeft,
+              vec![
+                Binding::Key(left),
+                Binding::Button(DPadLeft),
+                stick(LeftStickX, false),
+              ],
+            ),
+            (
+              Action::TurnDown,
+              vec![
+                Binding::Key(down),
+                Binding::Button(DPadDown),
+                stick(LeftStickY, false),
+              ],
+            ),
+            (
+              Action::TurnRight,
+              vec![
+                Binding::Key(right),
+                Binding::Button(DPadRight),
+                stick(LeftStickX, true),
+              ],
+            ),
+            (
+              Action::Respawn,
+              vec![Binding::Key(respawn), Binding::Button(South)],
+            ),
+            (
+              Action::Autopilot,
+              vec![Binding::Key(autopilot), Binding::Button(North)],
+            ),
+          ]);
+          (keys, bindings)
+        })
+        .collect();
+      let global = Bindings::from([
+        (
+          Action::Pause,
+          vec![Binding::Key(KeyCode::P), Binding::Button(Start)],
+        ),
+        (Action::Grow, vec![Binding::Key(KeyCode::E)]),
+        (Action::Shrink, vec![Binding::Key(KeyCode::Q)]),
+        (Action::DebugInfo, vec![Binding::Key(KeyCode::O)]),
+        (Action::MoveBoardUp, vec![Binding::Key(KeyCode::Up)]),
+        (Action::MoveBoardLeft, vec![Binding::Key(KeyCode::Left)]),
+        (Action::MoveBoardDown, vec![Binding::Key(KeyCode::Down)]),
+        (Action::MoveBoardRight, vec![Binding::Key(KeyCode::Right)]),
+        (Action::Start, vec![Binding::Key(KeyCode::Return)]),
+        (Action::Rewind, vec![Binding::Key(KeyCode::Back)]),
+        (Action::Quicksave, vec![Binding::Key(KeyCode::F5)]),
+        (Action::Quickload, vec![Binding::Key(KeyCode::F9)]),
+        (Action::ToggleSquad, vec![Binding::Key(KeyCode::G)]),
+        (Action::EasyDifficulty, vec![Binding::Key(KeyCode::Key1)]),
+        (Action::NormalDifficulty, vec![Binding::Key(KeyCode::Key2)]),
+        (Action::HardDifficulty, vec![Binding::Key(KeyCode::Key3)]),
+        (Action::InsaneDifficulty, vec![Binding::Key(KeyCode::Key4)]),
+        (
+          Action::ToggleDynamicDifficulty,
+          vec![Binding::Key(KeyCode::Key0)],
+        ),
+      ]);
+      Self {
+        players,
+        global,
+        steering: BTreeMap::new(),
+        assists: BTreeMap::new(),
+      }
+    }
+  }
+
+  /// Actions held this frame, for the players of a key scheme or globally.
+  #[derive(Debug, Resource, Default)]
+  pub struct Actions {
+    pub(super) pressed: HashSet<(Option<KeyScheme>, Action)>,
+    pub(super) just_pressed: HashSet<(Option<KeyScheme>, Action)>,
+  }
+
+  impl Actions {
+    pub fn pressed(&self, keys: Option<KeyScheme>, action: Action) -> bool {
+      self.pressed.contains(&(keys, action))
+    }
+
+    pub fn just_pressed(&self, keys: Option<KeyScheme>, action: Action) -> bool {
+      self.just_pressed.contains(&(keys, action))
+    }
+
+    /// Direction the players of a key scheme hold a turn towards, the first one if several are.
+    pub fn held_turn(&self, keys: KeyScheme) -> Option<Direction> {
+      Action::TURNS
+        .into_iter()
+        .find(|(action, _)| self.pressed(Some(keys), *action))
+        .map(|(_, direction)| direction)
+    }
+
+    /// Directions of the turns the players of a key scheme just asked for.
+    pub fn turns(&self, keys: KeyScheme) -> impl Iterator<Item = Direction> + '_ {
+      Action::TURNS
+        .into_iter()
+        .filter(move |(action, _)| self.just_pressed(Some(keys), *action))
+        .map(|(_, direction)| direction)
+    }
+  }
+
+  /// Present while the settings screen is open.
+  #[derive(Debug, Resource, Default)]
+  pub struct SettingsScreen {
+    /// Key scheme whose bindings are shown, `None` for the global ones.
+    pub keys: Option<KeyScheme>,
+    pub row: usize,
+    /// Whether the next input pressed gets bound to the selected action.
+    pub capturing: bool,
+    /// Whether the match was playing when the screen opened.
+    pub(super) resume: bool,
+  }
+
+  impl SettingsScreen {
+    pub fn actions(&self) -> &'static [Action] {
+      match self.keys {
+        Some(_) => &Action::PLAYER,
+        None => &Action::GLOBAL,
+      }
+    }
+
+    pub fn action(&self) -> Action {
+      self.actions()[self.row]
+    }
+  }
+}
@@ -0,0 +1,345 @@
+use super::{
+  components::SettingsHud,
+  resources::{Action, Actions, Binding, InputMap, SettingsScreen, SteeringMode},
+  AXIS_THRESHOLD, BINDINGS_FILE, MAX_ASSISTS, SETTINGS_KEY,
+};
+use crate::{
+  config::{self, CONFIG_DIR},
+  player::components::{KeyScheme, Player},
+  state::GameState,
+};
+use bevy::{
+  ecs::system::SystemParam,
+  input::gamepad::{Gamepad, GamepadAxis, GamepadAxisType, GamepadButton, Gamepads},
+  prelude::{
+    warn, AssetServer, Axis, Color, Commands, DespawnRecursiveExt, Entity, Input, KeyCode,
+    MouseButton, NextState, PositionType, Query, Res, ResMut, State, Style, Text, TextBundle,
+    TextStyle, Touches, UiRect, Val, Vec2, With,
+  },
+  window::{PrimaryWindow, Window},
+};
+use std::{collections::HashSet, path::Path, slice};
+
+/// Every device a binding can be on.
+#[derive(SystemParam)]
+pub(super) struct InputDevices<'w, 's> {
+  keyboard_input: Res<'w, Input<KeyCode>>,
+  button_input: Res<'w, Input<GamepadButton>>,
+  axes: Res<'w, Axis<GamepadAxis>>,
+  gamepads: Res<'w, Gamepads>,
+  mouse_input: Res<'w, Input<MouseButton>>,
+  touches: Res<'w, Touches>,
+  q_window: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+}
+
+impl InputDevices<'_, '_> {
+  /// Connected gamepads, in the order they connected.
+  fn gamepads(&self) -> Vec<Gamepad> {
+    let mut gamepads = self.gamepads.iter().collect::<Vec<_>>();
+    gamepads.sort_by_key(|gamepad| gamepad.id);
+    gamepads
+  }
+
+  fn held(&self, binding: Binding, gamepads: &[Gamepad]) -> bool {
+    match binding {
+      Binding::Key(key) => self.keyboard_input.pressed(key),
+      Binding::Button(button_type) => gamepads.iter().any(|gamepad| {
+        self
+          .button_input
+          .pressed(GamepadButton::new(*gamepad, button_type))
+      }),
+      Binding::Axis { axis, positive } => gamepads.iter().any(|gamepad| {
+        let value = self
+          .axes
+          .get(GamepadAxis::new(*gamepad, axis))
+          .unwrap_or_default();
+        if positive {
+          value > AXIS_THRESHOLD
+        } else {
+          value < -AXIS_THRESHOLD
+        }
+      }),
+      Binding::Pointer { left } => {
+        let clicked = self.mouse_input.pressed(MouseButton::Left)
+          && self
+            .cursor()
+            .map_or(false, |cursor| self.on_left(cursor) == left);
+        clicked
+          || self
+            .touches
+            .iter()
+            .any(|touch| self.on_left(touch.position()) == left)
+      }
+    }
+  }
+
+  fn cursor(&self) -> Option<Vec2> {
+    self.q_window.get_single().ok()?.cursor_position()
+  }
+
+  /// Whether a point of the window is on its left half.
+  fn on_left(&self, point: Vec2) -> bool {
+    self
+      .q_window
+      .get_single()
+      .map_or(false, |window| point.x < window.width() / 2.)
+  }
+
+  /// A key or button just pressed, or a stick pushed, on any device.
+  fn pressed_binding(&self) -> Option<Binding> {
+    use GamepadAxisType::*;
+    if let Some(key) = self.keyboard_input.get_just_pressed().next() {
+      return Some(Binding::Key(*key));
+    }
+    if let Some(button) = self.button_input.get_just_pressed().next() {
+      return Some(Binding::Button(button.button_type));
+    }
+    let clicked = self
+      .mouse_input
+      .just_pressed(MouseButton::Left)
+      .then(|| self.cursor())
+      .flatten();
+    let touched = self
+      .touches
+      .iter_just_pressed()
+      .next()
+      .map(|touch| touch.position());
+    if let Some(point) = clicked.or(touched) {
+      return Some(Binding::Pointer {
+        left: self.on_left(point),
+      });
+    }
+    self.gamepads().into_iter().find_map(|gamepad| {
+      [LeftStickX, LeftStickY, RightStickX, RightStickY]
+        .into_iter()
+        .find_map(|axis| {
+          let value = self.axes.get(GamepadAxis::new(gamepad, axis))?;
+          (value.abs() > AXIS_THRESHOLD).then_some(Binding::Axis {
+            axis,
+            positive: value > 0.,
+          })
+        })
+    })
+  }
+}
+
+fn save_bindings(input_map: &InputMap) {
+  let path = Path::new(CONFIG_DIR).join(BINDINGS_FILE);
+  if let Err(err) = config::save(&path, input_map) {
+    warn!("Couldn't save bindings to {}: {err}", path.display());
+  }
+}
+
+pub(super) fn load_bindings(mut input_map: ResMut<InputMap>) {
+  let path = Path::new(CONFIG_DIR).join(BINDINGS_FILE);
+  if let Some(config) = config::load::<InputMap>(path) {
+    *input_map = config.with_defaults();
+  }
+}
+
+pub(super) fn update_actions(
+  mut actions: ResMut<Actions>,
+  input_map: Res<InputMap>,
+  devices: InputDevices,
+  q_keys: Query<&KeyScheme, With<Player>>,
+  settings: Option<Res<SettingsScreen>>,
+) {
+  let mut pressed = HashSet::new();
+  // Nothing gets through to the match while bindings are edited.
+  if settings.is_none() {
+    let gamepads = devices.gamepads();
+    let mut claimed = HashSet::new();
+    // Free key schemes too, pressing one is how a player joins.
+    for (index, keys) in KeyScheme::ALL.into_iter().enumerate() {
+      let gamepad = gamepads.get(index).map_or(&[][..], slice::from_ref);
+      let seated = q_keys.iter().any(|taken| *taken == keys);
+      for action in Action::PLAYER {
+        let bindings = input_map.bindings(Some(keys), action);
+        if seated {
+          claimed.extend(bindings.iter().copied());
+        }
+        if bindings
+          .iter()
+          .any(|binding| devices.held(*binding, gamepad))
+        {
+          pressed.insert((Some(keys), action));
+        }
+      }
+    }
+    // What steers a player does nothing else, the arrows can't move both a snake and the board.
+    for action in Action::GLOBAL {
+      let mut bindings = input_map
+        .bindings(None, action)
+        .iter()
+        .filter(|binding| !claimed.contains(*binding));
+      if bindings.any(|binding| devices.held(*binding, &gamepads)) {
+        pressed.insert((None, action));
+      }
+    }
+  }
+  actions.just_pressed = pressed.difference(&actions.pressed).copied().collect();
+  actions.pressed = pressed;
+}
+
+/// Opens or closes the settings screen, pausing the match while it's open.
+pub(super) fn toggle_settings(
+  mut commands: Commands,
+  keyboard_input: Res<Input<KeyCode>>,
+  settings: Option<Res<SettingsScreen>>,
+  q_hud: Query<Entity, With<SettingsHud>>,
+  asset_server: Res<AssetServer>,
+  game_state: Res<State<GameState>>,
+  mut next_state: ResMut<NextState<GameState>>,
+) {
+  if !keyboard_input.just_pressed(SETTINGS_KEY) {
+    return;
+  }
+  if let Some(settings) = settings {
+    if settings.resume {
+      next_state.set(GameState::Playing);
+    }
+    commands.remove_resource::<SettingsScreen>();
+    for hud in &q_hud {
+      commands.entity(hud).despawn_recursive();
+    }
+    return;
+  }
+  let resume = game_state.0 == GameState::Playing;
+  if resume {
+    next_state.set(GameState::Paused);
+  }
+  commands.insert_resource(SettingsScreen {
+    resume,
+    ..Default::default()
+  });
+  commands.spawn((
+    SettingsHud,
+    TextBundle::from_section(
+      "",
+      TextStyle {
+        font: asset_server.load("fonts/UbuntuMono-Regular.ttf"),
+        font_size: 16.,
+        color: Color::WHITE,
+      },
+    )
+    .with_style(Style {
+      position_type: PositionType::Absolute,
+      position: UiRect {
+        left: Val::Px(10.),
+        top: Val::Px(40.),
+        ..Default::default()
+      },
+      ..Default::default()
+    }),
+  ));
+}
+
+/// Tab goes to the next key scheme, up and down pick an action, space adds whatever is pressed
+/// next to its bindings and delete clears them, m switches the steering of a key scheme and plus
+/// and minus change its assists. Changes are saved right away.
+pub(super) fn edit_settings(
+  devices: InputDevices,
+  mut settings: ResMut<SettingsScreen>,
+  mut input_map: ResMut<InputMap>,
+) {
+  if settings.capturing {
+    let Some(binding) = devices.pressed_binding() else {return};
+    settings.capturing = false;
+    if binding == Binding::Key(KeyCode::Escape) {
+      return;
+    }
+    let bindings = input_map.bindings_mut(settings.keys, settings.action());
+    if !bindings.contains(&binding) {
+      bindings.push(binding);
+      save_bindings(&input_map);
+    }
+    return;
+  }
+
+  let keyboard_input = &devices.keyboard_input;
+  let rows = settings.actions().len();
+  if keyboard_input.just_pressed(KeyCode::Tab) {
+    let pages = [None]
+      .into_iter()
+      .chain(KeyScheme::ALL.map(Some))
+      .collect::<Vec<_>>();
+    let page = pages.iter().position(|keys| *keys == settings.keys);
+    settings.keys = pages[page.map_or(0, |page| (page + 1) % pages.len())];
+    settings.row = 0;
+  } else if keyboard_input.just_pressed(KeyCode::Up) {
+    settings.row = (settings.row + rows - 1) % rows;
+  } else if keyboard_input.just_pressed(KeyCode::Down) {
+    settings.row = (settings.row + 1) % rows;
+  } else if keyboard_input.just_pressed(KeyCode::Space) {
+    settings.capturing = true;
+  } else if keyboard_input.just_pressed(KeyCode::Delete) {
+    input_map
+      .bindings_mut(settings.keys, settings.action())
+      .clear();
+    save_bindings(&input_map);
+  } else if keyboard_input.just_pressed(KeyCode::M) {
+    let Some(keys) = settings.keys else {return};
+    let steering = match input_map.steering(keys) {
+      SteeringMode::Absolute => SteeringMode::Relative,
+      SteeringMode::Relative => SteeringMode::Pointer,
+      SteeringMode::Pointer => SteeringMode::Absolute,
+    };
+    input_map.steering.insert(keys, steering);
+    save_bindings(&input_map);
+  } else if keyboard_input.any_just_pressed([KeyCode::Equals, KeyCode::NumpadAdd]) {
+    let Some(keys) = settings.keys else {return};
+    let assists = (input_map.assists(keys) + 1).min(MAX_ASSISTS);
+    input_map.assists.insert(keys, assists);
+    save_bindings(&input_map);
+  } else if keyboard_input.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+    let Some(keys) = settings.keys else {return};
+    let assists = input_map.assists(keys).saturating_sub(1);
+    input_map.assists.insert(keys, assists);
+    save_bindings(&input_map);
+  }
+}
+
+pub(super) fn update_settings_hud(
+  mut q_hud: Query<&mut Text, With<SettingsHud>>,
+  settings: Res<SettingsScreen>,
+  input_map: Res<InputMap>,
+) {
+  let Ok(mut hud) = q_hud.get_single_mut() else {return};
+  let page = settings.keys.map_or("global", |keys| keys.label());
+  let mut lines = vec![
+    "settings, f1 to close".to_string(),
+    format!("< {page} >  tab for the next page"),
+  ];
+  if let Some(keys) = settings.keys {
+    lines.push(format!(
+      "  {:<17} {}, m to switch",
+      "steering",
+      input_map.steering(keys).label()
+    ));
+    let assists = match input_map.assists(keys) {
+      0 => "off".to_string(),
+      assists => format!("{assists} per life"),
+    };
+    lines.push(format!("  {:<17} {assists}, +/- to change", "assists"));
+  }
+  lines.extend(settings.actions().iter().enumerate().map(|(row, action)| {
+    let cursor = if row == settings.row { ">" } else { " " };
+    format!(
+      "{cursor} {:<17} {}",
+      action.label(),
+      input_map.label(settings.keys, *action)
+    )
+  }));
+  lines.push(if settings.capturing {
+    format!(
+      "press a key, button or stick for {}, escape to cancel",
+      settings.action().label()
+    )
+  } else {
+    "up/down to pick, space to add a binding, delete to clear".to_string()
+  });
+  let status = lines.join("\n");
+  if hud.sections[0].value != status {
+    hud.sections[0].value = status;
+  }
+}
@@ -0,0 +1,153 @@
+use super::{resources::GameBoard, CELL_SIZE, HALF_CELL_SIZE};
+use crate::snake::components::Direction;
+use bevy::prelude::{IVec2, Vec3};
+use std::collections::VecDeque;
+
+/// Occupancy snapshot of the board in cell coordinates, wrapping around the edges.
+#[derive(Debug, Clone)]
+pub struct Grid {
+  pub cols: i32,
+  pub rows: i32,
+  blocked: Vec<bool>,
+}
+
+impl Grid {
+  pub fn new(game_board: &GameBoard) -> Self {
+    let cols = (game_board.width / CELL_SIZE).max(1.) as i32;
+    let rows = (game_board.height / CELL_SIZE).max(1.) as i32;
+    Self {
+      cols,
+      rows,
+      blocked: vec![false; (cols * rows) as usize],
+    }
+  }
+
+  pub fn with_obstacles<I: Iterator<Item = Vec3>>(game_board: &GameBoard, obstacles: I) -> Self {
+    let mut grid = Self::new(game_board);
+    for obstacle in obstacles {
+      grid.block(grid.cell(obstacle));
+    }
+    grid
+  }
+
+  /// Cell containing a board position, (0, 0) being the bottom left corner.
+  pub fn cell(&self, position: Vec3) -> IVec2 {
+    self.wrap(IVec2::new(
+      ((position.x + self.cols as f32 * HALF_CELL_SIZE) / CELL_SIZE).floor() as i32,
+      ((position.y + self.rows as f32 * HALF_CELL_SIZE) / CELL_SIZE).floor() as i32,
+    ))
+  }
+
+  /// Board position at the centre of a cell.
+  pub fn position(&self, cell: IVec2) -> Vec3 {
+    let cell = self.wrap(cell);
+    Vec3::new(
+      cell.x as f32 * CELL_SIZE + HALF_CELL_SIZE - self.cols as f32 * HALF_CELL_SIZE,
+      cell.y as f32 * CELL_SIZE + HALF_CELL_SIZE - self.rows as f32 * HALF_CELL_SIZE,
+      0.,
+    )
+  }
+
+  pub fn wrap(&self, cell: IVec2) -> IVec2 {
+    IVec2::new(cell.x.rem_euclid(self.cols), cell.y.rem_euclid(self.rows))
+  }
+
+  pub fn neighbour(&self, cell: IVec2, direction: Direction) -> IVec2 {
+    let (x, y) = direction.xy(1., 1.);
+    self.wrap(cell + IVec2::new(x as i32, y as i32))
+  }
+
+  /// Number of moves between two cells taking the wrapping edges into account.
+  pub fn distance(&self, a: IVec2, b: IVec2) -> i32 {
+    let d = (self.wrap(a) - self.wrap(b)).abs();
+    d.x.min(self.cols - d.x) + d.y.min(self.rows - d.y)
+  }
+
+  pub fn block(&mut self, cell: IVec2) {
+    let index = self.index(cell);
+    self.blocked[index] = true;
+  }
+
+  pub fn is_blocked(&self, cell: IVec2) -> bool {
+    self.blocked[self.index(cell)]
+  }
+
+  /// Closest free cell to the given one, itself if it's free or if there's none.
+  pub fn nearest_free(&self, cell: IVec2) -> IVec2 {
+    use Direction::*;
+    let mut visited = vec![false; self.blocked.len()];
+    let mut queue = VecDeque::from([self.wrap(cell)]);
+    visited[self.index(cell)] = true;
+    while let Some(cell) = queue.pop_front() {
+      if !self.is_blocked(cell) {
+        return cell;
+      }
+      for direction in [Top, Right, Bottom, Left] {
+        let next = self.neighbour(cell, direction);
+        let index = self.index(next);
+        if !visited[index] {
+          visited[index] = true;
+          queue.push_back(next);
+        }
+      }
+    }
+    self.wrap(cell)
+  }
+
+  /// Whether a free path exists from one position to another, the target cell may be occupied.
+  pub fn reachable(&self, from: Vec3, to: Vec3) -> bool {
+    self.path_length(self.cell(from), self.cell(to)).is_some()
+  }
+
+  /// Length of the shortest free path between two cells.
+  pub fn path_length(&self, from: IVec2, to: IVec2) -> Option<usize> {
+    use Direction::*;
+    let mut visited = vec![false; self.blocked.len()];
+    let mut queue = VecDeque::from([(self.wrap(from), 0)]);
+    visited[self.index(from)] = true;
+    while let Some((cell, steps)) = queue.pop_front() {
+      if cell == self.wrap(to) {
+        return Some(steps);
+      }
+      for direction in [Top, Right, Bottom, Left] {
+        let next = self.neighbour(cell, direction);
+        let index = self.index(next);
+        if visited[index] || (self.blocked[index] && next != self.wrap(to)) {
+          continue;
+        }
+        visited[index] = true;
+        queue.push_back((next, steps + 1));
+      }
+    }
+    None
+  }
+
+  /// Free cells reachable from a cell, counting no further than `limit`.
+  pub fn open_area(&self, from: IVec2, limit: usize) -> usize {
+    use Direction::*;
+    let mut visited = vec![false; self.blocked.len()];
+    let mut queue = VecDeque::from([self.wrap(from)]);
+    visited[self.index(from)] = true;
+    let mut area = 0;
+    while let Some(cell) = queue.pop_front() {
+      area += 1;
+      if area >= limit {
+        break;
+      }
+      for direction in [Top, Right, Bottom, Left] {
+        let next = self.neighbour(cell, direction);
+        let index = self.index(next);
+        if !visited[index] && !self.blocked[index] {
+          visited[index] = true;
+          queue.push_back(next);
+        }
+      }
+    }
+    area
+  }
+
+  fn index(&self, cell: IVec2) -> usize {
+    let cell = self.wrap(cell);
+    (cell.y * self.cols + cell.x) as usize
+  }
+}
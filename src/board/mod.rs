@@ -1,3 +1,4 @@
+pub mod grid;
 mod systems;
 pub mod utils;
 
@@ -43,9 +44,22 @@ pub mod resources {
   }
 
   impl GameBoard {
+    /// Board of the given size in cells, rounded down to even numbers like `resize` does.
+    pub fn with_cells(cols: i32, rows: i32) -> Self {
+      Self {
+        width: 2. * CELL_SIZE * (cols / 2).max(1) as f32,
+        height: 2. * CELL_SIZE * (rows / 2).max(1) as f32,
+      }
+    }
+
     pub fn resize(&mut self, width: f32, height: f32) {
       self.width = 2. * CELL_SIZE * (width * BOARD_WIDTH_FACTOR).floor();
       self.height = 2. * CELL_SIZE * (height * BOARD_HEIGHT_FACTOR).floor();
     }
   }
+
+  /// Keeps the `GameBoard` the size it was given whatever the window's, as when the match is
+  /// shared with others.
+  #[derive(Debug, Resource)]
+  pub struct FixedBoard;
 }
@@ -1,6 +1,6 @@
 use super::{
   components::{Board, BoardSprite},
-  resources::GameBoard,
+  resources::{FixedBoard, GameBoard},
   BOARD_COLOR, HALF_CELL_SIZE,
 };
 use bevy::{
@@ -15,9 +15,12 @@ pub(super) fn spawn(
   mut commands: Commands,
   window: Query<&Window, With<PrimaryWindow>>,
   mut game_board: ResMut<GameBoard>,
+  fixed_board: Option<Res<FixedBoard>>,
 ) {
-  let window = window.get_single().unwrap();
-  game_board.resize(window.width(), window.height());
+  // Without a window, as when running headless, the board keeps the size it was given.
+  if let (Ok(window), None) = (window.get_single(), fixed_board) {
+    game_board.resize(window.width(), window.height());
+  }
 
   let board = commands
     .spawn((
@@ -43,13 +46,16 @@ pub(super) fn resize_game_board(
   mut q_board_sprite: Query<&mut Sprite, With<BoardSprite>>,
   mut q_board_position: Query<&mut Transform, With<Board>>,
   mut game_board: ResMut<GameBoard>,
+  fixed_board: Option<Res<FixedBoard>>,
 ) {
   for resize in &mut resize_reader {
     let Ok(mut board_transform) = q_board_position.get_single_mut() else {return};
     let Ok(mut board_sprite) = q_board_sprite.get_single_mut() else {return};
     let Some(ref mut board_sprite) = board_sprite.custom_size else {return};
     board_transform.translation.x = resize.width * 0.1;
-    game_board.resize(resize.width, resize.height);
+    if fixed_board.is_none() {
+      game_board.resize(resize.width, resize.height);
+    }
     board_sprite.x = game_board.width;
     board_sprite.y = game_board.height;
   }
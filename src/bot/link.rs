@@ -0,0 +1,101 @@
+use std::{
+  io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+  net::TcpStream,
+  process::{Child, Command, Stdio},
+  sync::{
+    mpsc::{self, Receiver, Sender},
+    Mutex,
+  },
+  thread,
+  time::Duration,
+};
+
+/// Line based connection to a bot running in another process.
+pub struct BotLink {
+  requests: Mutex<Sender<String>>,
+  replies: Mutex<Receiver<String>>,
+  process: Option<Mutex<Child>>,
+}
+
+impl BotLink {
+  /// Launches a bot program with its arguments, talking to it over its stdin and stdout.
+  pub fn launch(program: &str, args: &[String]) -> io::Result<Self> {
+    let mut child = Command::new(program)
+      .args(args)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .spawn()?;
+    let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+      return Err(io::Error::new(ErrorKind::BrokenPipe, "bot has no stdio"));
+    };
+    Ok(Self::new(Box::new(stdin), stdout, Some(child)))
+  }
+
+  /// Connects to a bot listening on a local socket.
+  pub fn connect(address: &str) -> io::Result<Self> {
+    let stream = TcpStream::connect(address)?;
+    stream.set_nodelay(true)?;
+    let reader = stream.try_clone()?;
+    Ok(Self::new(Box::new(stream), reader, None))
+  }
+
+  fn new<R: Read + Send + 'static>(
+    writer: Box<dyn Write + Send>,
+    reader: R,
+    process: Option<Child>,
+  ) -> Self {
+    let (requests, outbox) = mpsc::channel::<String>();
+    // Writing can block on a bot that doesn't read, it's kept off the game's thread.
+    thread::spawn(move || {
+      let mut writer = writer;
+      for request in outbox {
+        if writeln!(writer, "{request}")
+          .and_then(|_| writer.flush())
+          .is_err()
+        {
+          break;
+        }
+      }
+    });
+    let (sender, replies) = mpsc::channel();
+    thread::spawn(move || {
+      for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else {break};
+        if sender.send(line).is_err() {
+          break;
+        }
+      }
+    });
+    Self {
+      requests: Mutex::new(requests),
+      replies: Mutex::new(replies),
+      process: process.map(Mutex::new),
+    }
+  }
+
+  /// Sends a request line without waiting for the reply, `false` if the bot is gone.
+  pub fn send(&self, request: &str) -> bool {
+    let Ok(replies) = self.replies.lock() else {return false};
+    // Answers that arrived too late for a previous request are stale by now.
+    while replies.try_recv().is_ok() {}
+    let Ok(requests) = self.requests.lock() else {return false};
+    requests.send(request.to_string()).is_ok()
+  }
+
+  /// Reply line to the last request, if it arrived by now.
+  pub fn poll(&self) -> Option<String> {
+    self.replies.lock().ok()?.try_recv().ok()
+  }
+
+  /// Reply line to the last request, waiting up to `timeout` for it.
+  pub fn wait(&self, timeout: Duration) -> Option<String> {
+    self.replies.lock().ok()?.recv_timeout(timeout).ok()
+  }
+}
+
+impl Drop for BotLink {
+  fn drop(&mut self) {
+    let Some(Ok(mut process)) = self.process.as_ref().map(Mutex::lock) else {return};
+    let _ = process.kill();
+  }
+}
@@ -0,0 +1,139 @@
+pub mod link;
+mod systems;
+
+use crate::{simulation::SimulationSet, snake::SnakeSystem};
+use bevy::prelude::{App, Color, CoreSchedule, IntoSystemAppConfig, IntoSystemConfig, Plugin};
+use std::time::Duration;
+
+pub(super) const BOT_COLOR: Color = Color::rgb(120. / 255., 200. / 255., 1.);
+pub(super) const INITIAL_BOT_LENGTH: usize = 4;
+pub const DEFAULT_BOT_TIMEOUT: Duration = Duration::from_millis(50);
+
+pub struct BotPlugin;
+
+impl Plugin for BotPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .insert_resource(resources::BotSettings::from_args(std::env::args()))
+      .add_startup_system(systems::spawn_bots)
+      .add_system(
+        systems::ask_bots
+          .after(SnakeSystem::Seek)
+          .in_set(SimulationSet::Bot)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      );
+  }
+}
+
+pub mod components {
+  use super::link::BotLink;
+  use bevy::prelude::Component;
+
+  /// Snake steered by an external program, it falls back to seeking without a link.
+  #[derive(Component)]
+  pub struct ExternalBot {
+    /// Position of the bot among the configured endpoints.
+    pub index: usize,
+    pub(super) link: Option<BotLink>,
+    /// Tick by which the reply to the request sent since the last move is taken.
+    pub(super) deadline: Option<u64>,
+  }
+
+  impl ExternalBot {
+    /// Bot seat without a program behind it, it seeks on its own.
+    pub fn unlinked(index: usize) -> Self {
+      Self {
+        index,
+        link: None,
+        deadline: None,
+      }
+    }
+  }
+}
+
+pub mod resources {
+  use super::{link::BotLink, DEFAULT_BOT_TIMEOUT};
+  use bevy::prelude::Resource;
+  use std::{io, time::Duration};
+
+  #[derive(Debug, Clone)]
+  pub enum BotEndpoint {
+    /// Program speaking the protocol over stdio, and its arguments.
+    Process { program: String, args: Vec<String> },
+    /// Address of a bot listening on a local socket.
+    Tcp(String),
+    /// Seat of a bot whose moves come from a replay instead.
+    Replayed,
+  }
+
+  impl BotEndpoint {
+    pub fn connect(&self) -> io::Result<Option<BotLink>> {
+      match self {
+        BotEndpoint::Process { program, args } => BotLink::launch(program, args).map(Some),
+        BotEndpoint::Tcp(address) => BotLink::connect(address).map(Some),
+        BotEndpoint::Replayed => Ok(None),
+      }
+    }
+  }
+
+  #[derive(Debug, Resource)]
+  pub struct BotSettings {
+    pub endpoints: Vec<BotEndpoint>,
+    pub timeout: Duration,
+  }
+
+  impl BotSettings {
+    /// Reads `--bot <program>`, each followed by any `--bot-arg <arg>` it takes, `--bot-tcp
+    /// <address>` and `--bot-timeout <ms>` arguments.
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Self {
+      let mut settings = Self {
+        endpoints: Vec::new(),
+        timeout: DEFAULT_BOT_TIMEOUT,
+      };
+      while let Some(arg) = args.next() {
+        match arg.as_str() {
+          "--bot" => settings
+            .endpoints
+            .extend(args.next().map(|program| BotEndpoint::Process {
+              program,
+              args: Vec::new(),
+            })),
+          "--bot-arg" => {
+            if let (Some(BotEndpoint::Process { args: bot_args, .. }), Some(arg)) =
+              (settings.endpoints.last_mut(), args.next())
+            {
+              bot_args.push(arg);
+            }
+          }
+          "--bot-tcp" => settings.endpoints.extend(args.next().map(BotEndpoint::Tcp)),
+          "--bot-timeout" => {
+            if let Some(ms) = args.next().and_then(|ms| ms.parse().ok()) {
+              settings.timeout = Duration::from_millis(ms);
+            }
+          }
+          _ => {}
+        }
+      }
+      settings
+    }
+  }
+}
+
+pub mod protocol {
+  use crate::{observation::BoardState, snake::components::Direction};
+  use serde::{Deserialize, Serialize};
+
+  /// Sent to a bot every time its snake moves, one JSON object per line.
+  #[derive(Debug, Serialize)]
+  pub struct BotRequest<'a> {
+    /// Id of the bot's own snake among `state.snakes`.
+    pub you: u64,
+    pub state: &'a BoardState,
+  }
+
+  /// Expected back from a bot, one JSON object per line.
+  #[derive(Debug, Deserialize)]
+  pub struct BotReply {
+    pub direction: Direction,
+  }
+}
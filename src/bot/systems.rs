@@ -0,0 +1,132 @@
+use super::{
+  components::ExternalBot,
+  protocol::{BotReply, BotRequest},
+  resources::BotSettings,
+  BOT_COLOR, INITIAL_BOT_LENGTH,
+};
+use crate::{
+  board::{components::Board, resources::GameBoard},
+  enemy::{
+    components::{Personality, RespawnTimer},
+    utils::spawn_enemy,
+  },
+  observation::{snake_id, BoardObserver},
+  simulation::resources::{GameRng, Headless, SimulationClock},
+  snake::{
+    components::{Direction, Reflexes, SnakeConfig, Speed},
+    events::Serpentine,
+  },
+};
+use bevy::{
+  prelude::{warn, Commands, Entity, EventReader, FixedTime, ParamSet, Query, Res, ResMut, With},
+  time::{Timer, TimerMode},
+};
+use rand::Rng;
+use std::time::Duration;
+
+pub(super) fn spawn_bots(
+  mut commands: Commands,
+  q_board: Query<Entity, With<Board>>,
+  game_board: Res<GameBoard>,
+  settings: Res<BotSettings>,
+  mut rng: ResMut<GameRng>,
+) {
+  let Ok(board) = q_board.get_single() else {return};
+  for (i, endpoint) in settings.endpoints.iter().enumerate() {
+    let link = match endpoint.connect() {
+      Ok(link) => link,
+      Err(err) => {
+        warn!("Bot {endpoint:?} is unavailable, it will seek on its own: {err}");
+        None
+      }
+    };
+    let bot = spawn_enemy(
+      (Personality::EATER, Reflexes::default()),
+      SnakeConfig {
+        name: format!("Bot {}", i + 1),
+        x: (rng.gen::<f32>() - 0.5) * game_board.width,
+        y: (rng.gen::<f32>() - 0.5) * game_board.height,
+        color: BOT_COLOR,
+        tail_length: INITIAL_BOT_LENGTH,
+        ..Default::default()
+      },
+      &mut commands,
+      board,
+    );
+    commands.entity(bot).insert((
+      ExternalBot {
+        index: i,
+        link,
+        deadline: None,
+      },
+      RespawnTimer(Timer::new(Duration::ZERO, TimerMode::Once)),
+    ));
+  }
+}
+
+/// Sends the board to every linked bot whose snake just moved, and takes its reply once the
+/// deadline comes, in time for the next move. Nothing waits on the bots in real time, a reply
+/// still missing by then leaves the direction picked by `seek`. Headless runs step far faster
+/// than a bot answers, they rather wait up to the timeout for each reply.
+pub(super) fn ask_bots(
+  mut serpentine_reader: EventReader<Serpentine>,
+  mut set: ParamSet<(
+    Query<(&mut ExternalBot, &mut Direction, &Speed)>,
+    BoardObserver,
+  )>,
+  settings: Res<BotSettings>,
+  clock: Res<SimulationClock>,
+  fixed_time: Res<FixedTime>,
+  headless: Option<Res<Headless>>,
+) {
+  let moved = serpentine_reader
+    .iter()
+    .map(|Serpentine(snake, _)| *snake)
+    .filter(|snake| set.p0().contains(*snake))
+    .collect::<Vec<_>>();
+  if !moved.is_empty() {
+    let state = set.p1().state();
+    let period = fixed_time.period.as_nanos().max(1);
+    let ticks = |duration: Duration| ((duration.as_nanos() + period - 1) / period) as u64;
+    let mut q_bot = set.p0();
+    for snake in moved {
+      let Ok((mut bot, _, speed)) = q_bot.get_mut(snake) else {continue};
+      let Some(link) = &bot.link else {continue};
+      let request = BotRequest {
+        you: snake_id(snake),
+        state: &state,
+      };
+      let Ok(request) = serde_json::to_string(&request) else {continue};
+      let sent = link.send(&request);
+      let wait = if headless.is_some() {
+        0
+      } else {
+        // The reply has to be set the tick before the move at the latest.
+        let next_move = ticks(speed.remaining()).saturating_sub(1);
+        ticks(settings.timeout).min(next_move)
+      };
+      bot.deadline = sent.then_some(clock.tick + wait);
+    }
+  }
+
+  for (mut bot, mut direction, _) in &mut set.p0() {
+    if bot.deadline.map_or(true, |deadline| clock.tick < deadline) {
+      continue;
+    }
+    bot.deadline = None;
+    let Some(link) = &bot.link else {continue};
+    let reply = if headless.is_some() {
+      link.wait(settings.timeout)
+    } else {
+      link.poll()
+    };
+    let Some(reply) = reply else {continue};
+    let Ok(BotReply { direction: next }) = serde_json::from_str(&reply) else {
+      warn!("Ignoring malformed bot reply: {reply}");
+      continue;
+    };
+    if next != direction.opposite() {
+      *direction = next;
+    }
+  }
+}
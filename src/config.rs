@@ -0,0 +1,52 @@
+use bevy::prelude::warn;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+  fs,
+  io::{self, ErrorKind},
+  path::Path,
+};
+
+pub const CONFIG_DIR: &str = "assets/config";
+
+/// Reads a JSON config file, returning `None` when it is missing or malformed.
+pub fn load<T: DeserializeOwned>(path: impl AsRef<Path>) -> Option<T> {
+  let path = path.as_ref();
+  let contents = fs::read_to_string(path).ok()?;
+  match serde_json::from_str(&contents) {
+    Ok(config) => Some(config),
+    Err(err) => {
+      warn!("Ignoring invalid config {}: {err}", path.display());
+      None
+    }
+  }
+}
+
+/// Reads a JSON file carrying a `version`, refusing any other version than the given one.
+pub fn load_versioned<T: DeserializeOwned>(
+  path: impl AsRef<Path>,
+  version: u32,
+) -> Result<T, String> {
+  let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+  let value =
+    serde_json::from_str::<serde_json::Value>(&contents).map_err(|err| err.to_string())?;
+  // Checked first, other versions may not even parse.
+  let found = value.get("version").and_then(serde_json::Value::as_u64);
+  if found != Some(version as u64) {
+    return Err(format!(
+      "unsupported version {}, expected {version}",
+      found.map_or("none".to_string(), |found| found.to_string())
+    ));
+  }
+  serde_json::from_value(value).map_err(|err| err.to_string())
+}
+
+/// Writes a value as JSON, creating the directories on the way.
+pub fn save<T: Serialize>(path: impl AsRef<Path>, value: &T) -> io::Result<()> {
+  let path = path.as_ref();
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  let json =
+    serde_json::to_string(value).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+  fs::write(path, json)
+}
@@ -0,0 +1,148 @@
+pub mod protocol;
+mod systems;
+
+use crate::simulation::SimulationSet;
+use bevy::prelude::{
+  apply_system_buffers, info, resource_exists, warn, App, CoreSchedule, IntoSystemAppConfigs,
+  IntoSystemConfig, IntoSystemConfigs, Plugin,
+};
+
+pub const DEFAULT_CONTROL_ADDRESS: &str = "127.0.0.1:4200";
+/// Bytes a request may take, longer ones drop the client.
+pub const MAX_REQUEST_LENGTH: usize = 1 << 16;
+
+/// Answers requests from clients connecting over TCP to `--control [address]`, the god mode keys
+/// for scripts and tools.
+pub struct ControlPlugin;
+
+impl Plugin for ControlPlugin {
+  fn build(&self, app: &mut App) {
+    let settings = resources::ControlSettings::from_args(std::env::args());
+    if let Some(address) = &settings.address {
+      match resources::ControlServer::bind(address) {
+        Ok(server) => {
+          info!("Taking requests on {address}");
+          app.insert_resource(server);
+        }
+        Err(err) => warn!("Couldn't take requests on {address}: {err}"),
+      }
+    }
+
+    let listening = resource_exists::<resources::ControlServer>;
+    app
+      .insert_resource(settings)
+      .add_system(systems::accept.run_if(listening()))
+      .add_systems(
+        (
+          systems::respond.run_if(listening()),
+          // Killed snakes are out of the tick they were killed in.
+          apply_system_buffers,
+        )
+          .chain()
+          .in_set(SimulationSet::Replay)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      );
+  }
+}
+
+pub mod resources {
+  use super::DEFAULT_CONTROL_ADDRESS;
+  use bevy::prelude::Resource;
+  use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+  };
+
+  #[derive(Debug, Resource)]
+  pub struct ControlSettings {
+    /// Address to take requests on, none are taken without one.
+    pub address: Option<String>,
+  }
+
+  impl ControlSettings {
+    /// Reads a `--control [address]` argument, without an address requests are taken on
+    /// `DEFAULT_CONTROL_ADDRESS`.
+    pub fn from_args<I: Iterator<Item = String>>(args: I) -> Self {
+      let mut settings = Self { address: None };
+      let mut args = args.peekable();
+      while let Some(arg) = args.next() {
+        if arg == "--control" {
+          let address = args.next_if(|address| !address.starts_with("--"));
+          settings.address = Some(address.unwrap_or_else(|| DEFAULT_CONTROL_ADDRESS.to_string()));
+        }
+      }
+      settings
+    }
+  }
+
+  #[derive(Debug)]
+  pub struct ControlClient {
+    pub address: SocketAddr,
+    pub(super) stream: TcpStream,
+    /// Bytes read past the last complete request.
+    pub(super) received: Vec<u8>,
+    /// Bytes not written yet.
+    pub(super) backlog: Vec<u8>,
+  }
+
+  impl ControlClient {
+    /// Reads whatever the connection has without blocking, `false` once it's closed.
+    pub(super) fn receive(&mut self) -> io::Result<bool> {
+      let mut buffer = [0; 4096];
+      loop {
+        match self.stream.read(&mut buffer) {
+          Ok(0) => return Ok(false),
+          Ok(read) => self.received.extend(&buffer[..read]),
+          Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(true),
+          Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+          Err(err) => return Err(err),
+        }
+      }
+    }
+
+    /// Takes the next complete line out of what was received.
+    pub(super) fn next_line(&mut self) -> Option<Vec<u8>> {
+      let end = self.received.iter().position(|byte| *byte == b'\n')?;
+      let mut line = self.received.drain(..=end).collect::<Vec<_>>();
+      line.pop();
+      Some(line)
+    }
+
+    /// Writes as much of the backlog as the connection takes without blocking.
+    pub(super) fn flush(&mut self) -> io::Result<()> {
+      while !self.backlog.is_empty() {
+        match self.stream.write(&self.backlog) {
+          Ok(0) => return Err(ErrorKind::WriteZero.into()),
+          Ok(written) => {
+            self.backlog.drain(..written);
+          }
+          Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+          Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+          Err(err) => return Err(err),
+        }
+      }
+      Ok(())
+    }
+  }
+
+  #[derive(Debug, Resource)]
+  pub struct ControlServer {
+    pub clients: Vec<ControlClient>,
+    pub(super) listener: TcpListener,
+  }
+
+  impl ControlServer {
+    pub fn bind(address: &str) -> io::Result<Self> {
+      let listener = TcpListener::bind(address)?;
+      listener.set_nonblocking(true)?;
+      Ok(Self {
+        clients: Vec::new(),
+        listener,
+      })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+      self.listener.local_addr()
+    }
+  }
+}
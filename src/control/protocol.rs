@@ -0,0 +1,58 @@
+use crate::{food::components::Food, observation::BoardState};
+use serde::{Deserialize, Serialize};
+
+/// Sent by a client, one JSON object per line, each answered by a `ControlResponse`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlRequest {
+  /// Asks for the snakes, their scores and positions, and the food.
+  State,
+  Pause,
+  Resume,
+  SpawnFood {
+    food: Food,
+    /// Column and row, wrapping around the edges.
+    cell: [i32; 2],
+  },
+  SpawnEnemy {
+    /// Personality name, built-in or from config.
+    kind: String,
+    /// `None` picks a random snake name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+  },
+  Kill {
+    snake: u64,
+  },
+  Grow {
+    /// The first player when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snake: Option<u64>,
+  },
+  Shrink {
+    /// The first player when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snake: Option<u64>,
+  },
+  RespawnPlayer {
+    /// Seat of the local player, the first one by default.
+    #[serde(default)]
+    player: usize,
+  },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlResponse {
+  State {
+    tick: u64,
+    paused: bool,
+    #[serde(flatten)]
+    board: BoardState,
+  },
+  /// The request was carried out, its effects show from this tick on.
+  Ok,
+  Error {
+    message: String,
+  },
+}
@@ -0,0 +1,198 @@
+use super::{
+  protocol::{ControlRequest, ControlResponse},
+  resources::{ControlClient, ControlServer},
+  MAX_REQUEST_LENGTH,
+};
+use crate::{
+  enemy::{
+    events::SpawnEnemy,
+    resources::{Personalities, RosterEntry},
+  },
+  food::events::SpawnFood,
+  observation::BoardObserver,
+  player::{components::Player, events::RespawnPlayer},
+  replay::resources::{Playback, Recorder},
+  simulation::resources::SimulationClock,
+  snake::{
+    components::{Living, Snake},
+    events::{BodySizeChange, SnakeDied, SnakeSizeChange},
+  },
+  state::GameState,
+};
+use bevy::{
+  ecs::system::SystemParam,
+  prelude::{
+    info, warn, Commands, Entity, EventWriter, IVec2, NextState, Query, Res, ResMut, State, With,
+  },
+};
+use std::io::ErrorKind;
+
+/// Everything a request may look at or change.
+#[derive(SystemParam)]
+pub(super) struct Control<'w, 's> {
+  commands: Commands<'w, 's>,
+  spawn_food_writer: EventWriter<'w, SpawnFood>,
+  spawn_enemy_writer: EventWriter<'w, SpawnEnemy>,
+  size_change_writer: EventWriter<'w, SnakeSizeChange>,
+  respawn_player_writer: EventWriter<'w, RespawnPlayer>,
+  snake_died_writer: EventWriter<'w, SnakeDied>,
+  q_player: Query<'w, 's, (Entity, &'static Player)>,
+  q_living: Query<'w, 's, (), (With<Snake>, With<Living>)>,
+  observer: BoardObserver<'w, 's>,
+  personalities: Res<'w, Personalities>,
+  game_state: Res<'w, State<GameState>>,
+  next_state: ResMut<'w, NextState<GameState>>,
+  clock: Res<'w, SimulationClock>,
+  playback: Option<Res<'w, Playback>>,
+  recorder: Option<Res<'w, Recorder>>,
+}
+
+impl<'w, 's> Control<'w, 's> {
+  fn handle(&mut self, request: ControlRequest) -> Result<ControlResponse, String> {
+    if self.playback.is_some() && !matches!(request, ControlRequest::State) {
+      return Err("a replay is being played back".to_string());
+    }
+    // Recordings only hold the inputs, changes made to the match wouldn't play back.
+    let changes_match = !matches!(
+      request,
+      ControlRequest::State | ControlRequest::Pause | ControlRequest::Resume
+    );
+    if self.recorder.is_some() && changes_match {
+      return Err("the match is being recorded".to_string());
+    }
+    match request {
+      ControlRequest::State => {
+        return Ok(ControlResponse::State {
+          tick: self.clock.tick,
+          paused: self.game_state.0 == GameState::Paused,
+          board: self.observer.state(),
+        })
+      }
+      ControlRequest::Pause => self.next_state.set(GameState::Paused),
+      ControlRequest::Resume => self.next_state.set(GameState::Playing),
+      ControlRequest::SpawnFood { food, cell: [x, y] } => self
+        .spawn_food_writer
+        .send(SpawnFood(food, Some(IVec2::new(x, y)))),
+      ControlRequest::SpawnEnemy { kind, name } => {
+        if !self.personalities.contains_key(&kind) {
+          return Err(format!("unknown enemy kind {kind}"));
+        }
+        self.spawn_enemy_writer.send(SpawnEnemy {
+          entry: RosterEntry {
+            name,
+            ..RosterEntry::of_kind(&kind)
+          },
+          wave: None,
+        });
+      }
+      ControlRequest::Kill { snake } => {
+        let snake = self.living(Some(snake))?;
+        self.commands.entity(snake).remove::<Living>();
+        self.snake_died_writer.send(SnakeDied {
+          snake,
+          killer: None,
+        });
+      }
+      ControlRequest::Grow { snake } => {
+        let snake = self.living(snake)?;
+        self.size_change_writer.send((snake, BodySizeChange::Grow));
+      }
+      ControlRequest::Shrink { snake } => {
+        let snake = self.living(snake)?;
+        self
+          .size_change_writer
+          .send((snake, BodySizeChange::Shrink));
+      }
+      ControlRequest::RespawnPlayer { player } => {
+        self.player(player)?;
+        self.respawn_player_writer.send(RespawnPlayer(player));
+      }
+    }
+    Ok(ControlResponse::Ok)
+  }
+
+  /// Living snake of the given id, or the first player without one.
+  fn living(&self, snake: Option<u64>) -> Result<Entity, String> {
+    let entity = match snake {
+      Some(snake) => Entity::from_bits(snake),
+      None => self.player(0)?,
+    };
+    if self.q_living.contains(entity) {
+      Ok(entity)
+    } else {
+      Err(match snake {
+        Some(snake) => format!("no living snake {snake}"),
+        None => "the player is dead".to_string(),
+      })
+    }
+  }
+
+  fn player(&self, seat: usize) -> Result<Entity, String> {
+    self
+      .q_player
+      .iter()
+      .find(|(_, player)| player.0 == seat)
+      .map(|(entity, _)| entity)
+      .ok_or_else(|| format!("there's no player {seat}"))
+  }
+}
+
+pub(super) fn accept(mut server: ResMut<ControlServer>) {
+  loop {
+    let (stream, address) = match server.listener.accept() {
+      Ok(accepted) => accepted,
+      Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+      Err(err) => {
+        warn!("Couldn't accept a client: {err}");
+        break;
+      }
+    };
+    if let Err(err) = stream.set_nonblocking(true) {
+      warn!("Couldn't take requests from {address}: {err}");
+      continue;
+    }
+    // Scripts wait for each answer before the next request.
+    let _ = stream.set_nodelay(true);
+    info!("Client {address} connected");
+    server.clients.push(ControlClient {
+      address,
+      stream,
+      received: Vec::new(),
+      backlog: Vec::new(),
+    });
+  }
+}
+
+/// Carries out the requests received since the last tick, as part of this one.
+pub(super) fn respond(mut server: ResMut<ControlServer>, mut control: Control) {
+  server.clients.retain_mut(|client| {
+    let open = match client.receive() {
+      Ok(open) => open,
+      Err(err) => {
+        info!("Client {} disconnected: {err}", client.address);
+        return false;
+      }
+    };
+    while let Some(line) = client.next_line() {
+      let response = serde_json::from_slice::<ControlRequest>(&line)
+        .map_err(|err| format!("invalid request: {err}"))
+        .and_then(|request| control.handle(request))
+        .unwrap_or_else(|message| ControlResponse::Error { message });
+      let Ok(response) = serde_json::to_vec(&response) else {continue};
+      client.backlog.extend(response);
+      client.backlog.push(b'\n');
+    }
+    if client.received.len() > MAX_REQUEST_LENGTH {
+      info!("Client {} sent too long a request", client.address);
+      return false;
+    }
+    if let Err(err) = client.flush() {
+      info!("Client {} disconnected: {err}", client.address);
+      return false;
+    }
+    if !open {
+      info!("Client {} disconnected", client.address);
+    }
+    open
+  });
+}
@@ -1,14 +1,24 @@
 mod systems;
 
-use bevy::prelude::{App, Plugin};
+use crate::replay::resources::{Playback, Recorder};
+use bevy::{
+  ecs::schedule::common_conditions::not,
+  prelude::{resource_exists, App, IntoSystemConfig, Plugin},
+};
 
 pub struct DebugPlugin;
 
 impl Plugin for DebugPlugin {
   fn build(&self, app: &mut App) {
+    // A replay plays out as recorded, its keys belong to the playback controls.
+    let live = || not(resource_exists::<Playback>());
+    // Resizing the player isn't among the inputs a replay keeps.
+    let unrecorded = || not(resource_exists::<Recorder>());
     app
-      .add_system(systems::god_mode)
-      .add_system(systems::print_debug_info)
-      .add_system(systems::move_board);
+      .add_system(systems::god_mode.run_if(live()))
+      .add_system(systems::resize_player.run_if(live()).run_if(unrecorded()))
+      .add_system(systems::tune_enemies.run_if(live()))
+      .add_system(systems::print_debug_info.run_if(live()))
+      .add_system(systems::move_board.run_if(live()));
   }
 }
@@ -1,35 +1,25 @@
 use crate::{
+  bindings::resources::{Action, Actions},
   board::components::Board,
-  player::{components::Player, events::RespawnPlayer},
-  scoreboard::components::Name,
+  difficulty::resources::{Difficulty, DynamicDifficulty},
+  enemy::{components::Target, resources::SquadCoordinator},
+  player::components::Player,
+  scoreboard::components::{Name, ScoreEntity},
   snake::{
     components::Snake,
     events::{BodySizeChange, SnakeSizeChange},
   },
   state::GameState,
 };
-use bevy::prelude::{
-  Entity, EventWriter, Input, KeyCode, NextState, Query, Res, ResMut, State, Transform, With,
-};
+use bevy::prelude::{Entity, EventWriter, NextState, Query, Res, ResMut, State, Transform, With};
 
+/// Players respawn with their own bindings.
 pub(super) fn god_mode(
-  mut respawn_player_writer: EventWriter<RespawnPlayer>,
-  mut size_change_writer: EventWriter<SnakeSizeChange>,
-  q_player: Query<Entity, With<Player>>,
-  keyboard_input: Res<Input<KeyCode>>,
+  actions: Res<Actions>,
   game_state: Res<State<GameState>>,
   mut next_state: ResMut<NextState<GameState>>,
 ) {
-  use BodySizeChange::*;
-  if keyboard_input.just_pressed(KeyCode::E) {
-    let Ok(player) = q_player.get_single() else { return; };
-    size_change_writer.send((player, Grow));
-  } else if keyboard_input.just_pressed(KeyCode::Q) {
-    let Ok(player) = q_player.get_single() else { return; };
-    size_change_writer.send((player, Shrink));
-  } else if keyboard_input.just_pressed(KeyCode::R) {
-    respawn_player_writer.send(RespawnPlayer);
-  } else if keyboard_input.just_pressed(KeyCode::P) {
+  if actions.just_pressed(None, Action::Pause) {
     next_state.set(if game_state.0 == GameState::Paused {
       GameState::Playing
     } else {
@@ -38,32 +28,74 @@ pub(super) fn god_mode(
   }
 }
 
-pub(super) fn move_board(
-  mut q_board: Query<&mut Transform, With<Board>>,
-  keyboard_input: Res<Input<KeyCode>>,
+/// Acts on the first local player.
+pub(super) fn resize_player(
+  mut size_change_writer: EventWriter<SnakeSizeChange>,
+  q_player: Query<(Entity, &Player)>,
+  actions: Res<Actions>,
+) {
+  use BodySizeChange::*;
+  let Some((player, _)) = q_player.iter().find(|(_, player)| player.0 == 0) else {return};
+  if actions.just_pressed(None, Action::Grow) {
+    size_change_writer.send((player, Grow));
+  } else if actions.just_pressed(None, Action::Shrink) {
+    size_change_writer.send((player, Shrink));
+  }
+}
+
+pub(super) fn tune_enemies(
+  actions: Res<Actions>,
+  mut squad_coordinator: ResMut<SquadCoordinator>,
+  mut difficulty: ResMut<Difficulty>,
+  mut dynamic_difficulty: ResMut<DynamicDifficulty>,
 ) {
+  if actions.just_pressed(None, Action::ToggleSquad) {
+    squad_coordinator.enabled = !squad_coordinator.enabled;
+  } else if actions.just_pressed(None, Action::EasyDifficulty) {
+    *difficulty = Difficulty::Easy;
+  } else if actions.just_pressed(None, Action::NormalDifficulty) {
+    *difficulty = Difficulty::Normal;
+  } else if actions.just_pressed(None, Action::HardDifficulty) {
+    *difficulty = Difficulty::Hard;
+  } else if actions.just_pressed(None, Action::InsaneDifficulty) {
+    *difficulty = Difficulty::Insane;
+  } else if actions.just_pressed(None, Action::ToggleDynamicDifficulty) {
+    dynamic_difficulty.enabled = !dynamic_difficulty.enabled;
+  }
+}
+
+pub(super) fn move_board(mut q_board: Query<&mut Transform, With<Board>>, actions: Res<Actions>) {
   let Ok(mut board) = q_board.get_single_mut() else {return};
-  if keyboard_input.pressed(KeyCode::Left) {
+  if actions.pressed(None, Action::MoveBoardLeft) {
     board.translation.x -= 1.;
-  } else if keyboard_input.pressed(KeyCode::Right) {
+  } else if actions.pressed(None, Action::MoveBoardRight) {
     board.translation.x += 1.;
-  } else if keyboard_input.pressed(KeyCode::Up) {
+  } else if actions.pressed(None, Action::MoveBoardUp) {
     board.translation.y += 1.;
-  } else if keyboard_input.pressed(KeyCode::Down) {
+  } else if actions.pressed(None, Action::MoveBoardDown) {
     board.translation.y -= 1.;
   }
 }
 
 pub(super) fn print_debug_info(
-  keyboard_input: Res<Input<KeyCode>>,
+  actions: Res<Actions>,
   q_entity: Query<Entity>,
   q_snake: Query<&Name, With<Snake>>,
+  q_enemy_target: Query<(&ScoreEntity, &Target)>,
+  q_score: Query<&Name>,
 ) {
-  if keyboard_input.just_pressed(KeyCode::O) {
+  if actions.just_pressed(None, Action::DebugInfo) {
     let debug = [
       "=== === === DEBUG === === ===",
       &format!("Entity Count: {}", q_entity.iter().count()),
       &format!("Snakes: {:#?}", q_snake.iter().collect::<Vec<_>>()),
+      &format!(
+        "Enemy Targets: {:#?}",
+        q_enemy_target
+          .iter()
+          .filter_map(|(score, target)| Some((&q_score.get(score.0).ok()?.0, target.goal)))
+          .collect::<Vec<_>>()
+      ),
     ]
     .join("\n");
     println!("{debug}");
@@ -0,0 +1,127 @@
+mod systems;
+
+use crate::simulation::SimulationSet;
+use bevy::prelude::{
+  apply_system_buffers, App, CoreSchedule, IntoSystemAppConfigs, IntoSystemConfigs, Plugin,
+};
+use std::time::Duration;
+
+/// How long a player's death keeps easing the dynamic difficulty.
+pub const DEATH_MEMORY: Duration = Duration::from_secs(60);
+/// Score above the starting length that pushes the dynamic difficulty to its maximum.
+pub const SCORE_FOR_MAX_PRESSURE: f32 = 20.;
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<resources::Difficulty>()
+      .init_resource::<resources::DynamicDifficulty>()
+      .add_systems(
+        (
+          // Enemies spawned earlier in the tick count towards the balance.
+          apply_system_buffers,
+          systems::track_player_deaths,
+          systems::adjust_dynamic_difficulty,
+          systems::apply_reflexes,
+          systems::balance_enemies,
+        )
+          .chain()
+          .in_set(SimulationSet::Difficulty)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      );
+  }
+}
+
+pub mod resources {
+  use crate::snake::components::Reflexes;
+  use bevy::prelude::Resource;
+  use serde::{Deserialize, Serialize};
+  use std::{collections::VecDeque, time::Duration};
+
+  #[derive(Debug, Resource, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+  #[serde(rename_all = "snake_case")]
+  pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Insane,
+  }
+
+  #[derive(Debug, Clone, Copy)]
+  pub struct DifficultySettings {
+    pub enemy_count: usize,
+    pub planning_depth: usize,
+    pub reaction_delay: u32,
+    pub mistake_probability: f32,
+  }
+
+  impl Difficulty {
+    pub fn settings(&self) -> DifficultySettings {
+      match self {
+        Difficulty::Easy => DifficultySettings {
+          enemy_count: 3,
+          planning_depth: 0,
+          reaction_delay: 4,
+          mistake_probability: 0.05,
+        },
+        Difficulty::Normal => DifficultySettings {
+          enemy_count: 5,
+          planning_depth: 8,
+          reaction_delay: 2,
+          mistake_probability: 0.02,
+        },
+        Difficulty::Hard => DifficultySettings {
+          enemy_count: 7,
+          planning_depth: 24,
+          reaction_delay: 1,
+          mistake_probability: 0.005,
+        },
+        Difficulty::Insane => DifficultySettings {
+          enemy_count: 10,
+          planning_depth: 64,
+          reaction_delay: 0,
+          mistake_probability: 0.,
+        },
+      }
+    }
+
+    pub fn reflexes(&self, dynamic: &DynamicDifficulty) -> Reflexes {
+      let settings = self.settings();
+      Reflexes {
+        planning_depth: settings.planning_depth,
+        reaction_delay: settings.reaction_delay,
+        mistake_probability: settings.mistake_probability,
+        aggressiveness: dynamic.aggressiveness,
+      }
+    }
+
+    /// Enemies to keep on the board, `base` overrides the preset's count.
+    pub fn enemy_count(&self, dynamic: &DynamicDifficulty, base: Option<usize>) -> usize {
+      let base = base.unwrap_or(self.settings().enemy_count);
+      (base as i32 + dynamic.extra_enemies).max(0) as usize
+    }
+  }
+
+  /// Scales enemies with how well the player is doing when enabled.
+  #[derive(Debug, Resource, Clone, Serialize, Deserialize)]
+  pub struct DynamicDifficulty {
+    pub enabled: bool,
+    pub aggressiveness: f32,
+    pub extra_enemies: i32,
+    pub(super) deaths: VecDeque<Duration>,
+  }
+
+  impl Default for DynamicDifficulty {
+    fn default() -> Self {
+      Self {
+        enabled: false,
+        aggressiveness: 1.,
+        extra_enemies: 0,
+        deaths: VecDeque::new(),
+      }
+    }
+  }
+}
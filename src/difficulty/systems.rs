@@ -0,0 +1,120 @@
+use super::{
+  resources::{Difficulty, DynamicDifficulty},
+  DEATH_MEMORY, SCORE_FOR_MAX_PRESSURE,
+};
+use crate::{
+  bot::components::ExternalBot,
+  enemy::{
+    components::{Enemy, Personality, Retired, WaveEnemy},
+    events::SpawnEnemy,
+    resources::Roster,
+  },
+  player::{components::Player, INITIAL_PLAYER_LENGTH},
+  scoreboard::components::{Score, ScoreEntity},
+  simulation::resources::SimulationClock,
+  snake::{components::Reflexes, events::SnakeDied},
+};
+use bevy::prelude::{
+  Commands, DetectChanges, Entity, EventReader, EventWriter, Query, Res, ResMut, With, Without,
+};
+
+pub(super) fn track_player_deaths(
+  mut snake_died_reader: EventReader<SnakeDied>,
+  mut dynamic: ResMut<DynamicDifficulty>,
+  q_player: Query<(), With<Player>>,
+  clock: Res<SimulationClock>,
+) {
+  for SnakeDied { snake, .. } in snake_died_reader.iter() {
+    if q_player.contains(*snake) {
+      dynamic.deaths.push_back(clock.elapsed);
+    }
+  }
+  while let Some(death) = dynamic.deaths.front() {
+    if clock.elapsed - *death < DEATH_MEMORY {
+      break;
+    }
+    dynamic.deaths.pop_front();
+  }
+}
+
+pub(super) fn adjust_dynamic_difficulty(
+  mut dynamic: ResMut<DynamicDifficulty>,
+  q_player: Query<&ScoreEntity, With<Player>>,
+  q_scores: Query<&Score>,
+) {
+  let (aggressiveness, extra_enemies) = if dynamic.enabled {
+    let score = q_player
+      .iter()
+      .filter_map(|score| q_scores.get(score.0).ok())
+      .map(|score| score.0)
+      .max()
+      .unwrap_or(INITIAL_PLAYER_LENGTH);
+    let pressure = (score.saturating_sub(INITIAL_PLAYER_LENGTH) as f32 / SCORE_FOR_MAX_PRESSURE
+      - dynamic.deaths.len() as f32 * 0.5)
+      .clamp(-1., 1.);
+    (
+      ((1. + pressure / 2.) * 10.).round() / 10.,
+      (pressure * 3.).round() as i32,
+    )
+  } else {
+    (1., 0)
+  };
+  if dynamic.aggressiveness != aggressiveness || dynamic.extra_enemies != extra_enemies {
+    dynamic.aggressiveness = aggressiveness;
+    dynamic.extra_enemies = extra_enemies;
+  }
+}
+
+pub(super) fn apply_reflexes(
+  mut q_enemy: Query<(&mut Reflexes, &Personality), With<Enemy>>,
+  difficulty: Res<Difficulty>,
+  dynamic: Res<DynamicDifficulty>,
+) {
+  if difficulty.is_changed() || dynamic.is_changed() {
+    let reflexes = difficulty.reflexes(&dynamic);
+    for (mut enemy_reflexes, personality) in &mut q_enemy {
+      *enemy_reflexes = personality.reflexes(reflexes);
+    }
+  }
+}
+
+pub(super) fn balance_enemies(
+  mut commands: Commands,
+  mut spawn_enemy_writer: EventWriter<SpawnEnemy>,
+  q_enemy: Query<
+    (Entity, Option<&Retired>),
+    (With<Enemy>, Without<WaveEnemy>, Without<ExternalBot>),
+  >,
+  difficulty: Res<Difficulty>,
+  dynamic: Res<DynamicDifficulty>,
+  roster: Res<Roster>,
+) {
+  if !difficulty.is_changed() && !dynamic.is_changed() && !roster.is_changed() {
+    return;
+  }
+  let mut enemies = q_enemy.iter().collect::<Vec<_>>();
+  enemies.sort_by_key(|(enemy, _)| *enemy);
+  let (retired, active): (Vec<_>, Vec<_>) = enemies
+    .into_iter()
+    .partition(|(_, retired)| retired.is_some());
+  let wanted = difficulty.enemy_count(&dynamic, roster.configured.then(|| roster.size()));
+  if active.len() < wanted {
+    // Enemies still dying out are called back before any new one is spawned.
+    let recalled = retired.len().min(wanted - active.len());
+    for (enemy, _) in retired.into_iter().take(recalled) {
+      commands.entity(enemy).remove::<Retired>();
+    }
+    let count = active.len() + recalled;
+    for entry in roster.lineup().skip(count).take(wanted - count) {
+      spawn_enemy_writer.send(SpawnEnemy {
+        entry: entry.clone(),
+        wave: None,
+      });
+    }
+  } else {
+    // Removing a snake mid-move is jarring, the extra ones rather don't come back once dead.
+    for (enemy, _) in active.into_iter().skip(wanted) {
+      commands.entity(enemy).insert(Retired);
+    }
+  }
+}
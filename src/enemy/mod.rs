@@ -1,29 +1,65 @@
 mod systems;
+pub mod utils;
 
-use bevy::prelude::{App, Color, Plugin};
+use crate::simulation::{AddSimulationEvent, SimulationSet};
+use bevy::prelude::{
+  App, Color, CoreSchedule, IntoSystemAppConfigs, IntoSystemConfig, IntoSystemConfigs, Plugin,
+  StartupSet,
+};
 
 pub(super) const INITIAL_ENEMY_LENGTH: usize = 4;
 pub(super) const EATER_COLOR: Color = Color::rgb(1., 1., 1.);
 pub(super) const KILLER_COLOR: Color = Color::rgb(202. / 255., 98. / 255., 157. / 255.);
 pub(super) const SPEEDSTER_COLOR: Color = Color::rgb(99. / 255., 250. / 255., 250. / 255.);
 pub(super) const GLUTTON_COLOR: Color = Color::rgb(254. / 255., 165. / 255., 1. / 255.);
+pub(super) const HUNTER_COLOR: Color = Color::rgb(240. / 255., 70. / 255., 60. / 255.);
+pub(super) const CUSTOM_COLOR: Color = Color::rgb(160. / 255., 160. / 255., 220. / 255.);
+
+pub const PERSONALITIES_FILE: &str = "personalities.json";
+pub const ROSTER_FILE: &str = "roster.json";
+/// How many moves ahead a `Hunter` projects its prey.
+pub const INTERCEPT_LOOKAHEAD: i32 = 8;
+/// Cells to either side of the prey's path flankers aim for.
+pub const FLANK_OFFSET: i32 = 3;
 
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
   fn build(&self, app: &mut App) {
     app
-      .add_startup_system(systems::spawn_enemies)
-      .add_system(systems::respawn)
-      .add_system(systems::seek_food)
-      .add_system(systems::seek_snake)
-      .add_system(systems::seek_speed)
-      .add_system(systems::seek_nourishment);
+      .init_resource::<resources::Personalities>()
+      .init_resource::<resources::SquadCoordinator>()
+      .init_resource::<resources::Roster>()
+      .init_resource::<resources::Waves>()
+      .add_startup_system(systems::load_personalities.in_base_set(StartupSet::PreStartup))
+      .add_startup_system(
+        systems::load_roster
+          .in_base_set(StartupSet::PreStartup)
+          .after(systems::load_personalities),
+      )
+      .add_simulation_event::<events::SpawnEnemy>()
+      .add_systems(
+        (
+          systems::spawn,
+          systems::respawn,
+          systems::run_waves,
+          systems::clear_fallen_waves,
+          systems::seek_goals,
+          systems::intercept,
+          systems::coordinate_squad,
+          systems::apply_squad_roles,
+        )
+          .chain()
+          .in_set(SimulationSet::Enemy)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      );
   }
 }
 
 pub mod components {
-  use bevy::prelude::Component;
+  use super::utils::Goal;
+  use bevy::{prelude::Component, time::Timer};
+  use serde::{Deserialize, Serialize};
 
   #[derive(Component)]
   pub struct Enemy;
@@ -39,4 +75,359 @@ pub mod components {
 
   #[derive(Component)]
   pub struct Glutton;
+
+  /// Aims for where its prey is heading rather than where it is.
+  #[derive(Component)]
+  pub struct Hunter;
+
+  /// Plays a personality defined in config rather than a built-in archetype.
+  #[derive(Component)]
+  pub struct Custom;
+
+  /// Left to die out since the difficulty lowered the enemy count, it doesn't respawn.
+  #[derive(Component)]
+  pub struct Retired;
+
+  /// Time a dead enemy waits, hidden, before coming back.
+  #[derive(Debug, Component)]
+  pub struct RespawnTimer(pub Timer);
+
+  /// Enemy added by the given wave, it doesn't respawn.
+  #[derive(Debug, Component)]
+  pub struct WaveEnemy(pub u32);
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+  #[serde(rename_all = "snake_case")]
+  pub enum SquadRole {
+    /// Goes straight for the squad's prey.
+    Chaser,
+    /// Runs alongside the prey's path, clockwise or counter clockwise from its heading.
+    Flanker { clockwise: bool },
+    /// Walls off the cells far ahead of the prey.
+    Blocker,
+  }
+
+  #[derive(Debug, Component)]
+  pub struct SquadMember(pub SquadRole);
+
+  /// Goal an enemy has committed to, kept between moves so it doesn't dither.
+  #[derive(Debug, Component, Default)]
+  pub struct Target {
+    pub goal: Option<Goal>,
+    /// Moves left before the goal is reconsidered.
+    pub cooldown: u32,
+  }
+
+  /// Weights an enemy gives to each of its goals, higher means more appealing.
+  #[derive(Debug, Component, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+  #[serde(default)]
+  pub struct Personality {
+    pub regular_food: f32,
+    pub swiftness_food: f32,
+    pub extra_growth_food: f32,
+    pub hunt_smaller: f32,
+    pub flee_larger: f32,
+    pub stay_central: f32,
+    pub avoid_crowding: f32,
+    /// Overrides the difficulty's planning depth.
+    pub planning_depth: Option<usize>,
+  }
+
+  impl Personality {
+    pub const EATER: Self = Self {
+      regular_food: 1.,
+      swiftness_food: 1.,
+      extra_growth_food: 1.,
+      hunt_smaller: 0.,
+      flee_larger: 0.5,
+      stay_central: 0.1,
+      avoid_crowding: 0.2,
+      planning_depth: None,
+    };
+
+    pub const KILLER: Self = Self {
+      regular_food: 0.,
+      swiftness_food: 0.6,
+      extra_growth_food: 0.,
+      hunt_smaller: 1.5,
+      flee_larger: 0.3,
+      stay_central: 0.1,
+      avoid_crowding: 0.,
+      planning_depth: None,
+    };
+
+    pub const SPEEDSTER: Self = Self {
+      regular_food: 0.1,
+      swiftness_food: 1.,
+      extra_growth_food: 0.,
+      hunt_smaller: 0.,
+      flee_larger: 0.6,
+      stay_central: 0.1,
+      avoid_crowding: 0.3,
+      planning_depth: None,
+    };
+
+    pub const GLUTTON: Self = Self {
+      regular_food: 0.3,
+      swiftness_food: 0.,
+      extra_growth_food: 1.,
+      hunt_smaller: 0.,
+      flee_larger: 0.4,
+      stay_central: 0.1,
+      avoid_crowding: 0.1,
+      planning_depth: None,
+    };
+
+    pub const HUNTER: Self = Self {
+      regular_food: 0.2,
+      swiftness_food: 0.4,
+      extra_growth_food: 0.,
+      hunt_smaller: 2.,
+      flee_larger: 0.2,
+      stay_central: 0.1,
+      avoid_crowding: 0.,
+      planning_depth: None,
+    };
+  }
+}
+
+pub mod resources {
+  use super::{components::Personality, INITIAL_ENEMY_LENGTH};
+  use bevy::{
+    prelude::{Color, Deref, DerefMut, Entity, Resource},
+    time::{Timer, TimerMode},
+  };
+  use serde::{Deserialize, Serialize};
+  use std::{collections::BTreeMap, iter, time::Duration};
+
+  pub const EATER: &str = "eater";
+  pub const KILLER: &str = "killer";
+  pub const SPEEDSTER: &str = "speedster";
+  pub const GLUTTON: &str = "glutton";
+  pub const HUNTER: &str = "hunter";
+  /// Archetype snapshots give the enemies of personalities defined in config.
+  pub const CUSTOM: &str = "custom";
+
+  /// Named personalities, the built-in archetypes plus any defined in config.
+  #[derive(Debug, Resource, Deref, DerefMut)]
+  pub struct Personalities(pub BTreeMap<String, Personality>);
+
+  impl Personalities {
+    pub const BUILTIN: [&str; 5] = [EATER, KILLER, SPEEDSTER, GLUTTON, HUNTER];
+
+    pub fn is_builtin(name: &str) -> bool {
+      Self::BUILTIN.contains(&name)
+    }
+  }
+
+  impl Default for Personalities {
+    fn default() -> Self {
+      Self(BTreeMap::from([
+        (EATER.to_string(), Personality::EATER),
+        (KILLER.to_string(), Personality::KILLER),
+        (SPEEDSTER.to_string(), Personality::SPEEDSTER),
+        (GLUTTON.to_string(), Personality::GLUTTON),
+        (HUNTER.to_string(), Personality::HUNTER),
+      ]))
+    }
+  }
+
+  /// Groups enemies into a pack that hunts a single snake together when enabled.
+  #[derive(Debug, Resource)]
+  pub struct SquadCoordinator {
+    pub enabled: bool,
+    pub size: usize,
+    pub prey: Option<Entity>,
+  }
+
+  impl Default for SquadCoordinator {
+    fn default() -> Self {
+      Self {
+        enabled: false,
+        size: 3,
+        prey: None,
+      }
+    }
+  }
+
+  /// One line of the roster, `count` enemies sharing a personality and look.
+  #[derive(Debug, Clone, Serialize, Deserialize)]
+  #[serde(default)]
+  pub struct RosterEntry {
+    /// Personality name, built-in archetypes also get their marker component.
+    pub kind: String,
+    pub count: usize,
+    /// RGB components, `None` uses the kind's default color.
+    pub color: Option<[f32; 3]>,
+    /// `None` picks a random snake name.
+    pub name: Option<String>,
+    pub length: usize,
+    pub respawn_delay_ms: u64,
+    /// Network weights file, relative to the config directory, steering instead of seeking.
+    pub network: Option<String>,
+  }
+
+  impl Default for RosterEntry {
+    fn default() -> Self {
+      Self {
+        kind: EATER.to_string(),
+        count: 1,
+        color: None,
+        name: None,
+        length: INITIAL_ENEMY_LENGTH,
+        respawn_delay_ms: 0,
+        network: None,
+      }
+    }
+  }
+
+  impl RosterEntry {
+    pub fn of_kind(kind: &str) -> Self {
+      Self {
+        kind: kind.to_string(),
+        ..Default::default()
+      }
+    }
+
+    pub fn color(&self) -> Option<Color> {
+      self.color.map(|[r, g, b]| Color::rgb(r, g, b))
+    }
+
+    pub fn respawn_delay(&self) -> Duration {
+      Duration::from_millis(self.respawn_delay_ms)
+    }
+  }
+
+  #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+  #[serde(rename_all = "snake_case")]
+  pub enum WaveTrigger {
+    /// A new wave arrives every `interval_ms`.
+    #[default]
+    Timer,
+    /// A new wave arrives once every enemy of the previous one is dead.
+    Cleared,
+  }
+
+  #[derive(Debug, Clone, Serialize, Deserialize)]
+  #[serde(default)]
+  pub struct WaveConfig {
+    pub trigger: WaveTrigger,
+    pub interval_ms: u64,
+    pub batch: Vec<RosterEntry>,
+    /// Enemies added to every batch entry per wave.
+    pub extra_per_wave: usize,
+    /// Starting length added to every batch entry per wave.
+    pub length_per_wave: usize,
+  }
+
+  impl Default for WaveConfig {
+    fn default() -> Self {
+      Self {
+        trigger: WaveTrigger::Timer,
+        interval_ms: 30_000,
+        batch: vec![RosterEntry::of_kind(KILLER)],
+        extra_per_wave: 1,
+        length_per_wave: 2,
+      }
+    }
+  }
+
+  impl WaveConfig {
+    /// Batch for the given wave, the first wave being 0.
+    pub fn batch(&self, wave: u32) -> impl Iterator<Item = RosterEntry> + '_ {
+      let wave = wave as usize;
+      self.batch.iter().map(move |entry| RosterEntry {
+        count: entry.count + self.extra_per_wave * wave,
+        length: entry.length + self.length_per_wave * wave,
+        ..entry.clone()
+      })
+    }
+  }
+
+  /// Enemies that make up a match.
+  #[derive(Debug, Resource, Default, Clone, Serialize, Deserialize)]
+  #[serde(default)]
+  pub struct Roster {
+    pub enemies: Vec<RosterEntry>,
+    pub waves: Option<WaveConfig>,
+    /// Whether the roster was configured, its size then takes over from the difficulty's.
+    #[serde(skip)]
+    pub configured: bool,
+  }
+
+  impl Roster {
+    pub fn size(&self) -> usize {
+      self.enemies.iter().map(|entry| entry.count).sum()
+    }
+
+    /// Endless order in which enemies are added, each entry repeated by its count.
+    pub fn lineup(&self) -> impl Iterator<Item = &RosterEntry> + '_ {
+      self
+        .enemies
+        .iter()
+        .flat_map(|entry| iter::repeat(entry).take(entry.count))
+        .cycle()
+    }
+  }
+
+  #[derive(Debug, Resource)]
+  pub struct Waves {
+    /// Waves sent so far.
+    pub sent: u32,
+    pub timer: Timer,
+    /// Whether the last wave sent is still to spawn.
+    pub pending: bool,
+  }
+
+  impl Default for Waves {
+    fn default() -> Self {
+      Self {
+        sent: 0,
+        timer: Timer::new(Duration::ZERO, TimerMode::Repeating),
+        pending: false,
+      }
+    }
+  }
+}
+
+pub mod events {
+  use super::resources::RosterEntry;
+
+  /// Adds an enemy from a roster entry, as part of a wave when given one.
+  pub struct SpawnEnemy {
+    pub entry: RosterEntry,
+    pub wave: Option<u32>,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::resources::{Roster, WaveTrigger, EATER, HUNTER, KILLER, SPEEDSTER};
+
+  #[test]
+  fn parses_the_example_roster() {
+    let roster: Roster =
+      serde_json::from_str(include_str!("../../assets/config/roster.example.json")).unwrap();
+    assert_eq!(roster.size(), 4);
+    let lineup = roster
+      .lineup()
+      .take(5)
+      .map(|entry| entry.kind.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(lineup, [EATER, EATER, KILLER, HUNTER, EATER]);
+
+    let jaws = &roster.enemies[1];
+    assert_eq!(jaws.name.as_deref(), Some("Jaws"));
+    assert_eq!(
+      (jaws.count, jaws.length, jaws.respawn_delay_ms),
+      (1, 6, 3000)
+    );
+
+    let waves = roster.waves.unwrap();
+    assert_eq!(waves.trigger, WaveTrigger::Cleared);
+    assert_eq!(waves.interval_ms, 30_000);
+    let third = waves.batch(2).collect::<Vec<_>>();
+    assert_eq!(third[1].kind, SPEEDSTER);
+    assert_eq!((third[1].count, third[1].length), (3, 8));
+  }
 }
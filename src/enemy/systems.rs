@@ -1,41 +1,104 @@
 use super::{
-  components::{Eater, Enemy, Glutton, Killer, Speedster},
-  EATER_COLOR, GLUTTON_COLOR, INITIAL_ENEMY_LENGTH, KILLER_COLOR, SPEEDSTER_COLOR,
+  components::{
+    Custom, Eater, Enemy, Glutton, Hunter, Killer, Personality, RespawnTimer, Retired, Speedster,
+    SquadMember, SquadRole, Target, WaveEnemy,
+  },
+  events::SpawnEnemy,
+  resources::{
+    Personalities, Roster, RosterEntry, SquadCoordinator, WaveTrigger, Waves, EATER, GLUTTON,
+    HUNTER, KILLER, SPEEDSTER,
+  },
+  utils::{
+    commit_goal, custom_personalities, intercept_cell, list_goals, projected_path, spawn_enemy,
+    squad_cell, Goal, Motive,
+  },
+  CUSTOM_COLOR, EATER_COLOR, GLUTTON_COLOR, HUNTER_COLOR, KILLER_COLOR, ROSTER_FILE,
+  SPEEDSTER_COLOR,
 };
 use crate::{
-  board::{components::Board, resources::GameBoard},
+  board::{components::Board, grid::Grid, resources::GameBoard},
   color::components::Brightness,
+  config::{self, CONFIG_DIR},
+  difficulty::resources::{Difficulty, DynamicDifficulty},
   food::components::Food,
+  neural::components::NeuralBrain,
+  player::components::{Autopilot, Player},
+  scoreboard::components::ScoreEntity,
+  simulation::resources::GameRng,
   snake::{
-    components::{Living, Seeker, SnakeBundle, SnakeConfig, Speed},
+    components::{
+      Direction, Living, Reflexes, Seeker, Snake, SnakeBody, SnakeConfig, SnakeSegment, Speed,
+    },
     events::Serpentine,
-    utils::revive_snake,
+    utils::{despawn_snake, random_name, revive_snake},
   },
 };
 use bevy::{
-  ecs::query::{ReadOnlyWorldQuery, WorldQuery},
   prelude::{
-    BuildChildren, Changed, Color, Commands, Component, Entity, EventReader, Or, Query, Res,
-    Transform, Vec3, Visibility, With, Without,
+    Commands, Entity, EventReader, EventWriter, FixedTime, Or, Query, Res, ResMut, Transform,
+    Visibility, With, Without,
   },
+  time::{Timer, TimerMode},
 };
-use rand::random;
+use rand::Rng;
+use std::{path::Path, time::Duration};
 
-pub(super) fn spawn_enemies(
+pub(super) fn spawn(
   mut commands: Commands,
+  mut spawn_enemy_reader: EventReader<SpawnEnemy>,
   q_board: Query<Entity, With<Board>>,
   game_board: Res<GameBoard>,
+  personalities: Res<Personalities>,
+  (difficulty, dynamic): (Res<Difficulty>, Res<DynamicDifficulty>),
+  mut rng: ResMut<GameRng>,
 ) {
-  spawn_single_seeker(Eater, EATER_COLOR, &mut commands, &q_board, &game_board);
-  spawn_single_seeker(Killer, KILLER_COLOR, &mut commands, &q_board, &game_board);
-  spawn_single_seeker(
-    Speedster,
-    SPEEDSTER_COLOR,
-    &mut commands,
-    &q_board,
-    &game_board,
-  );
-  spawn_single_seeker(Glutton, GLUTTON_COLOR, &mut commands, &q_board, &game_board);
+  for SpawnEnemy { entry, wave } in spawn_enemy_reader.iter() {
+    let Ok(board) = q_board.get_single() else {return};
+    let personality = personalities.get(&entry.kind).copied().unwrap_or_default();
+    let reflexes = personality.reflexes(difficulty.reflexes(&dynamic));
+    let color = entry.color().unwrap_or(match entry.kind.as_str() {
+      EATER => EATER_COLOR,
+      KILLER => KILLER_COLOR,
+      SPEEDSTER => SPEEDSTER_COLOR,
+      GLUTTON => GLUTTON_COLOR,
+      HUNTER => HUNTER_COLOR,
+      _ => CUSTOM_COLOR,
+    });
+    let config = SnakeConfig {
+      name: entry
+        .name
+        .clone()
+        .unwrap_or_else(|| random_name(&mut **rng)),
+      x: (rng.gen::<f32>() - 0.5) * game_board.width,
+      y: (rng.gen::<f32>() - 0.5) * game_board.height,
+      color,
+      tail_length: entry.length,
+      ..Default::default()
+    };
+    let enemy = spawn_enemy((personality, reflexes), config, &mut commands, board);
+    let mut enemy = commands.entity(enemy);
+    match entry.kind.as_str() {
+      EATER => enemy.insert(Eater),
+      KILLER => enemy.insert(Killer),
+      SPEEDSTER => enemy.insert(Speedster),
+      GLUTTON => enemy.insert(Glutton),
+      HUNTER => enemy.insert(Hunter),
+      _ => enemy.insert(Custom),
+    };
+    if let Some(network) = &entry.network {
+      enemy.insert(NeuralBrain {
+        network: network.clone(),
+      });
+    }
+    if let Some(wave) = wave {
+      enemy.insert(WaveEnemy(*wave));
+    } else {
+      enemy.insert(RespawnTimer(Timer::new(
+        entry.respawn_delay(),
+        TimerMode::Once,
+      )));
+    }
+  }
 }
 
 pub(super) fn respawn(
@@ -47,12 +110,29 @@ pub(super) fn respawn(
       &mut Transform,
       &mut Speed,
       &mut Brightness,
+      &mut RespawnTimer,
     ),
-    (Without<Living>, Changed<Visibility>, With<Enemy>),
+    (Without<Living>, With<Enemy>),
   >,
+  q_retired: Query<(&SnakeBody, &ScoreEntity), With<Retired>>,
   game_board: Res<GameBoard>,
+  fixed_time: Res<FixedTime>,
+  mut rng: ResMut<GameRng>,
 ) {
-  for (enemy, mut visibility, mut transform, mut speed, mut brightness) in &mut q_dead_enemy {
+  for (enemy, mut visibility, mut transform, mut speed, mut brightness, mut respawn_timer) in
+    &mut q_dead_enemy
+  {
+    if *visibility != Visibility::Hidden {
+      continue;
+    }
+    if let Ok((body, score)) = q_retired.get(enemy) {
+      despawn_snake(&mut commands, enemy, body, score);
+      continue;
+    }
+    if !respawn_timer.0.tick(fixed_time.period).finished() {
+      continue;
+    }
+    respawn_timer.0.reset();
     revive_snake(
       &mut commands,
       (
@@ -63,109 +143,262 @@ pub(super) fn respawn(
         &mut brightness,
       ),
       &game_board,
+      &mut rng,
     );
   }
 }
 
-pub(super) fn seek_food(
-  mut serpentine_reader: EventReader<Serpentine>,
-  mut q_seeker: Query<&mut Seeker, (With<Enemy>, With<Eater>)>,
-  q_target: Query<&Transform, With<Food>>,
+pub(super) fn run_waves(
+  mut spawn_enemy_writer: EventWriter<SpawnEnemy>,
+  mut waves: ResMut<Waves>,
+  q_wave_enemy: Query<(&WaveEnemy, Option<&Living>)>,
+  roster: Res<Roster>,
+  fixed_time: Res<FixedTime>,
 ) {
-  for Serpentine(seeker, head) in serpentine_reader.iter().copied() {
-    seek_closest(seeker, &mut q_seeker, &q_target, |food| {
-      Some((food.translation.distance(head), food.translation))
-    });
+  let Some(config) = &roster.waves else {return};
+  if waves.pending {
+    if !q_wave_enemy
+      .iter()
+      .any(|(wave, _)| wave.0 + 1 == waves.sent)
+    {
+      return;
+    }
+    waves.pending = false;
+  }
+
+  let due = match config.trigger {
+    WaveTrigger::Timer => {
+      waves
+        .timer
+        .set_duration(Duration::from_millis(config.interval_ms));
+      waves.timer.tick(fixed_time.period).just_finished()
+    }
+    WaveTrigger::Cleared => !q_wave_enemy.iter().any(|(_, living)| living.is_some()),
+  };
+  if !due {
+    return;
   }
+
+  let wave = waves.sent;
+  for entry in config.batch(wave) {
+    for _ in 0..entry.count {
+      waves.pending = true;
+      spawn_enemy_writer.send(SpawnEnemy {
+        entry: entry.clone(),
+        wave: Some(wave),
+      });
+    }
+  }
+  waves.sent += 1;
 }
 
-pub(super) fn seek_snake(
-  mut serpentine_reader: EventReader<Serpentine>,
-  mut q_seeker: Query<&mut Seeker, (With<Enemy>, With<Killer>)>,
-  q_target: Query<(Entity, &Transform, Option<&Food>), Or<(With<Living>, With<Food>)>>,
+pub(super) fn clear_fallen_waves(
+  mut commands: Commands,
+  q_fallen: Query<
+    (Entity, &Visibility, &SnakeBody, &ScoreEntity),
+    (With<WaveEnemy>, Without<Living>),
+  >,
 ) {
-  for Serpentine(seeker, head) in serpentine_reader.iter().copied() {
-    seek_closest(
-      seeker,
-      &mut q_seeker,
-      &q_target,
-      |(entity, target, food)| {
-        ((food.is_none() && seeker != entity)
-          || food.map(|f| *f == Food::Swiftness).unwrap_or_default())
-        .then_some((target.translation.distance(head), target.translation))
-      },
-    );
+  for (enemy, visibility, body, score) in &q_fallen {
+    if *visibility == Visibility::Hidden {
+      despawn_snake(&mut commands, enemy, body, score);
+    }
   }
 }
 
-pub(super) fn seek_speed(
+pub(super) fn load_personalities(mut personalities: ResMut<Personalities>) {
+  personalities.extend(custom_personalities());
+}
+
+pub(super) fn load_roster(mut roster: ResMut<Roster>, personalities: Res<Personalities>) {
+  let path = Path::new(CONFIG_DIR).join(ROSTER_FILE);
+  if let Some(config) = config::load::<Roster>(path) {
+    *roster = Roster {
+      configured: true,
+      ..config
+    };
+    return;
+  }
+  roster.enemies = Personalities::BUILTIN
+    .into_iter()
+    .chain(
+      personalities
+        .keys()
+        .map(String::as_str)
+        .filter(|name| !Personalities::is_builtin(name)),
+    )
+    .map(RosterEntry::of_kind)
+    .collect();
+}
+
+pub(super) fn seek_goals(
   mut serpentine_reader: EventReader<Serpentine>,
-  mut q_seeker: Query<&mut Seeker, (With<Enemy>, With<Speedster>)>,
-  q_target: Query<(&Food, &Transform), With<Food>>,
+  mut q_seeker: Query<
+    (
+      &mut Seeker,
+      &mut Target,
+      &Personality,
+      &SnakeBody,
+      Option<&Reflexes>,
+    ),
+    Or<(With<Enemy>, With<Autopilot>)>,
+  >,
+  q_food: Query<(Entity, &Food, &Transform)>,
+  q_snake: Query<(Entity, &Transform, &SnakeBody), (With<Snake>, With<Living>)>,
+  q_snake_segment: Query<&Transform, With<SnakeSegment>>,
+  game_board: Res<GameBoard>,
 ) {
+  let mut grid = None;
   for Serpentine(seeker, head) in serpentine_reader.iter().copied() {
-    seek_closest(seeker, &mut q_seeker, &q_target, |(food, target)| {
-      (*food == Food::Swiftness).then_some((target.translation.distance(head), target.translation))
+    let Ok((mut seeker_target, mut target, personality, body, reflexes)) = q_seeker.get_mut(seeker) else {continue};
+    if target.cooldown > 0 {
+      target.cooldown -= 1;
+      continue;
+    }
+    let reflexes = reflexes.copied().unwrap_or_default();
+    target.cooldown = reflexes.reaction_delay;
+    let goals = list_goals(
+      &personality.with_aggressiveness(reflexes.aggressiveness),
+      (seeker, head, body.len()),
+      q_food
+        .iter()
+        .map(|(entity, food, food_transform)| (entity, *food, food_transform.translation)),
+      q_snake
+        .iter()
+        .map(|(snake, snake_head, body)| (snake, snake_head.translation, body.len())),
+      &game_board,
+    );
+    let grid = grid.get_or_insert_with(|| {
+      Grid::with_obstacles(
+        &game_board,
+        q_snake_segment
+          .iter()
+          .chain(q_snake.iter().map(|(_, snake_head, _)| snake_head))
+          .map(|transform| transform.translation),
+      )
     });
+    target.goal = commit_goal(target.goal, &goals, |goal| {
+      grid.reachable(head, goal.target)
+    });
+    let Some(goal) = target.goal else {continue};
+    seeker_target.0 = goal.target;
   }
 }
 
-pub(super) fn seek_nourishment(
+pub(super) fn intercept(
   mut serpentine_reader: EventReader<Serpentine>,
-  mut q_seeker: Query<&mut Seeker, (With<Enemy>, With<Glutton>)>,
-  q_target: Query<(&Food, &Transform), With<Food>>,
+  mut q_hunter: Query<(&mut Seeker, &Target, &Speed), (With<Enemy>, With<Hunter>)>,
+  q_prey: Query<(&Transform, &Direction, &Speed), (With<Snake>, With<Living>)>,
+  game_board: Res<GameBoard>,
 ) {
-  for Serpentine(seeker, head) in serpentine_reader.iter().copied() {
-    seek_closest(seeker, &mut q_seeker, &q_target, |(food, target)| {
-      (*food == Food::ExtraGrowth)
-        .then_some((target.translation.distance(head), target.translation))
-    });
+  let grid = Grid::new(&game_board);
+  for Serpentine(hunter, head) in serpentine_reader.iter().copied() {
+    let Ok((mut seeker, target, speed)) = q_hunter.get_mut(hunter) else {continue};
+    let Some(Goal { motive: Motive::Hunt, entity: Some(prey), .. }) = target.goal else {continue};
+    let Ok((prey_head, prey_direction, prey_speed)) = q_prey.get(prey) else {continue};
+    let cell = intercept_cell(
+      &grid,
+      (grid.cell(head), speed.duration()),
+      (
+        grid.cell(prey_head.translation),
+        *prey_direction,
+        prey_speed.duration(),
+      ),
+    );
+    seeker.0 = grid.position(cell);
   }
 }
 
-fn seek_closest<
-  C: Component,
-  Q: WorldQuery,
-  F: ReadOnlyWorldQuery,
-  M: FnMut(<<Q as WorldQuery>::ReadOnly as WorldQuery>::Item<'_>) -> Option<(f32, Vec3)>,
->(
-  seeker: Entity,
-  q_seeker: &mut Query<&mut Seeker, (With<Enemy>, With<C>)>,
-  q_target: &Query<Q, F>,
-  mut filter_map: M,
+pub(super) fn coordinate_squad(
+  mut commands: Commands,
+  mut coordinator: ResMut<SquadCoordinator>,
+  q_enemy: Query<(Entity, &Transform, Option<&SquadMember>), (With<Enemy>, With<Living>)>,
+  q_fallen: Query<Entity, (With<SquadMember>, Without<Living>)>,
+  q_prey: Query<(Entity, &Transform, &SnakeBody, Option<&Player>), (With<Living>, Without<Enemy>)>,
 ) {
-  let Ok(mut seeker) = q_seeker.get_mut(seeker) else {return};
-  let Some((_, target)) = q_target
+  for fallen in &q_fallen {
+    commands.entity(fallen).remove::<SquadMember>();
+  }
+
+  let prey = coordinator
+    .prey
+    .filter(|prey| coordinator.enabled && q_prey.contains(*prey))
+    .or_else(|| {
+      q_prey
+        .iter()
+        .filter(|_| coordinator.enabled)
+        .max_by_key(|(_, _, body, player)| (player.is_some(), body.len()))
+        .map(|(prey, ..)| prey)
+    });
+  if prey != coordinator.prey {
+    coordinator.prey = prey;
+    for (enemy, _, member) in &q_enemy {
+      if member.is_some() {
+        commands.entity(enemy).remove::<SquadMember>();
+      }
+    }
+    return;
+  }
+  let Some(Ok((_, prey_head, ..))) = prey.map(|prey| q_prey.get(prey)) else {return};
+
+  let mut roles = q_enemy
+    .iter()
+    .filter_map(|(_, _, member)| member.map(|member| member.0))
+    .collect::<Vec<_>>();
+  let mut recruits = q_enemy
     .iter()
-    .filter_map(&mut filter_map)
-    .min_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap()) else {return};
-  seeker.0 = target;
+    .filter(|(.., member)| member.is_none())
+    .map(|(enemy, head, _)| (enemy, head.translation.distance(prey_head.translation)))
+    .collect::<Vec<_>>();
+  recruits.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+  for (recruit, _) in recruits
+    .into_iter()
+    .take(coordinator.size.saturating_sub(roles.len()))
+  {
+    let role = [
+      SquadRole::Chaser,
+      SquadRole::Flanker { clockwise: true },
+      SquadRole::Flanker { clockwise: false },
+    ]
+    .into_iter()
+    .find(|role| !roles.contains(role))
+    .unwrap_or(SquadRole::Blocker);
+    roles.push(role);
+    commands.entity(recruit).insert(SquadMember(role));
+  }
 }
 
-fn spawn_single_seeker<C: Component>(
-  id_component: C,
-  color: Color,
-  commands: &mut Commands,
-  q_board: &Query<Entity, With<Board>>,
-  game_board: &GameBoard,
+pub(super) fn apply_squad_roles(
+  mut serpentine_reader: EventReader<Serpentine>,
+  mut q_member: Query<(Entity, &Transform, &mut Seeker, &SquadMember, &Speed), With<Enemy>>,
+  q_prey: Query<(&Transform, &Direction, &Speed), (With<Snake>, With<Living>)>,
+  coordinator: Res<SquadCoordinator>,
+  game_board: Res<GameBoard>,
 ) {
-  let Ok(board) = q_board.get_single() else {return};
-  let enemy = (
-    Enemy,
-    id_component,
-    Seeker::default(),
-    SnakeBundle::new(
-      commands,
-      board,
-      SnakeConfig {
-        x: (random::<f32>() - 0.5) * game_board.width,
-        y: (random::<f32>() - 0.5) * game_board.height,
-        color,
-        tail_length: INITIAL_ENEMY_LENGTH,
-        ..Default::default()
-      },
-    ),
-  );
-  let enemy = commands.spawn(enemy).id();
-  commands.entity(board).add_child(enemy);
+  let Some(Ok((prey_head, prey_direction, prey_speed))) = coordinator.prey.map(|prey| q_prey.get(prey)) else {return};
+  for Serpentine(member, head) in serpentine_reader.iter().copied() {
+    let mut grid = Grid::new(&game_board);
+    for (other, other_head, other_seeker, ..) in &q_member {
+      if other == member {
+        continue;
+      }
+      let (from, to) = (grid.cell(other_head.translation), grid.cell(other_seeker.0));
+      for cell in projected_path(&grid, from, to) {
+        grid.block(cell);
+      }
+    }
+    let Ok((_, _, mut seeker, SquadMember(role), speed)) = q_member.get_mut(member) else {continue};
+    let cell = squad_cell(
+      &grid,
+      *role,
+      (grid.cell(head), speed.duration()),
+      (
+        grid.cell(prey_head.translation),
+        *prey_direction,
+        prey_speed.duration(),
+      ),
+    );
+    seeker.0 = grid.position(cell);
+  }
 }
@@ -0,0 +1,310 @@
+use super::{
+  components::{Enemy, Personality, SquadRole, Target},
+  FLANK_OFFSET, INTERCEPT_LOOKAHEAD, PERSONALITIES_FILE,
+};
+use crate::{
+  board::{grid::Grid, resources::GameBoard, CELL_SIZE},
+  config::{self, CONFIG_DIR},
+  food::components::Food,
+  snake::components::{Direction, Reflexes, Seeker, SnakeBundle, SnakeConfig},
+};
+use bevy::prelude::{BuildChildren, Commands, Entity, IVec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+/// Cells within which a larger snake is considered a threat.
+pub const FLEE_RADIUS: f32 = 8.;
+/// Cells within which other snakes count towards crowding.
+pub const CROWD_RADIUS: f32 = 6.;
+/// How much better, relatively, a new goal must be to replace the current one.
+pub const SWITCH_MARGIN: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Motive {
+  Eat(Food),
+  Hunt,
+  Flee,
+  Centre,
+  Disperse,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Goal {
+  pub motive: Motive,
+  pub entity: Option<Entity>,
+  pub target: Vec3,
+  pub utility: f32,
+}
+
+impl Goal {
+  pub fn is_same(&self, other: &Goal) -> bool {
+    self.motive == other.motive && self.entity == other.entity
+  }
+}
+
+impl Personality {
+  pub fn food_weight(&self, food: Food) -> f32 {
+    match food {
+      Food::Regular => self.regular_food,
+      Food::Swiftness => self.swiftness_food,
+      Food::ExtraGrowth => self.extra_growth_food,
+    }
+  }
+
+  pub fn reflexes(&self, base: Reflexes) -> Reflexes {
+    Reflexes {
+      planning_depth: self.planning_depth.unwrap_or(base.planning_depth),
+      ..base
+    }
+  }
+
+  /// Leans the personality towards hunting and away from fleeing, or the reverse below 1.
+  pub fn with_aggressiveness(&self, aggressiveness: f32) -> Self {
+    Self {
+      hunt_smaller: self.hunt_smaller * aggressiveness,
+      flee_larger: self.flee_larger / aggressiveness.max(f32::EPSILON),
+      ..*self
+    }
+  }
+}
+
+/// Personalities defined in config, on top of the built-in ones.
+pub fn custom_personalities() -> BTreeMap<String, Personality> {
+  let path = Path::new(CONFIG_DIR).join(PERSONALITIES_FILE);
+  config::load(path).unwrap_or_default()
+}
+
+/// Scores every goal currently available to a snake.
+pub fn list_goals<
+  F: Iterator<Item = (Entity, Food, Vec3)>,
+  S: Iterator<Item = (Entity, Vec3, usize)>,
+>(
+  personality: &Personality,
+  (seeker, head, length): (Entity, Vec3, usize),
+  food: F,
+  snakes: S,
+  game_board: &GameBoard,
+) -> Vec<Goal> {
+  let cells = |a: Vec3, b: Vec3| a.distance(b) / CELL_SIZE;
+  let away_from =
+    |from: Vec3, radius: f32| head + (head - from).normalize_or_zero() * radius * CELL_SIZE;
+
+  let mut goals = food
+    .map(|(entity, food, target)| Goal {
+      motive: Motive::Eat(food),
+      entity: Some(entity),
+      target,
+      utility: personality.food_weight(food) / (1. + cells(head, target)),
+    })
+    .collect::<Vec<_>>();
+
+  let mut crowd = (0, Vec3::ZERO);
+  for (snake, target, snake_length) in snakes {
+    if snake == seeker {
+      continue;
+    }
+    let distance = cells(head, target);
+    if snake_length < length {
+      goals.push(Goal {
+        motive: Motive::Hunt,
+        entity: Some(snake),
+        target,
+        utility: personality.hunt_smaller / (1. + distance),
+      });
+    } else if snake_length > length && distance < FLEE_RADIUS {
+      goals.push(Goal {
+        motive: Motive::Flee,
+        entity: Some(snake),
+        target: away_from(target, FLEE_RADIUS),
+        utility: personality.flee_larger * (1. - distance / FLEE_RADIUS),
+      });
+    }
+    if distance < CROWD_RADIUS {
+      crowd = (crowd.0 + 1, crowd.1 + target);
+    }
+  }
+
+  if crowd.0 > 0 {
+    let crowding = crowd.0 as f32;
+    goals.push(Goal {
+      motive: Motive::Disperse,
+      entity: None,
+      target: away_from(crowd.1 / crowding, CROWD_RADIUS),
+      utility: personality.avoid_crowding * crowding / (crowding + 1.),
+    });
+  }
+
+  let max_distance = Vec3::new(game_board.width, game_board.height, 0.).length() / 2.;
+  if max_distance > 0. {
+    goals.push(Goal {
+      motive: Motive::Centre,
+      entity: None,
+      target: Vec3::ZERO,
+      utility: personality.stay_central * head.length() / max_distance,
+    });
+  }
+
+  goals.retain(|goal| goal.utility > 0.);
+  goals
+}
+
+/// Keeps the current goal unless it is gone, unreachable or clearly beaten by another one.
+pub fn commit_goal<R: FnMut(&Goal) -> bool>(
+  current: Option<Goal>,
+  goals: &[Goal],
+  mut reachable: R,
+) -> Option<Goal> {
+  let current = current
+    .and_then(|current| goals.iter().find(|goal| goal.is_same(&current)))
+    .copied()
+    .filter(&mut reachable);
+  let mut candidates = goals.to_vec();
+  candidates.sort_by(|a, b| b.utility.total_cmp(&a.utility));
+  let best = candidates.into_iter().find(|goal| match current {
+    Some(current) if goal.is_same(&current) => true,
+    _ => reachable(goal),
+  });
+  match (current, best) {
+    (Some(current), Some(best)) if best.utility <= current.utility * (1. + SWITCH_MARGIN) => {
+      Some(current)
+    }
+    (current, best) => best.or(current),
+  }
+}
+
+/// Earliest cell on the prey's projected path that the hunter can reach first, or the furthest
+/// projected cell so the hunter at least cuts across its path.
+pub fn intercept_cell(
+  grid: &Grid,
+  (hunter, hunter_move): (IVec2, Duration),
+  (prey, prey_direction, prey_move): (IVec2, Direction, Duration),
+) -> IVec2 {
+  let mut cell = prey;
+  for moves in 1..=INTERCEPT_LOOKAHEAD {
+    cell = grid.neighbour(cell, prey_direction);
+    if hunter_move * grid.distance(hunter, cell) as u32 <= prey_move * moves as u32 {
+      break;
+    }
+  }
+  cell
+}
+
+/// Cell a squad member should head for given its role around the prey.
+pub fn squad_cell(
+  grid: &Grid,
+  role: SquadRole,
+  (member, member_move): (IVec2, Duration),
+  (prey, prey_direction, prey_move): (IVec2, Direction, Duration),
+) -> IVec2 {
+  let ahead = |cell: IVec2, direction: Direction, moves: i32| {
+    (0..moves).fold(cell, |cell, _| grid.neighbour(cell, direction))
+  };
+  let cell = match role {
+    SquadRole::Chaser => intercept_cell(
+      grid,
+      (member, member_move),
+      (prey, prey_direction, prey_move),
+    ),
+    SquadRole::Flanker { clockwise } => {
+      let side = if clockwise {
+        prey_direction.clockwise()
+      } else {
+        prey_direction.counter_clockwise()
+      };
+      ahead(
+        ahead(prey, prey_direction, INTERCEPT_LOOKAHEAD / 2),
+        side,
+        FLANK_OFFSET,
+      )
+    }
+    SquadRole::Blocker => ahead(prey, prey_direction, INTERCEPT_LOOKAHEAD * 2),
+  };
+  // The other members' paths are blocked on the grid so the squad doesn't run into itself.
+  grid.nearest_free(cell)
+}
+
+/// Cells a snake crosses heading for a target, closing the widest gap first like seeking does.
+pub fn projected_path(grid: &Grid, from: IVec2, to: IVec2) -> Vec<IVec2> {
+  let shortest = |d: i32, size: i32| {
+    let d = d.rem_euclid(size);
+    if d > size / 2 {
+      d - size
+    } else {
+      d
+    }
+  };
+  let mut cell = grid.wrap(from);
+  let mut path = vec![cell];
+  while cell != grid.wrap(to) {
+    let d = IVec2::new(
+      shortest(to.x - cell.x, grid.cols),
+      shortest(to.y - cell.y, grid.rows),
+    );
+    let direction = if d.x.abs() > d.y.abs() {
+      if d.x > 0 {
+        Direction::Right
+      } else {
+        Direction::Left
+      }
+    } else if d.y > 0 {
+      Direction::Top
+    } else {
+      Direction::Bottom
+    };
+    cell = grid.neighbour(cell, direction);
+    path.push(cell);
+  }
+  path
+}
+
+pub fn spawn_enemy(
+  (personality, reflexes): (Personality, Reflexes),
+  config: SnakeConfig,
+  commands: &mut Commands,
+  board: Entity,
+) -> Entity {
+  let enemy = (
+    Enemy,
+    personality,
+    reflexes,
+    Target::default(),
+    Seeker::default(),
+    SnakeBundle::new(commands, board, config),
+  );
+  let enemy = commands.spawn(enemy).id();
+  commands.entity(board).add_child(enemy);
+  enemy
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn squad_members_keep_off_each_others_paths() {
+    let game_board = GameBoard {
+      width: 30. * CELL_SIZE,
+      height: 30. * CELL_SIZE,
+    };
+    let step = Duration::from_millis(100);
+    let prey = (IVec2::new(10, 10), Direction::Right, step);
+    let flanker = SquadRole::Flanker { clockwise: true };
+
+    // Both members converge on the prey and the chaser's way up crosses the flanker's cell.
+    let grid = Grid::new(&game_board);
+    let chaser = IVec2::new(14, 2);
+    let chaser_cell = squad_cell(&grid, SquadRole::Chaser, (chaser, step), prey);
+    let chaser_path = projected_path(&grid, chaser, chaser_cell);
+    let open_cell = squad_cell(&grid, flanker, (IVec2::new(4, 4), step), prey);
+    assert!(chaser_path.contains(&open_cell));
+
+    let mut grid = Grid::new(&game_board);
+    for cell in &chaser_path {
+      grid.block(*cell);
+    }
+    let flanker_cell = squad_cell(&grid, flanker, (IVec2::new(4, 4), step), prey);
+    assert!(!chaser_path.contains(&flanker_cell));
+    assert_eq!(grid.distance(flanker_cell, open_cell), 1);
+  }
+}
@@ -0,0 +1,139 @@
+use crate::{
+  board::{grid::Grid, resources::GameBoard, CELL_SIZE},
+  observation::{BoardState, SnakeState},
+  snake::{components::Direction, MAX_SERPENTINE_DURATION_MS},
+};
+use bevy::prelude::{IVec2, Vec2};
+
+/// Walls, bodies, heads, the observing snake and food.
+pub const GRID_CHANNELS: usize = 5;
+/// See `egocentric` for their meaning and order.
+pub const EGOCENTRIC_FEATURES: usize = 11;
+
+fn grid(state: &BoardState) -> Grid {
+  Grid::new(&GameBoard {
+    width: state.width as f32 * CELL_SIZE,
+    height: state.height as f32 * CELL_SIZE,
+  })
+}
+
+/// Cells a snake can't move into, living heads and every body segment like `snake_crashed`.
+fn obstacles(state: &BoardState) -> Grid {
+  let mut grid = grid(state);
+  for snake in &state.snakes {
+    if snake.living {
+      grid.block(IVec2::from(snake.head));
+    }
+    for segment in &snake.body {
+      grid.block(IVec2::from(*segment));
+    }
+  }
+  grid
+}
+
+/// Direction of the snake's last move, from its head and the segment right behind it.
+pub fn heading(state: &BoardState, you: &SnakeState) -> Option<Direction> {
+  use Direction::*;
+  let grid = grid(state);
+  let neck = IVec2::from(*you.body.first()?);
+  [Top, Right, Bottom, Left]
+    .into_iter()
+    .find(|direction| grid.neighbour(neck, *direction) == IVec2::from(you.head))
+}
+
+/// Channel-major `[GRID_CHANNELS, height, width]` tensor, 1 where a channel's content is.
+///
+/// The board wraps around its edges so the walls channel stays empty, it is kept so boards
+/// with walls don't change the layout.
+pub fn grid_tensor(state: &BoardState, you: &SnakeState) -> Vec<f32> {
+  const BODIES: usize = 1;
+  const HEADS: usize = 2;
+  const YOU: usize = 3;
+  const FOOD: usize = 4;
+  let (width, height) = (state.width as usize, state.height as usize);
+  let mut tensor = vec![0.; GRID_CHANNELS * width * height];
+  let mut mark = |channel: usize, [x, y]: [i32; 2]| {
+    tensor[(channel * height + y as usize) * width + x as usize] = 1.;
+  };
+  for snake in &state.snakes {
+    if snake.living {
+      mark(HEADS, snake.head);
+    }
+    for segment in &snake.body {
+      mark(BODIES, *segment);
+    }
+  }
+  if you.living {
+    mark(YOU, you.head);
+  }
+  for segment in &you.body {
+    mark(YOU, *segment);
+  }
+  for food in &state.food {
+    mark(FOOD, food.cell);
+  }
+  tensor
+}
+
+/// Features relative to the snake's heading, in order:
+/// - danger one cell ahead, to the left and to the right,
+/// - free cells in a straight line ahead, to the left and to the right, over the board's size,
+/// - nearest food ahead and to the right, negative behind and to the left, over the board's size,
+/// - length over the board's area,
+/// - time between moves over the slowest one,
+/// - whether the snake is alive.
+pub fn egocentric(state: &BoardState, you: &SnakeState) -> Vec<f32> {
+  let grid = obstacles(state);
+  let head = IVec2::from(you.head);
+  let heading = you.direction;
+  let sides = [heading, heading.counter_clockwise(), heading.clockwise()];
+  let size = state.width.max(state.height);
+
+  let free_run = |direction: Direction| {
+    let mut cell = head;
+    let mut run = 0;
+    while run < size {
+      cell = grid.neighbour(cell, direction);
+      if grid.is_blocked(cell) {
+        break;
+      }
+      run += 1;
+    }
+    run
+  };
+  let offset = |cell: IVec2| {
+    let wrap = |d: i32, size: i32| (d + size / 2).rem_euclid(size) - size / 2;
+    let d = IVec2::new(
+      wrap(cell.x - head.x, state.width),
+      wrap(cell.y - head.y, state.height),
+    );
+    let along = |direction: Direction| {
+      let (x, y) = direction.xy(1., 1.);
+      d.as_vec2().dot(Vec2::new(x, y))
+    };
+    (along(heading), along(heading.clockwise()))
+  };
+
+  let mut features = Vec::with_capacity(EGOCENTRIC_FEATURES);
+  features.extend(
+    sides
+      .iter()
+      .map(|side| grid.is_blocked(grid.neighbour(head, *side)) as u8 as f32),
+  );
+  features.extend(
+    sides
+      .iter()
+      .map(|side| free_run(*side) as f32 / size as f32),
+  );
+  let (ahead, right) = state
+    .food
+    .iter()
+    .map(|food| IVec2::from(food.cell))
+    .min_by_key(|cell| grid.distance(head, *cell))
+    .map_or((0., 0.), offset);
+  features.extend([ahead / size as f32, right / size as f32]);
+  features.push(you.length as f32 / (state.width * state.height) as f32);
+  features.push(you.move_ms as f32 / MAX_SERPENTINE_DURATION_MS as f32);
+  features.push(you.living as u8 as f32);
+  features
+}
@@ -0,0 +1,283 @@
+pub mod features;
+
+use self::{
+  components::Agent,
+  features::{EGOCENTRIC_FEATURES, GRID_CHANNELS},
+};
+use crate::{
+  board::{components::Board, resources::GameBoard},
+  difficulty::resources::Difficulty,
+  observation::{snake_id, BoardObserver, BoardState},
+  simulation::{
+    resources::{GameRng, SimulationClock},
+    utils::{headless_app, step},
+  },
+  snake::{
+    components::{Direction, SnakeBody, SnakeBundle, SnakeConfig},
+    events::{Serpentine, SnakeDied},
+  },
+};
+use bevy::{
+  ecs::{event::ManualEventReader, system::SystemState},
+  prelude::{App, BuildChildren, Color, Commands, Entity, Events, Query, Res, ResMut, With, World},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub const AGENT_COLOR: Color = Color::rgb(250. / 255., 210. / 255., 90. / 255.);
+pub const INITIAL_AGENT_LENGTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObservationKind {
+  /// Whole board as a `[channels, height, width]` tensor, see `features::grid_tensor`.
+  #[default]
+  Grid,
+  /// Handful of features around the snake's head, see `features::egocentric`.
+  Egocentric,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Rewards {
+  /// Per segment gained, or lost.
+  pub length: f32,
+  pub kill: f32,
+  pub death: f32,
+}
+
+impl Default for Rewards {
+  fn default() -> Self {
+    Self {
+      length: 1.,
+      kill: 5.,
+      death: -10.,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnvConfig {
+  /// Board size in cells, rounded down to even numbers.
+  pub cols: i32,
+  pub rows: i32,
+  /// Snakes steered through `step`, the rest of the board is filled by the usual enemies.
+  pub agents: usize,
+  pub difficulty: Difficulty,
+  pub observation: ObservationKind,
+  pub rewards: Rewards,
+  /// Ticks after which an episode ends even if agents are still alive.
+  pub max_ticks: u64,
+}
+
+impl Default for EnvConfig {
+  fn default() -> Self {
+    Self {
+      cols: 40,
+      rows: 40,
+      agents: 1,
+      difficulty: Difficulty::default(),
+      observation: ObservationKind::default(),
+      rewards: Rewards::default(),
+      max_ticks: 60_000,
+    }
+  }
+}
+
+/// One flat vector per agent, all of them laid out as `shape`.
+#[derive(Debug, Clone)]
+pub struct Observation {
+  pub shape: Vec<usize>,
+  pub agents: Vec<Vec<f32>>,
+}
+
+struct AgentState {
+  snake: Entity,
+  /// Direction of the last move, turning back onto it is ignored.
+  heading: Direction,
+  length: usize,
+  living: bool,
+}
+
+/// Gym-like environment over the headless simulation, every `step` advances it by one tick.
+pub struct SnakeEnv {
+  config: EnvConfig,
+  app: App,
+  agents: Vec<AgentState>,
+  serpentine_reader: ManualEventReader<Serpentine>,
+  snake_died_reader: ManualEventReader<SnakeDied>,
+}
+
+impl SnakeEnv {
+  pub fn new(config: EnvConfig, seed: u64) -> Self {
+    let mut env = Self {
+      app: App::new(),
+      config,
+      agents: Vec::new(),
+      serpentine_reader: Default::default(),
+      snake_died_reader: Default::default(),
+    };
+    env.reset(seed);
+    env
+  }
+
+  /// Starts a new episode, the same seed and actions always play out the same way.
+  pub fn reset(&mut self, seed: u64) -> Observation {
+    self.app = headless_app(
+      seed,
+      GameBoard::with_cells(self.config.cols, self.config.rows),
+    );
+    self.app.insert_resource(self.config.difficulty);
+    // Runs the startup systems so the board exists before the agents are put on it.
+    self.app.update();
+    self.agents = spawn_agents(&mut self.app.world, self.config.agents)
+      .into_iter()
+      .map(|snake| AgentState {
+        snake,
+        heading: Direction::default(),
+        length: INITIAL_AGENT_LENGTH,
+        living: true,
+      })
+      .collect();
+    self.serpentine_reader = Default::default();
+    self.snake_died_reader = Default::default();
+    self.observe()
+  }
+
+  /// Turns each agent to its action, `None` keeps its direction, then simulates one tick.
+  pub fn step(&mut self, actions: &[Option<Direction>]) -> (Observation, Vec<f32>, bool) {
+    for (agent, action) in self.agents.iter().zip(actions) {
+      let Some(action) = *action else {continue};
+      if !agent.living || action == agent.heading.opposite() {
+        continue;
+      }
+      if let Some(mut direction) = self.app.world.get_mut::<Direction>(agent.snake) {
+        *direction = action;
+      }
+    }
+    step(&mut self.app);
+
+    let world = &self.app.world;
+    let moved = self
+      .serpentine_reader
+      .iter(world.resource::<Events<Serpentine>>())
+      .map(|Serpentine(snake, _)| *snake)
+      .collect::<Vec<_>>();
+    let deaths = self
+      .snake_died_reader
+      .iter(world.resource::<Events<SnakeDied>>())
+      .copied()
+      .collect::<Vec<_>>();
+    let rewards = self
+      .agents
+      .iter_mut()
+      .map(|agent| {
+        if !agent.living {
+          return 0.;
+        }
+        if moved.contains(&agent.snake) {
+          agent.heading = world
+            .get::<Direction>(agent.snake)
+            .copied()
+            .unwrap_or(agent.heading);
+        }
+        let kills = deaths
+          .iter()
+          .filter(|death| death.killer == Some(agent.snake))
+          .count();
+        let mut reward = kills as f32 * self.config.rewards.kill;
+        if deaths.iter().any(|death| death.snake == agent.snake) {
+          agent.living = false;
+          return reward + self.config.rewards.death;
+        }
+        let length = world
+          .get::<SnakeBody>(agent.snake)
+          .map_or(agent.length, SnakeBody::len);
+        reward += (length as f32 - agent.length as f32) * self.config.rewards.length;
+        agent.length = length;
+        reward
+      })
+      .collect();
+
+    let done = self.agents.iter().all(|agent| !agent.living)
+      || world.resource::<SimulationClock>().tick >= self.config.max_ticks;
+    (self.observe(), rewards, done)
+  }
+
+  pub fn agents(&self) -> usize {
+    self.agents.len()
+  }
+
+  /// Snake steered by an agent, to look it up in `state`.
+  pub fn agent_id(&self, agent: usize) -> Option<u64> {
+    self.agents.get(agent).map(|agent| snake_id(agent.snake))
+  }
+
+  pub fn state(&mut self) -> BoardState {
+    let mut observer = SystemState::<BoardObserver>::new(&mut self.app.world);
+    observer.get_mut(&mut self.app.world).state()
+  }
+
+  pub fn world(&self) -> &World {
+    &self.app.world
+  }
+
+  pub fn observe(&mut self) -> Observation {
+    let state = self.state();
+    let shape = match self.config.observation {
+      ObservationKind::Grid => vec![GRID_CHANNELS, state.height as usize, state.width as usize],
+      ObservationKind::Egocentric => vec![EGOCENTRIC_FEATURES],
+    };
+    let agents = self
+      .agents
+      .iter()
+      .map(|agent| {
+        let id = snake_id(agent.snake);
+        let Some(you) = state.snakes.iter().find(|snake| snake.id == id) else {
+          return vec![0.; shape.iter().product()];
+        };
+        match self.config.observation {
+          ObservationKind::Grid => features::grid_tensor(&state, you),
+          ObservationKind::Egocentric => features::egocentric(&state, you),
+        }
+      })
+      .collect();
+    Observation { shape, agents }
+  }
+}
+
+fn spawn_agents(world: &mut World, count: usize) -> Vec<Entity> {
+  let mut system_state = SystemState::<(
+    Commands,
+    Query<Entity, With<Board>>,
+    Res<GameBoard>,
+    ResMut<GameRng>,
+  )>::new(world);
+  let (mut commands, q_board, game_board, mut rng) = system_state.get_mut(world);
+  let Ok(board) = q_board.get_single() else {return Vec::new()};
+  let agents = (0..count)
+    .map(|i| {
+      let config = SnakeConfig {
+        name: format!("Agent {}", i + 1),
+        x: (rng.gen::<f32>() - 0.5) * game_board.width,
+        y: (rng.gen::<f32>() - 0.5) * game_board.height,
+        color: AGENT_COLOR,
+        tail_length: INITIAL_AGENT_LENGTH,
+        ..Default::default()
+      };
+      let agent = (Agent(i), SnakeBundle::new(&mut commands, board, config));
+      let agent = commands.spawn(agent).id();
+      commands.entity(board).add_child(agent);
+      agent
+    })
+    .collect();
+  system_state.apply(world);
+  agents
+}
+
+pub mod components {
+  use bevy::prelude::Component;
+
+  /// Snake steered through `SnakeEnv::step`, by its index in the actions.
+  #[derive(Debug, Component)]
+  pub struct Agent(pub usize);
+}
@@ -1,6 +1,7 @@
 mod systems;
 
-use bevy::prelude::{App, Plugin};
+use crate::simulation::SimulationSet;
+use bevy::prelude::{App, CoreSchedule, IntoSystemAppConfigs, IntoSystemConfigs, Plugin};
 
 pub struct FoodPlugin;
 
@@ -9,18 +10,28 @@ impl Plugin for FoodPlugin {
     app
       .add_event::<events::SpawnFood>()
       .add_event::<events::FoodEaten>()
-      .add_startup_system(systems::startup)
-      .add_system(systems::spawn)
-      .add_system(systems::reposition)
-      .add_system(systems::apply_effects);
+      .add_systems(
+        (
+          // Sent from a tick, events from startup may be gone by the time it runs.
+          systems::startup,
+          systems::spawn,
+          systems::reposition,
+          systems::apply_effects,
+        )
+          .chain()
+          .in_set(SimulationSet::Food)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      );
   }
 }
 
 pub mod components {
   use bevy::prelude::{Color, Component};
   use rand::{distributions::Standard, prelude::Distribution, Rng};
+  use serde::{Deserialize, Serialize};
 
-  #[derive(Debug, Component, Copy, Clone, PartialEq, Eq)]
+  #[derive(Debug, Component, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+  #[serde(rename_all = "snake_case")]
   pub enum Food {
     Regular,
     Swiftness,
@@ -50,12 +61,13 @@ pub mod components {
 
 pub mod events {
   use super::components::Food;
-  use bevy::prelude::Entity;
+  use bevy::prelude::{Entity, IVec2};
 
   pub struct FoodEaten {
     pub snake: Entity,
     pub food: Entity,
   }
 
-  pub struct SpawnFood(pub Food);
+  /// Lays out food on the given cell, or a random one.
+  pub struct SpawnFood(pub Food, pub Option<IVec2>);
 }
@@ -5,10 +5,12 @@ use super::{
 use crate::{
   board::{
     components::Board,
+    grid::Grid,
     resources::GameBoard,
     utils::{create_cell_bundle, get_board_position},
   },
   color::components::Brightness,
+  simulation::resources::GameRng,
   snake::{
     components::{Living, Nourished, Snake, Speed},
     events::{BodySizeChange, SnakeSizeChange},
@@ -16,15 +18,23 @@ use crate::{
   },
 };
 use bevy::prelude::{
-  BuildChildren, Commands, Entity, EventReader, EventWriter, Query, Res, Transform, With,
+  BuildChildren, Commands, Entity, EventReader, EventWriter, Query, Res, ResMut, Transform, Vec3,
+  With,
 };
-use rand::random;
+use rand::Rng;
 use std::time::Duration;
 
-pub(super) fn startup(mut spawn_food_writer: EventWriter<SpawnFood>) {
-  spawn_food_writer.send(SpawnFood(Food::Regular));
-  spawn_food_writer.send(SpawnFood(Food::ExtraGrowth));
-  spawn_food_writer.send(SpawnFood(Food::Swiftness));
+/// Lays out the food when the board has none, on the first tick or a snapshot without any.
+pub(super) fn startup(
+  mut spawn_food_writer: EventWriter<SpawnFood>,
+  q_food: Query<(), With<Food>>,
+) {
+  if !q_food.is_empty() {
+    return;
+  }
+  spawn_food_writer.send(SpawnFood(Food::Regular, None));
+  spawn_food_writer.send(SpawnFood(Food::ExtraGrowth, None));
+  spawn_food_writer.send(SpawnFood(Food::Swiftness, None));
 }
 
 pub(super) fn spawn(
@@ -32,17 +42,22 @@ pub(super) fn spawn(
   mut spawn_food_reader: EventReader<SpawnFood>,
   q_board: Query<Entity, With<Board>>,
   game_board: Res<GameBoard>,
+  mut rng: ResMut<GameRng>,
 ) {
-  for SpawnFood(food) in &mut spawn_food_reader {
+  for SpawnFood(food, cell) in &mut spawn_food_reader {
     let Ok(board) = q_board.get_single() else {continue};
+    let position = match cell {
+      Some(cell) => Grid::new(&game_board).position(*cell),
+      None => Vec3::new(
+        (rng.gen::<f32>() - 0.5) * game_board.width,
+        (rng.gen::<f32>() - 0.5) * game_board.height,
+        0.,
+      ),
+    };
     let food = commands
       .spawn((
         *food,
-        create_cell_bundle(
-          (*food).into(),
-          (random::<f32>() - 0.5) * game_board.width,
-          (random::<f32>() - 0.5) * game_board.height,
-        ),
+        create_cell_bundle((*food).into(), position.x, position.y),
       ))
       .id();
     commands.entity(board).add_child(food);
@@ -53,12 +68,13 @@ pub(super) fn reposition(
   mut food_eaten_reader: EventReader<FoodEaten>,
   mut q_food: Query<&mut Transform>,
   game_board: Res<GameBoard>,
+  mut rng: ResMut<GameRng>,
 ) {
   for eaten in food_eaten_reader.iter() {
     let Ok(mut food) = q_food.get_mut(eaten.food) else {continue};
     food.translation = get_board_position(
-      (random::<f32>() - 0.5) * game_board.width,
-      (random::<f32>() - 0.5) * game_board.height,
+      (rng.gen::<f32>() - 0.5) * game_board.width,
+      (rng.gen::<f32>() - 0.5) * game_board.height,
     );
   }
 }
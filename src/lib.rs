@@ -0,0 +1,54 @@
+// Systems take every query and resource they use as a parameter, clippy's limits on those don't
+// fit the way Bevy is written.
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+
+pub mod bindings;
+pub mod board;
+pub mod bot;
+pub mod color;
+pub mod config;
+pub mod control;
+pub mod debug;
+pub mod difficulty;
+pub mod enemy;
+pub mod env;
+pub mod food;
+pub mod lobby;
+pub mod main_camera;
+pub mod net;
+pub mod neural;
+pub mod observation;
+pub mod player;
+pub mod replay;
+pub mod rewind;
+pub mod rollback;
+pub mod scoreboard;
+pub mod simulation;
+pub mod snake;
+pub mod snapshot;
+pub mod spectator;
+pub mod tournament;
+pub mod tuning;
+
+pub mod state {
+  use bevy::prelude::States;
+
+  #[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+  pub enum GameState {
+    #[default]
+    Paused,
+    Playing,
+  }
+}
+
+mod collections {
+  pub trait ExternalOps {
+    fn add(&self, rhs: Self) -> Self;
+  }
+
+  impl ExternalOps for (f32, f32) {
+    fn add(&self, rhs: Self) -> Self {
+      (self.0 + rhs.0, self.1 + rhs.1)
+    }
+  }
+}
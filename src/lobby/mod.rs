@@ -0,0 +1,44 @@
+mod systems;
+
+use crate::replay::resources::Playback;
+use bevy::{
+  ecs::schedule::common_conditions::not,
+  prelude::{resource_exists, App, IntoSystemConfig, IntoSystemConfigs, Plugin},
+};
+
+/// Lists the local players before the match starts, more of them join by pressing a steering key
+/// of a key scheme nobody took yet.
+pub struct LobbyPlugin;
+
+impl Plugin for LobbyPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      // A replay brings its own players.
+      .add_startup_system(systems::open.run_if(not(resource_exists::<Playback>())))
+      .add_systems(
+        (
+          systems::join,
+          systems::start,
+          systems::close,
+          systems::update_hud,
+        )
+          .chain()
+          .distributive_run_if(resource_exists::<resources::Lobby>()),
+      );
+  }
+}
+
+pub mod components {
+  use bevy::prelude::Component;
+
+  #[derive(Component)]
+  pub struct LobbyHud;
+}
+
+pub mod resources {
+  use bevy::prelude::Resource;
+
+  /// Present until the match starts, players can join while it is.
+  #[derive(Debug, Resource)]
+  pub struct Lobby;
+}
@@ -0,0 +1,109 @@
+use super::{components::LobbyHud, resources::Lobby};
+use crate::{
+  bindings::resources::{Action, Actions, InputMap},
+  player::{
+    components::{KeyScheme, Player},
+    events::AddPlayer,
+  },
+  scoreboard::components::{Name, ScoreEntity},
+  state::GameState,
+};
+use bevy::prelude::{
+  AssetServer, Color, Commands, DespawnRecursiveExt, Entity, EventWriter, NextState, PositionType,
+  Query, Res, ResMut, State, Style, Text, TextBundle, TextStyle, UiRect, Val, With,
+};
+
+pub(super) fn open(mut commands: Commands, asset_server: Res<AssetServer>) {
+  commands.insert_resource(Lobby);
+  commands.spawn((
+    LobbyHud,
+    TextBundle::from_section(
+      "",
+      TextStyle {
+        font: asset_server.load("fonts/UbuntuMono-Regular.ttf"),
+        font_size: 16.,
+        color: Color::WHITE,
+      },
+    )
+    .with_style(Style {
+      position_type: PositionType::Absolute,
+      position: UiRect {
+        right: Val::Px(10.),
+        top: Val::Px(10.),
+        ..Default::default()
+      },
+      ..Default::default()
+    }),
+  ));
+}
+
+pub(super) fn join(
+  mut add_player_writer: EventWriter<AddPlayer>,
+  actions: Res<Actions>,
+  q_keys: Query<&KeyScheme, With<Player>>,
+) {
+  for keys in KeyScheme::ALL {
+    if q_keys.iter().any(|taken| *taken == keys) {
+      continue;
+    }
+    let mut turns = Action::TURNS.into_iter();
+    if turns.any(|(action, _)| actions.just_pressed(Some(keys), action)) {
+      add_player_writer.send(AddPlayer(keys));
+    }
+  }
+}
+
+pub(super) fn start(actions: Res<Actions>, mut next_state: ResMut<NextState<GameState>>) {
+  if actions.just_pressed(None, Action::Start) {
+    next_state.set(GameState::Playing);
+  }
+}
+
+/// Closes the lobby once the match plays, however it was started.
+pub(super) fn close(
+  mut commands: Commands,
+  q_hud: Query<Entity, With<LobbyHud>>,
+  game_state: Res<State<GameState>>,
+) {
+  if game_state.0 != GameState::Playing {
+    return;
+  }
+  commands.remove_resource::<Lobby>();
+  for hud in &q_hud {
+    commands.entity(hud).despawn_recursive();
+  }
+}
+
+pub(super) fn update_hud(
+  mut q_hud: Query<&mut Text, With<LobbyHud>>,
+  q_player: Query<(&Player, &KeyScheme, &ScoreEntity)>,
+  q_name: Query<&Name>,
+  input_map: Res<InputMap>,
+) {
+  let Ok(mut hud) = q_hud.get_single_mut() else {return};
+  let mut players = q_player.iter().collect::<Vec<_>>();
+  players.sort_by_key(|(player, ..)| player.0);
+  let mut lines = vec!["players".to_string()];
+  lines.extend(players.iter().map(|(_, keys, score)| {
+    let name = q_name.get(score.0).map_or("", |name| name.0.as_str());
+    format!(
+      "  {name:<10} {} {} steer  {} respawn",
+      keys.label(),
+      input_map.steering(**keys).label(),
+      input_map.label(Some(**keys), Action::Respawn)
+    )
+  }));
+  let free = KeyScheme::ALL
+    .into_iter()
+    .filter(|keys| players.iter().all(|(_, taken, _)| *taken != keys))
+    .map(|keys| keys.label())
+    .collect::<Vec<_>>();
+  if !free.is_empty() {
+    lines.push(format!("{} to join", free.join(" / ")));
+  }
+  lines.push(format!("{} to start", input_map.label(None, Action::Start)));
+  let status = lines.join("\n");
+  if hud.sections[0].value != status {
+    hud.sections[0].value = status;
+  }
+}
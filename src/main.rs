@@ -1,61 +1,97 @@
-mod board;
-mod color;
-mod debug;
-mod enemy;
-mod food;
-mod main_camera;
-mod player;
-mod scoreboard;
-mod snake;
-
 use bevy::{
-  prelude::{App, DefaultPlugins, PluginGroup, Window, WindowPlugin},
+  prelude::{App, DefaultPlugins, PluginGroup, State, Window, WindowPlugin},
   window::PresentMode,
 };
+use snake::{
+  bindings, board, bot, color, control, debug, difficulty, enemy, food, lobby, main_camera, net,
+  neural, player, replay, rewind, rollback, scoreboard, simulation, snake::SnakePlugin, snapshot,
+  spectator, state,
+};
 
 fn main() {
-  App::new()
-    .add_plugins(DefaultPlugins.set(WindowPlugin {
-      primary_window: Some(Window {
-        title: "Snake".into(),
-        resolution: (800., 800.).into(),
-        present_mode: PresentMode::AutoVsync,
-        ..Default::default()
-      }),
+  let mut app = App::new();
+  app.add_plugins(DefaultPlugins.set(WindowPlugin {
+    primary_window: Some(Window {
+      title: "Snake".into(),
+      resolution: (800., 800.).into(),
+      present_mode: PresentMode::AutoVsync,
       ..Default::default()
-    }))
+    }),
+    ..Default::default()
+  }));
+
+  let settings = net::resources::ClientSettings::from_args(std::env::args());
+  if settings.server.is_some() {
+    // The server runs the match, it's only shown here.
+    app
+      .add_plugin(main_camera::MainCameraPlugin)
+      .add_plugin(scoreboard::ScoreboardPlugin)
+      .add_plugin(board::BoardPlugin)
+      .add_plugin(bindings::ActionsPlugin)
+      .add_plugin(net::ClientPlugin)
+      .run();
+    return;
+  }
+
+  let settings = rollback::resources::RollbackSettings::from_args(std::env::args());
+  if settings.is_enabled() {
+    let session = match rollback::resources::Session::join(&settings) {
+      Ok(session) => session,
+      Err(err) => {
+        eprintln!("couldn't join the peers: {err}");
+        return;
+      }
+    };
+    // Every peer plays the whole match, only the players' inputs come from elsewhere.
+    let [cols, rows] = session.board;
+    app
+      .insert_resource(simulation::resources::Seed(session.seed))
+      .insert_resource(board::resources::GameBoard::with_cells(cols, rows))
+      .insert_resource(board::resources::FixedBoard)
+      .insert_resource(session)
+      .add_state::<state::GameState>()
+      .insert_resource(State(state::GameState::Playing))
+      .add_plugin(simulation::SimulationPlugin)
+      .add_plugin(main_camera::MainCameraPlugin)
+      .add_plugin(scoreboard::ScoreboardPlugin)
+      .add_plugin(color::ColorPlugin)
+      .add_plugin(board::BoardPlugin)
+      .add_plugin(bindings::ActionsPlugin)
+      .add_plugin(enemy::EnemyPlugin)
+      .add_plugin(difficulty::DifficultyPlugin)
+      .add_plugin(neural::NeuralPlugin)
+      .add_plugin(SnakePlugin)
+      .add_plugin(food::FoodPlugin)
+      .add_plugin(snapshot::SnapshotPlugin)
+      .add_plugin(rollback::RollbackPlugin)
+      .add_plugin(spectator::SpectatorPlugin)
+      .run();
+    return;
+  }
+
+  app
     .add_state::<state::GameState>()
+    .add_plugin(simulation::SimulationPlugin)
     .add_plugin(main_camera::MainCameraPlugin)
     .add_plugin(scoreboard::ScoreboardPlugin)
     .add_plugin(color::ColorPlugin)
     .add_plugin(board::BoardPlugin)
+    .add_plugin(bindings::ActionsPlugin)
+    .add_plugin(bindings::BindingsPlugin)
     .add_plugin(player::PlayerPlugin)
+    .add_plugin(lobby::LobbyPlugin)
     .add_plugin(enemy::EnemyPlugin)
-    .add_plugin(snake::SnakePlugin)
+    .add_plugin(difficulty::DifficultyPlugin)
+    .add_plugin(bot::BotPlugin)
+    .add_plugin(neural::NeuralPlugin)
+    .add_plugin(SnakePlugin)
     .add_plugin(food::FoodPlugin)
     .add_plugin(debug::DebugPlugin)
+    .add_plugin(control::ControlPlugin)
+    .add_plugin(replay::ReplayPlugin)
+    .add_plugin(snapshot::SnapshotPlugin)
+    .add_plugin(snapshot::QuicksavePlugin)
+    .add_plugin(rewind::RewindPlugin)
+    .add_plugin(spectator::SpectatorPlugin)
     .run();
 }
-
-pub mod state {
-  use bevy::prelude::States;
-
-  #[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
-  pub enum GameState {
-    #[default]
-    Paused,
-    Playing,
-  }
-}
-
-mod collections {
-  pub trait ExternalOps {
-    fn add(&self, rhs: Self) -> Self;
-  }
-
-  impl ExternalOps for (f32, f32) {
-    fn add(&self, rhs: Self) -> Self {
-      (self.0 + rhs.0, self.1 + rhs.1)
-    }
-  }
-}
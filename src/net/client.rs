@@ -0,0 +1,294 @@
+use super::{
+  components::ClientHud,
+  protocol::{ClientMessage, NetState, ServerMessage},
+  resources::{Client, SnakeView},
+  CLIENT_STATES, INTERPOLATION_DELAY, JOIN_RETRY, MAX_DATAGRAM, TIMEOUT,
+};
+use crate::{
+  bindings::resources::{Action, Actions},
+  board::{
+    components::{Board, BoardSprite},
+    grid::Grid,
+    resources::GameBoard,
+    utils::create_cell_bundle,
+    CELL_SIZE,
+  },
+  player::components::KeyScheme,
+  scoreboard::{components::Score, utils::spawn_score},
+  simulation::TICK,
+};
+use bevy::{
+  app::AppExit,
+  prelude::{
+    AssetServer, BuildChildren, Color, Commands, DespawnRecursiveExt, Entity, EventReader, IVec2,
+    Query, Res, ResMut, Sprite, TextBundle, TextStyle, Transform, Vec2, Vec3, With, Without,
+  },
+  text::Text,
+  time::Time,
+  ui::{PositionType, Style, UiRect, Val},
+};
+use std::collections::VecDeque;
+
+pub(super) fn receive(mut client: ResMut<Client>, time: Res<Time>) {
+  let now = time.raw_elapsed();
+  let mut buffer = vec![0; MAX_DATAGRAM];
+  // Errors mostly mean the server isn't up yet, the client keeps trying to join.
+  while let Ok(length) = client.socket.recv(&mut buffer) {
+    let Ok(message) = serde_json::from_slice::<ServerMessage>(&buffer[..length]) else {continue};
+    client.last_heard = Some(now);
+    let delta = match message {
+      ServerMessage::Welcome { you } => {
+        client.you = Some(you);
+        continue;
+      }
+      ServerMessage::State(delta) => delta,
+    };
+    let Some(delta) = client.assemble(delta) else {continue};
+    // Datagrams can come out of order, older states are of no use by then.
+    if client
+      .latest()
+      .map_or(false, |latest| latest.tick >= delta.tick)
+    {
+      continue;
+    }
+    let state = match delta.baseline {
+      None => NetState::default().apply(delta),
+      Some(tick) => {
+        let Some(baseline) = client.states.iter().find(|state| state.tick == tick) else {continue};
+        baseline.apply(delta)
+      }
+    };
+
+    // The quickest state to arrive tells best when the server sent it, the offset otherwise only
+    // creeps up to follow the clocks drifting apart.
+    let sample = now.as_secs_f64() - state.tick as f64 * TICK.as_secs_f64();
+    client.offset = Some(match client.offset {
+      Some(offset) if offset < sample => offset + (sample - offset) * 0.01,
+      _ => sample,
+    });
+    if client.states.len() == CLIENT_STATES {
+      client.states.pop_front();
+    }
+    client.states.push_back(state);
+  }
+}
+
+pub(super) fn send_input(mut client: ResMut<Client>, actions: Res<Actions>, time: Res<Time>) {
+  let now = time.raw_elapsed();
+  let message = if client.you.is_some() {
+    ClientMessage::Input {
+      direction: actions.held_turn(KeyScheme::Wasd),
+      respawn: actions.just_pressed(Some(KeyScheme::Wasd), Action::Respawn),
+      ack: client.latest().map(|state| state.tick),
+    }
+  } else if client
+    .last_join
+    .map_or(true, |last_join| now - last_join >= JOIN_RETRY)
+  {
+    client.last_join = Some(now);
+    ClientMessage::Join {
+      name: client.name.clone(),
+    }
+  } else {
+    return;
+  };
+  let Ok(message) = serde_json::to_vec(&message) else {return};
+  // Lost inputs are made up for by the next frame's.
+  let _ = client.socket.send(&message);
+}
+
+/// Gives the board the size of the server's.
+pub(super) fn fit_board(
+  client: Res<Client>,
+  mut game_board: ResMut<GameBoard>,
+  mut q_board_sprite: Query<&mut Sprite, With<BoardSprite>>,
+) {
+  let Some([cols, rows]) = client.latest().map(|state| state.board) else {return};
+  let server_board = GameBoard::with_cells(cols, rows);
+  if game_board.width != server_board.width || game_board.height != server_board.height {
+    *game_board = server_board;
+  }
+  for mut board_sprite in &mut q_board_sprite {
+    let size = Vec2::new(game_board.width, game_board.height);
+    if board_sprite.custom_size != Some(size) {
+      board_sprite.custom_size = Some(size);
+    }
+  }
+}
+
+/// Draws the match as of `INTERPOLATION_DELAY` ago, blending the two states around that time.
+pub(super) fn render(
+  mut commands: Commands,
+  mut client: ResMut<Client>,
+  mut q_cell: Query<(&mut Transform, &mut Sprite), Without<BoardSprite>>,
+  mut q_score: Query<&mut Score>,
+  q_board: Query<Entity, With<Board>>,
+  time: Res<Time>,
+) {
+  let Ok(board) = q_board.get_single() else {return};
+  let Some(offset) = client.offset else {return};
+  let server_time = time.raw_elapsed().as_secs_f64() - offset - INTERPOLATION_DELAY.as_secs_f64();
+  let Some((from, to, blend)) = bracket(&client.states, server_time) else {return};
+  let grid = Grid::new(&GameBoard::with_cells(to.board[0], to.board[1]));
+  let position = |[x, y]: [i32; 2]| grid.position(IVec2::new(x, y));
+
+  let mut snakes = Vec::new();
+  for snake in &to.snakes {
+    let previous = from.snake(snake.id).map(|previous| &previous.cells);
+    let cells = snake
+      .cells
+      .iter()
+      .enumerate()
+      .map(|(i, cell)| {
+        let to = position(*cell);
+        let Some(from) = previous.and_then(|cells| cells.get(i).or(cells.last())) else {return to};
+        let from = position(*from);
+        // Cells wrapping around the board or a snake respawning jump rather than slide across.
+        if from.distance(to) > CELL_SIZE * 2. {
+          to
+        } else {
+          from.lerp(to, blend)
+        }
+      })
+      .collect::<Vec<_>>();
+    snakes.push((snake.clone(), cells));
+  }
+  let food = to
+    .food
+    .iter()
+    .map(|food| (position(food.cell), Color::from(food.kind)))
+    .collect::<Vec<_>>();
+
+  let client = &mut *client;
+  client.snakes.retain(|id, view| {
+    if snakes.iter().any(|(snake, _)| snake.id == *id) {
+      return true;
+    }
+    for cell in &view.cells {
+      commands.entity(*cell).despawn_recursive();
+    }
+    commands.entity(view.score).despawn_recursive();
+    false
+  });
+  for (snake, cells) in snakes {
+    let [r, g, b] = snake.color;
+    let color = Color::rgb(r, g, b);
+    let view = client.snakes.entry(snake.id).or_insert_with(|| SnakeView {
+      cells: Vec::new(),
+      score: spawn_score(&mut commands, snake.score, snake.name.clone(), color),
+    });
+    if let Ok(mut score) = q_score.get_mut(view.score) {
+      if score.0 != snake.score {
+        score.0 = snake.score;
+      }
+    }
+    let cells = cells
+      .into_iter()
+      .map(|cell| (cell, color))
+      .collect::<Vec<_>>();
+    sync_cells(&mut commands, board, &mut view.cells, &cells, &mut q_cell);
+  }
+  sync_cells(&mut commands, board, &mut client.food, &food, &mut q_cell);
+}
+
+/// States on either side of `time`, in seconds of the server's clock, and how far between them it
+/// is, the nearest state twice when none is on the other side.
+fn bracket(states: &VecDeque<NetState>, time: f64) -> Option<(&NetState, &NetState, f32)> {
+  let seconds = |state: &NetState| state.tick as f64 * TICK.as_secs_f64();
+  let Some(to) = states.iter().position(|state| seconds(state) > time) else {
+    let latest = states.back()?;
+    return Some((latest, latest, 0.));
+  };
+  let Some(from) = to.checked_sub(1) else {
+    return Some((&states[to], &states[to], 0.));
+  };
+  let (from, to) = (&states[from], &states[to]);
+  let blend = (time - seconds(from)) / (seconds(to) - seconds(from));
+  Some((from, to, blend as f32))
+}
+
+/// Moves the sprites to the cells given, spawning or despawning some so there's one per cell.
+fn sync_cells(
+  commands: &mut Commands,
+  board: Entity,
+  sprites: &mut Vec<Entity>,
+  cells: &[(Vec3, Color)],
+  q_cell: &mut Query<(&mut Transform, &mut Sprite), Without<BoardSprite>>,
+) {
+  for sprite in sprites.drain(cells.len().min(sprites.len())..) {
+    commands.entity(sprite).despawn_recursive();
+  }
+  for (sprite, (position, color)) in sprites.iter().zip(cells) {
+    let Ok((mut transform, mut sprite)) = q_cell.get_mut(*sprite) else {continue};
+    transform.translation = *position;
+    sprite.color = *color;
+  }
+  for (position, color) in &cells[sprites.len()..] {
+    let mut cell = create_cell_bundle(*color, 0., 0.);
+    cell.transform.translation = *position;
+    let sprite = commands.spawn(cell).id();
+    commands.entity(board).add_child(sprite);
+    sprites.push(sprite);
+  }
+}
+
+pub(super) fn leave(mut exit_reader: EventReader<AppExit>, client: Res<Client>) {
+  if exit_reader.iter().last().is_none() {
+    return;
+  }
+  let Ok(message) = serde_json::to_vec(&ClientMessage::Leave) else {return};
+  let _ = client.socket.send(&message);
+}
+
+pub(super) fn spawn_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+  commands.spawn((
+    ClientHud,
+    TextBundle::from_section(
+      "",
+      TextStyle {
+        font: asset_server.load("fonts/UbuntuMono-Regular.ttf"),
+        font_size: 16.,
+        color: Color::WHITE,
+      },
+    )
+    .with_style(Style {
+      position_type: PositionType::Absolute,
+      position: UiRect {
+        left: Val::Px(10.),
+        bottom: Val::Px(10.),
+        ..Default::default()
+      },
+      ..Default::default()
+    }),
+  ));
+}
+
+pub(super) fn update_hud(
+  mut q_hud: Query<&mut Text, With<ClientHud>>,
+  client: Option<Res<Client>>,
+  time: Res<Time>,
+) {
+  let Ok(mut hud) = q_hud.get_single_mut() else {return};
+  let status = match client {
+    None => "not connected".to_string(),
+    Some(client) => {
+      let silent = client.last_heard.map_or(true, |last_heard| {
+        time.raw_elapsed() - last_heard >= TIMEOUT
+      });
+      let living = client
+        .latest()
+        .zip(client.you)
+        .and_then(|(state, you)| state.snake(you))
+        .map(|snake| snake.living);
+      match (client.you, living) {
+        (None, _) => format!("joining {}", client.server),
+        (Some(_), _) if silent => format!("lost {}", client.server),
+        (Some(_), Some(false)) => format!("{}  r respawn", client.name),
+        (Some(_), _) => format!("{}  wasd steer", client.name),
+      }
+    }
+  };
+  if hud.sections[0].value != status {
+    hud.sections[0].value = status;
+  }
+}
@@ -0,0 +1,272 @@
+mod client;
+pub mod protocol;
+mod server;
+
+use crate::simulation::{conditions::every, SimulationSet};
+use bevy::prelude::{
+  resource_exists, warn, App, CoreSchedule, CoreSet, IntoSystemAppConfig, IntoSystemAppConfigs,
+  IntoSystemConfig, IntoSystemConfigs, Plugin,
+};
+use std::time::Duration;
+
+pub const DEFAULT_SERVER_ADDRESS: &str = "127.0.0.1:4000";
+/// Simulated time between two states sent to clients.
+pub const SEND_INTERVAL: Duration = Duration::from_millis(50);
+/// States the server keeps to diff against, clients acknowledging older ones get a whole state.
+pub const STATE_HISTORY: usize = 64;
+/// States a client keeps to interpolate between and to apply deltas to.
+pub const CLIENT_STATES: usize = 32;
+/// Silence after which the server drops a client, or a client gives up on the server.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+pub const JOIN_RETRY: Duration = Duration::from_secs(1);
+/// How far behind the latest state clients show the match, so they have states to blend on
+/// either side of it.
+pub const INTERPOLATION_DELAY: Duration = Duration::from_millis(100);
+pub const MAX_DATAGRAM: usize = 65_507;
+pub const MAX_NAME_LENGTH: usize = 16;
+
+pub(super) const INITIAL_REMOTE_LENGTH: usize = 4;
+
+/// Runs the match for clients connecting over UDP, each steering a snake of its own, and sends
+/// them the state every `SEND_INTERVAL`.
+pub struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_event::<events::RespawnRemotePlayer>()
+      .add_event::<events::RemotePlayerLeft>()
+      .add_systems(
+        (
+          server::receive,
+          server::drop_silent_clients,
+          server::remove_players,
+          server::respawn,
+        )
+          .chain()
+          .before(SimulationSet::Player)
+          .distributive_run_if(resource_exists::<resources::Server>())
+          .in_schedule(CoreSchedule::FixedUpdate),
+      )
+      .add_system(
+        server::broadcast
+          .run_if(resource_exists::<resources::Server>())
+          .run_if(every(SEND_INTERVAL))
+          .in_set(SimulationSet::Snapshot)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      );
+  }
+}
+
+/// Shows a match run by a server given with `--connect <address>`, steering a snake of its own
+/// named with `--name <name>`.
+pub struct ClientPlugin;
+
+impl Plugin for ClientPlugin {
+  fn build(&self, app: &mut App) {
+    let settings = resources::ClientSettings::from_args(std::env::args());
+    if let Some(address) = &settings.server {
+      match resources::Client::connect(address, settings.name.clone()) {
+        Ok(client) => {
+          app.insert_resource(client);
+        }
+        Err(err) => warn!("Couldn't connect to {address}: {err}"),
+      }
+    }
+
+    let connected = resource_exists::<resources::Client>;
+    app
+      .insert_resource(settings)
+      .add_startup_system(client::spawn_hud)
+      .add_systems(
+        (
+          client::receive,
+          client::send_input,
+          client::fit_board,
+          client::render,
+        )
+          .chain()
+          .distributive_run_if(connected()),
+      )
+      .add_system(client::update_hud.after(client::render))
+      .add_system(client::leave.run_if(connected()).in_base_set(CoreSet::Last));
+  }
+}
+
+pub mod components {
+  use bevy::prelude::Component;
+
+  /// Snake of a client connected to the server.
+  #[derive(Debug, Component)]
+  pub struct RemotePlayer;
+
+  #[derive(Debug, Component)]
+  pub struct ClientHud;
+}
+
+pub mod resources {
+  use super::{
+    protocol::{Delta, NetState},
+    DEFAULT_SERVER_ADDRESS,
+  };
+  use bevy::prelude::{Entity, Resource};
+  use std::{
+    collections::{BTreeMap, VecDeque},
+    io,
+    net::{SocketAddr, UdpSocket},
+    time::Duration,
+  };
+
+  #[derive(Debug, Resource)]
+  pub struct ClientSettings {
+    /// Address of the server to join, the game runs locally without one.
+    pub server: Option<String>,
+    pub name: String,
+  }
+
+  impl ClientSettings {
+    /// Reads `--connect <address>` and `--name <name>` arguments, `--connect` without an address
+    /// joining a server on this machine.
+    pub fn from_args<I: Iterator<Item = String>>(args: I) -> Self {
+      let mut settings = Self {
+        server: None,
+        name: "Player".to_string(),
+      };
+      let mut args = args.peekable();
+      while let Some(arg) = args.next() {
+        match arg.as_str() {
+          "--connect" => {
+            let address = args.next_if(|address| !address.starts_with("--"));
+            settings.server = Some(address.unwrap_or_else(|| DEFAULT_SERVER_ADDRESS.to_string()));
+          }
+          "--name" => {
+            if let Some(name) = args.next() {
+              settings.name = name;
+            }
+          }
+          _ => {}
+        }
+      }
+      settings
+    }
+  }
+
+  /// Client known to the server.
+  #[derive(Debug)]
+  pub struct RemoteClient {
+    pub snake: Entity,
+    /// Latest state the client said it received.
+    pub acked: Option<u64>,
+    /// Simulated time the client was last heard from.
+    pub last_heard: Duration,
+  }
+
+  #[derive(Debug, Resource)]
+  pub struct Server {
+    pub clients: BTreeMap<SocketAddr, RemoteClient>,
+    pub(super) socket: UdpSocket,
+    /// States sent, oldest first.
+    pub(super) history: VecDeque<NetState>,
+    /// Clients that ever joined, to pick their colors.
+    pub(super) joined: usize,
+  }
+
+  impl Server {
+    pub fn bind(address: &str) -> io::Result<Self> {
+      let socket = UdpSocket::bind(address)?;
+      socket.set_nonblocking(true)?;
+      Ok(Self {
+        clients: BTreeMap::new(),
+        socket,
+        history: VecDeque::new(),
+        joined: 0,
+      })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+      self.socket.local_addr()
+    }
+  }
+
+  /// Cells drawn for a snake of the server's match.
+  #[derive(Debug)]
+  pub(super) struct SnakeView {
+    pub(super) cells: Vec<Entity>,
+    pub(super) score: Entity,
+  }
+
+  #[derive(Debug, Resource)]
+  pub struct Client {
+    pub server: String,
+    pub name: String,
+    /// Id of the client's snake, once the server welcomed it.
+    pub you: Option<u64>,
+    /// States received, oldest first.
+    pub states: VecDeque<NetState>,
+    pub(super) socket: UdpSocket,
+    /// Real time the server was last heard from.
+    pub(super) last_heard: Option<Duration>,
+    pub(super) last_join: Option<Duration>,
+    /// Real time minus the server's time, as of the quickest state to arrive.
+    pub(super) offset: Option<f64>,
+    pub(super) snakes: BTreeMap<u64, SnakeView>,
+    pub(super) food: Vec<Entity>,
+    /// Delta split across datagrams being put back together, with the parts received.
+    pub(super) partial: Option<(Delta, Vec<usize>)>,
+  }
+
+  impl Client {
+    pub fn connect(server: &str, name: String) -> io::Result<Self> {
+      let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+      socket.connect(server)?;
+      socket.set_nonblocking(true)?;
+      Ok(Self {
+        server: server.to_string(),
+        name,
+        you: None,
+        states: VecDeque::new(),
+        socket,
+        last_heard: None,
+        last_join: None,
+        offset: None,
+        snakes: BTreeMap::new(),
+        food: Vec::new(),
+        partial: None,
+      })
+    }
+
+    pub fn latest(&self) -> Option<&NetState> {
+      self.states.back()
+    }
+
+    /// The whole delta once all of its parts came in, parts of an earlier one are given up on.
+    pub(super) fn assemble(&mut self, part: Delta) -> Option<Delta> {
+      let Some((index, count)) = part.part else {return Some(part)};
+      match &mut self.partial {
+        Some((delta, received)) if delta.tick == part.tick => {
+          if received.contains(&index) {
+            return None;
+          }
+          received.push(index);
+          delta.merge(part);
+        }
+        _ => self.partial = Some((part, vec![index])),
+      }
+      if self.partial.as_ref()?.1.len() < count {
+        return None;
+      }
+      let (mut delta, _) = self.partial.take()?;
+      delta.part = None;
+      Some(delta)
+    }
+  }
+}
+
+pub mod events {
+  use bevy::prelude::Entity;
+
+  pub struct RespawnRemotePlayer(pub Entity);
+
+  /// A client left or went silent, its snake goes with it.
+  pub struct RemotePlayerLeft(pub Entity);
+}
@@ -0,0 +1,275 @@
+use crate::{observation::FoodState, snake::components::Direction};
+use serde::{Deserialize, Serialize};
+
+/// Sent by a client to the server, one JSON object per datagram.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+  /// Asks for a snake, sent again until the server welcomes the client.
+  Join {
+    name: String,
+  },
+  /// Sent every frame, it also keeps the client from timing out.
+  Input {
+    /// Direction key held, if any.
+    direction: Option<Direction>,
+    /// Whether the respawn key was pressed since the last input.
+    respawn: bool,
+    /// Tick of the latest state received, the next one is sent relative to it.
+    ack: Option<u64>,
+  },
+  Leave,
+}
+
+/// Sent by the server to a client, one JSON object per datagram.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+  /// Answer to a join, with the id of the client's snake.
+  Welcome {
+    you: u64,
+  },
+  State(Delta),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetSnake {
+  pub id: u64,
+  pub name: String,
+  /// RGB components.
+  pub color: [f32; 3],
+  /// Whether a player steers it rather than the game.
+  pub human: bool,
+  pub living: bool,
+  pub score: usize,
+  /// Head first, empty once the snake disappeared.
+  pub cells: Vec<[i32; 2]>,
+}
+
+/// What clients get to see of the match at a tick, in cells with (0, 0) at the bottom left corner.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetState {
+  pub tick: u64,
+  /// Columns and rows.
+  pub board: [i32; 2],
+  /// Sorted by id.
+  pub snakes: Vec<NetSnake>,
+  pub food: Vec<FoodState>,
+}
+
+/// A state as the changes since one the client already has.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delta {
+  pub tick: u64,
+  /// Tick of the state it's relative to, `None` when it holds the whole state.
+  pub baseline: Option<u64>,
+  pub board: [i32; 2],
+  /// Snakes that appeared or changed.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub snakes: Vec<NetSnake>,
+  /// Ids of the snakes that are gone.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub removed: Vec<u64>,
+  /// All of the food, when any of it changed.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub food: Option<Vec<FoodState>>,
+  /// Index of the part and how many the delta was split into, when it doesn't fit in a datagram.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub part: Option<(usize, usize)>,
+}
+
+impl NetState {
+  pub fn snake(&self, id: u64) -> Option<&NetSnake> {
+    let index = self
+      .snakes
+      .binary_search_by_key(&id, |snake| snake.id)
+      .ok()?;
+    self.snakes.get(index)
+  }
+
+  /// Changes from `baseline`, the whole state without one.
+  pub fn delta(&self, baseline: Option<&NetState>) -> Delta {
+    let empty = NetState::default();
+    let base = baseline.unwrap_or(&empty);
+    Delta {
+      tick: self.tick,
+      baseline: baseline.map(|baseline| baseline.tick),
+      board: self.board,
+      snakes: self
+        .snakes
+        .iter()
+        .filter(|snake| base.snake(snake.id) != Some(*snake))
+        .cloned()
+        .collect(),
+      removed: base
+        .snakes
+        .iter()
+        .filter(|snake| self.snake(snake.id).is_none())
+        .map(|snake| snake.id)
+        .collect(),
+      food: (baseline.is_none() || self.food != base.food).then(|| self.food.clone()),
+      part: None,
+    }
+  }
+
+  /// State `delta` leads to, this one being its baseline.
+  pub fn apply(&self, delta: Delta) -> NetState {
+    let mut snakes = self
+      .snakes
+      .iter()
+      .filter(|snake| !delta.removed.contains(&snake.id))
+      .filter(|snake| delta.snakes.iter().all(|changed| changed.id != snake.id))
+      .cloned()
+      .collect::<Vec<_>>();
+    snakes.extend(delta.snakes);
+    snakes.sort_by_key(|snake| snake.id);
+    NetState {
+      tick: delta.tick,
+      board: delta.board,
+      snakes,
+      food: delta.food.unwrap_or_else(|| self.food.clone()),
+    }
+  }
+}
+
+impl Delta {
+  /// The delta in `count` parts with the snakes spread across them, the first one also carries
+  /// the removed snakes and the food.
+  pub fn split(self, count: usize) -> Vec<Delta> {
+    let mut parts = (0..count)
+      .map(|index| Delta {
+        tick: self.tick,
+        baseline: self.baseline,
+        board: self.board,
+        snakes: Vec::new(),
+        removed: Vec::new(),
+        food: None,
+        part: Some((index, count)),
+      })
+      .collect::<Vec<_>>();
+    for (index, snake) in self.snakes.into_iter().enumerate() {
+      parts[index % count].snakes.push(snake);
+    }
+    parts[0].removed = self.removed;
+    parts[0].food = self.food;
+    parts
+  }
+
+  /// Takes in another part of the same delta.
+  pub fn merge(&mut self, part: Delta) {
+    self.snakes.extend(part.snakes);
+    self.removed.extend(part.removed);
+    self.food = self.food.take().or(part.food);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Delta, NetSnake, NetState};
+  use crate::{food::components::Food, observation::FoodState};
+
+  fn snake(id: u64, cells: Vec<[i32; 2]>) -> NetSnake {
+    NetSnake {
+      id,
+      name: format!("Snake {id}"),
+      color: [1., 0., 0.],
+      human: id == 1,
+      living: !cells.is_empty(),
+      score: cells.len(),
+      cells,
+    }
+  }
+
+  fn state(tick: u64, snakes: Vec<NetSnake>, food: Vec<FoodState>) -> NetState {
+    NetState {
+      tick,
+      board: [20, 20],
+      snakes,
+      food,
+    }
+  }
+
+  /// As a client gets it, through JSON.
+  fn sent(delta: Delta) -> Delta {
+    serde_json::from_str(&serde_json::to_string(&delta).unwrap()).unwrap()
+  }
+
+  fn food(cell: [i32; 2]) -> Vec<FoodState> {
+    vec![FoodState {
+      kind: Food::Regular,
+      cell,
+    }]
+  }
+
+  #[test]
+  fn whole_state_without_baseline() {
+    let next = state(
+      7,
+      vec![snake(1, vec![[1, 1], [1, 0]]), snake(2, vec![[5, 5]])],
+      food([3, 3]),
+    );
+    let delta = next.delta(None);
+    assert_eq!(delta.baseline, None);
+    assert_eq!(NetState::default().apply(sent(delta)), next);
+  }
+
+  #[test]
+  fn changes_since_baseline() {
+    let baseline = state(
+      7,
+      vec![
+        snake(1, vec![[1, 1], [1, 0]]),
+        snake(2, vec![[5, 5]]),
+        snake(3, vec![[9, 9]]),
+      ],
+      food([3, 3]),
+    );
+    let next = state(
+      8,
+      vec![
+        snake(1, vec![[1, 1], [1, 0]]),
+        snake(2, vec![[5, 6]]),
+        snake(4, vec![[0, 0]]),
+      ],
+      food([3, 3]),
+    );
+    let delta = next.delta(Some(&baseline));
+    assert_eq!(delta.baseline, Some(7));
+    let changed = delta
+      .snakes
+      .iter()
+      .map(|snake| snake.id)
+      .collect::<Vec<_>>();
+    assert_eq!(changed, [2, 4]);
+    assert_eq!(delta.removed, [3]);
+    assert_eq!(delta.food, None);
+    assert_eq!(baseline.apply(sent(delta)), next);
+
+    let eaten = state(9, next.snakes.clone(), food([8, 2]));
+    let delta = eaten.delta(Some(&next));
+    assert!(delta.snakes.is_empty() && delta.removed.is_empty());
+    assert_eq!(delta.food, Some(food([8, 2])));
+    assert_eq!(next.apply(sent(delta)), eaten);
+  }
+
+  #[test]
+  fn parts_merge_back() {
+    let baseline = state(
+      7,
+      vec![snake(1, vec![[1, 1]]), snake(9, vec![[4, 4]])],
+      food([3, 3]),
+    );
+    let next = state(
+      8,
+      (2..7).map(|id| snake(id, vec![[id as i32, 0]])).collect(),
+      food([8, 2]),
+    );
+    let mut parts = next.delta(Some(&baseline)).split(3).into_iter().map(sent);
+    let mut delta = parts.next().unwrap();
+    assert_eq!(delta.part, Some((0, 3)));
+    for part in parts {
+      delta.merge(part);
+    }
+    assert_eq!(baseline.apply(delta), next);
+  }
+}
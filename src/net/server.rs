@@ -0,0 +1,371 @@
+use super::{
+  components::RemotePlayer,
+  events::{RemotePlayerLeft, RespawnRemotePlayer},
+  protocol::{ClientMessage, Delta, NetSnake, NetState, ServerMessage},
+  resources::{RemoteClient, Server},
+  INITIAL_REMOTE_LENGTH, MAX_DATAGRAM, MAX_NAME_LENGTH, STATE_HISTORY, TIMEOUT,
+};
+use crate::{
+  board::{components::Board, grid::Grid, resources::GameBoard},
+  color::components::Brightness,
+  food::components::Food,
+  observation::{snake_id, FoodState},
+  player::{
+    components::{DirectionQueue, Player},
+    PLAYER_COLORS,
+  },
+  scoreboard::components::{Name, Score, ScoreEntity},
+  simulation::resources::{GameRng, SimulationClock},
+  snake::{
+    components::{
+      Direction, Living, Snake, SnakeBody, SnakeBundle, SnakeConfig, SnakeSegment, Speed,
+    },
+    utils::{despawn_snake, revive_snake},
+  },
+};
+use bevy::{
+  ecs::system::SystemParam,
+  prelude::{
+    info, warn, BuildChildren, Commands, Entity, EventReader, EventWriter, IVec2, Local, Or, Query,
+    Res, ResMut, Sprite, Transform, Visibility, With, Without,
+  },
+};
+use rand::Rng;
+use std::{io::ErrorKind, net::SocketAddr};
+
+pub(super) fn receive(
+  mut commands: Commands,
+  mut server: ResMut<Server>,
+  mut q_remote: Query<(&mut Direction, &mut DirectionQueue), With<RemotePlayer>>,
+  mut respawn_writer: EventWriter<RespawnRemotePlayer>,
+  mut left_writer: EventWriter<RemotePlayerLeft>,
+  q_board: Query<Entity, With<Board>>,
+  (game_board, clock, mut rng): (Res<GameBoard>, Res<SimulationClock>, ResMut<GameRng>),
+) {
+  let mut buffer = vec![0; MAX_DATAGRAM];
+  loop {
+    let (length, address) = match server.socket.recv_from(&mut buffer) {
+      Ok(received) => received,
+      // Some platforms report a client that went away on the next read, other datagrams still wait.
+      Err(err) if err.kind() == ErrorKind::ConnectionReset => continue,
+      Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+      Err(err) => {
+        warn!("Couldn't receive from clients: {err}");
+        break;
+      }
+    };
+    let Ok(message) = serde_json::from_slice::<ClientMessage>(&buffer[..length]) else {
+      warn!("Ignoring malformed message from {address}");
+      continue;
+    };
+
+    if let ClientMessage::Join { name } = message {
+      let snake = match server.clients.get_mut(&address) {
+        Some(client) => client.snake,
+        None => {
+          let Ok(board) = q_board.get_single() else {continue};
+          let name = name.chars().take(MAX_NAME_LENGTH).collect::<String>();
+          let snake = (
+            RemotePlayer,
+            DirectionQueue::default(),
+            SnakeBundle::new(
+              &mut commands,
+              board,
+              SnakeConfig {
+                x: (rng.gen::<f32>() - 0.5) * game_board.width,
+                y: (rng.gen::<f32>() - 0.5) * game_board.height,
+                color: PLAYER_COLORS[server.joined % PLAYER_COLORS.len()],
+                tail_length: INITIAL_REMOTE_LENGTH,
+                name: name.clone(),
+                ..Default::default()
+              },
+            ),
+          );
+          let snake = commands.spawn(snake).id();
+          commands.entity(board).add_child(snake);
+          info!("{name} joined from {address}");
+          server.joined += 1;
+          server.clients.insert(
+            address,
+            RemoteClient {
+              snake,
+              acked: None,
+              last_heard: clock.elapsed,
+            },
+          );
+          snake
+        }
+      };
+      let you = snake_id(snake);
+      if let Ok(welcome) = serde_json::to_vec(&ServerMessage::Welcome { you }) {
+        send(&server, address, &welcome);
+      }
+      continue;
+    }
+
+    let Some(client) = server.clients.get_mut(&address) else {continue};
+    client.last_heard = clock.elapsed;
+    match message {
+      ClientMessage::Input {
+        direction,
+        respawn,
+        ack,
+      } => {
+        // Datagrams can come out of order, an older acknowledgement doesn't take back a newer one.
+        client.acked = client.acked.max(ack);
+        let snake = client.snake;
+        if let (Some(new_direction), Ok((mut direction, mut direction_queue))) =
+          (direction, q_remote.get_mut(snake))
+        {
+          direction_queue.steer(&mut direction, new_direction);
+        }
+        if respawn {
+          respawn_writer.send(RespawnRemotePlayer(snake));
+        }
+      }
+      ClientMessage::Leave => {
+        let snake = client.snake;
+        server.clients.remove(&address);
+        info!("Client {address} left");
+        left_writer.send(RemotePlayerLeft(snake));
+      }
+      ClientMessage::Join { .. } => {}
+    }
+  }
+}
+
+pub(super) fn drop_silent_clients(
+  mut server: ResMut<Server>,
+  mut left_writer: EventWriter<RemotePlayerLeft>,
+  clock: Res<SimulationClock>,
+) {
+  server.clients.retain(|address, client| {
+    if clock.elapsed.saturating_sub(client.last_heard) < TIMEOUT {
+      return true;
+    }
+    info!("Client {address} timed out");
+    left_writer.send(RemotePlayerLeft(client.snake));
+    false
+  });
+}
+
+pub(super) fn remove_players(
+  mut commands: Commands,
+  mut left_reader: EventReader<RemotePlayerLeft>,
+  q_remote: Query<(&SnakeBody, &ScoreEntity), With<RemotePlayer>>,
+) {
+  for RemotePlayerLeft(snake) in &mut left_reader {
+    let Ok((body, score)) = q_remote.get(*snake) else {continue};
+    despawn_snake(&mut commands, *snake, body, score);
+  }
+}
+
+pub(super) fn respawn(
+  mut commands: Commands,
+  mut respawn_reader: EventReader<RespawnRemotePlayer>,
+  mut q_remote: Query<
+    (&mut Visibility, &mut Transform, &mut Speed, &mut Brightness),
+    (With<RemotePlayer>, Without<Living>),
+  >,
+  game_board: Res<GameBoard>,
+  mut rng: ResMut<GameRng>,
+) {
+  for RespawnRemotePlayer(snake) in &mut respawn_reader {
+    let Ok((mut visibility, mut transform, mut speed, mut brightness)) = q_remote.get_mut(*snake) else {continue};
+    revive_snake(
+      &mut commands,
+      (
+        *snake,
+        &mut visibility,
+        &mut transform,
+        &mut speed,
+        &mut brightness,
+      ),
+      &game_board,
+      &mut rng,
+    );
+  }
+}
+
+/// What clients get to see of the match.
+#[derive(SystemParam)]
+pub(super) struct StateObserver<'w, 's> {
+  q_snake: Query<
+    'w,
+    's,
+    (
+      Entity,
+      &'static Transform,
+      &'static SnakeBody,
+      &'static ScoreEntity,
+      &'static Sprite,
+      &'static Visibility,
+      Option<&'static Living>,
+    ),
+    With<Snake>,
+  >,
+  q_human: Query<'w, 's, (), Or<(With<Player>, With<RemotePlayer>)>>,
+  q_snake_segment: Query<'w, 's, &'static Transform, With<SnakeSegment>>,
+  q_score: Query<'w, 's, (&'static Name, &'static Score)>,
+  q_food: Query<'w, 's, (&'static Food, &'static Transform)>,
+  game_board: Res<'w, GameBoard>,
+  clock: Res<'w, SimulationClock>,
+}
+
+impl<'w, 's> StateObserver<'w, 's> {
+  pub(super) fn state(&self) -> NetState {
+    let grid = Grid::new(&self.game_board);
+    let cell = |transform: &Transform| {
+      let IVec2 { x, y } = grid.cell(transform.translation);
+      [x, y]
+    };
+    let mut snakes = self
+      .q_snake
+      .iter()
+      .map(|(snake, head, body, score, sprite, visibility, living)| {
+        let (name, score) = self
+          .q_score
+          .get(score.0)
+          .map(|(name, score)| (name.0.clone(), score.0))
+          .unwrap_or_default();
+        let cells = if *visibility == Visibility::Hidden {
+          Vec::new()
+        } else {
+          std::iter::once(head)
+            .chain(
+              body
+                .segments()
+                .filter_map(|segment| self.q_snake_segment.get(segment).ok()),
+            )
+            .map(cell)
+            .collect()
+        };
+        NetSnake {
+          id: snake_id(snake),
+          name,
+          color: [sprite.color.r(), sprite.color.g(), sprite.color.b()],
+          human: self.q_human.contains(snake),
+          living: living.is_some(),
+          score,
+          cells,
+        }
+      })
+      .collect::<Vec<_>>();
+    snakes.sort_by_key(|snake| snake.id);
+    NetState {
+      tick: self.clock.tick,
+      board: [grid.cols, grid.rows],
+      snakes,
+      food: self
+        .q_food
+        .iter()
+        .map(|(food, transform)| FoodState {
+          kind: *food,
+          cell: cell(transform),
+        })
+        .collect(),
+    }
+  }
+}
+
+pub(super) fn broadcast(
+  mut server: ResMut<Server>,
+  observer: StateObserver,
+  mut warned: Local<bool>,
+) {
+  let state = observer.state();
+  for (address, client) in &server.clients {
+    // Each client gets what changed since the latest state it has, if that's still kept.
+    let baseline = client
+      .acked
+      .and_then(|tick| server.history.iter().find(|state| state.tick == tick));
+    for datagram in datagrams(state.delta(baseline), &mut warned) {
+      send(&server, *address, &datagram);
+    }
+  }
+  if server.history.len() == STATE_HISTORY {
+    server.history.pop_front();
+  }
+  server.history.push_back(state);
+}
+
+/// `delta` encoded in as few datagrams as it fits in, none if even a single snake doesn't fit.
+fn datagrams(delta: Delta, warned: &mut bool) -> Vec<Vec<u8>> {
+  let mut count = 1;
+  loop {
+    let parts = match count {
+      1 => vec![delta.clone()],
+      _ => delta.clone().split(count),
+    };
+    let Ok(datagrams) = parts
+      .into_iter()
+      .map(|part| serde_json::to_vec(&ServerMessage::State(part)))
+      .collect::<Result<Vec<_>, _>>() else {return Vec::new()};
+    if datagrams
+      .iter()
+      .all(|datagram| datagram.len() <= MAX_DATAGRAM)
+    {
+      if count > 1 && !*warned {
+        *warned = true;
+        warn!("States outgrew a datagram, they're sent in parts");
+      }
+      return datagrams;
+    }
+    if count >= delta.snakes.len() {
+      if !*warned {
+        *warned = true;
+        warn!("A state doesn't fit in datagrams, it isn't sent");
+      }
+      return Vec::new();
+    }
+    count = (count * 2).min(delta.snakes.len());
+  }
+}
+
+fn send(server: &Server, address: SocketAddr, datagram: &[u8]) {
+  if let Err(err) = server.socket.send_to(datagram, address) {
+    warn!("Couldn't send to {address}: {err}");
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::datagrams;
+  use crate::net::{
+    protocol::{NetSnake, NetState, ServerMessage},
+    MAX_DATAGRAM,
+  };
+
+  #[test]
+  fn large_states_are_sent_in_parts() {
+    let state = NetState {
+      tick: 3,
+      board: [200, 200],
+      snakes: (0..40)
+        .map(|id| NetSnake {
+          id,
+          name: format!("Snake {id}"),
+          color: [0., 1., 0.],
+          human: false,
+          living: true,
+          score: 0,
+          cells: (0..200).map(|x| [x, id as i32]).collect(),
+        })
+        .collect(),
+      food: Vec::new(),
+    };
+    let mut warned = false;
+    let datagrams = datagrams(state.delta(None), &mut warned);
+    assert!(warned && datagrams.len() > 1);
+    let mut parts = datagrams.iter().map(|datagram| {
+      assert!(datagram.len() <= MAX_DATAGRAM);
+      match serde_json::from_slice(datagram).unwrap() {
+        ServerMessage::State(part) => part,
+        ServerMessage::Welcome { .. } => unreachable!(),
+      }
+    });
+    let mut delta = parts.next().unwrap();
+    parts.for_each(|part| delta.merge(part));
+    assert_eq!(NetState::default().apply(delta), state);
+  }
+}
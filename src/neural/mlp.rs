@@ -0,0 +1,137 @@
+use crate::env::ObservationKind;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Activation {
+  #[default]
+  Linear,
+  Relu,
+  Tanh,
+  Sigmoid,
+}
+
+impl Activation {
+  fn apply(&self, x: f32) -> f32 {
+    match self {
+      Activation::Linear => x,
+      Activation::Relu => x.max(0.),
+      Activation::Tanh => x.tanh(),
+      Activation::Sigmoid => 1. / (1. + (-x).exp()),
+    }
+  }
+}
+
+/// Fully connected layer, `weights` holds one row of input weights per output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Layer {
+  pub weights: Vec<Vec<f32>>,
+  pub biases: Vec<f32>,
+  #[serde(default)]
+  pub activation: Activation,
+}
+
+/// Multi-layer perceptron as exported by training, along with the observation it was fed.
+///
+/// Three outputs score turning left, going ahead and turning right, four score moving to the
+/// top, right, bottom and left.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mlp {
+  pub observation: ObservationKind,
+  pub layers: Vec<Layer>,
+}
+
+impl Mlp {
+  pub fn inputs(&self) -> usize {
+    self
+      .layers
+      .first()
+      .and_then(|layer| layer.weights.first())
+      .map_or(0, Vec::len)
+  }
+
+  pub fn outputs(&self) -> usize {
+    self.layers.last().map_or(0, |layer| layer.biases.len())
+  }
+
+  /// Checks every layer fits the previous one, describing the first mismatch.
+  pub fn validate(&self) -> Result<(), String> {
+    if self.layers.is_empty() {
+      return Err("no layers".to_string());
+    }
+    let mut inputs = self.inputs();
+    for (i, layer) in self.layers.iter().enumerate() {
+      if layer.weights.len() != layer.biases.len() {
+        return Err(format!(
+          "layer {i} has {} weight rows for {} biases",
+          layer.weights.len(),
+          layer.biases.len()
+        ));
+      }
+      if let Some(row) = layer.weights.iter().find(|row| row.len() != inputs) {
+        return Err(format!(
+          "layer {i} expects {inputs} inputs, a row has {}",
+          row.len()
+        ));
+      }
+      inputs = layer.biases.len();
+    }
+    match self.outputs() {
+      3 | 4 => Ok(()),
+      outputs => Err(format!("{outputs} outputs, expected 3 or 4")),
+    }
+  }
+
+  pub fn forward(&self, input: &[f32]) -> Vec<f32> {
+    self.layers.iter().fold(input.to_vec(), |input, layer| {
+      layer
+        .weights
+        .iter()
+        .zip(&layer.biases)
+        .map(|(row, bias)| {
+          let sum = row.iter().zip(&input).map(|(w, x)| w * x).sum::<f32>();
+          layer.activation.apply(sum + bias)
+        })
+        .collect()
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn layer(inputs: usize, outputs: usize) -> Layer {
+    Layer {
+      weights: vec![vec![0.5; inputs]; outputs],
+      biases: vec![0.; outputs],
+      activation: Activation::Relu,
+    }
+  }
+
+  fn mlp(layers: Vec<Layer>) -> Mlp {
+    Mlp {
+      observation: ObservationKind::Egocentric,
+      layers,
+    }
+  }
+
+  #[test]
+  fn accepts_layers_that_fit() {
+    let mlp = mlp(vec![layer(11, 8), layer(8, 3)]);
+    assert_eq!(mlp.validate(), Ok(()));
+    assert_eq!((mlp.inputs(), mlp.outputs()), (11, 3));
+    assert_eq!(mlp.forward(&[1.; 11]), vec![22.; 3]);
+  }
+
+  #[test]
+  fn rejects_mismatched_networks() {
+    assert!(mlp(Vec::new()).validate().is_err());
+    // The second layer expects more inputs than the first one gives.
+    assert!(mlp(vec![layer(11, 8), layer(6, 3)]).validate().is_err());
+    assert!(mlp(vec![layer(11, 5)]).validate().is_err());
+    let mut uneven = layer(11, 4);
+    uneven.biases.pop();
+    assert!(mlp(vec![uneven]).validate().is_err());
+  }
+}
@@ -0,0 +1,63 @@
+pub mod mlp;
+mod systems;
+
+use crate::{simulation::SimulationSet, snake::SnakeSystem};
+use bevy::prelude::{App, CoreSchedule, IntoSystemAppConfig, IntoSystemConfig, Plugin};
+
+pub struct NeuralPlugin;
+
+impl Plugin for NeuralPlugin {
+  fn build(&self, app: &mut App) {
+    app.init_resource::<resources::Networks>().add_system(
+      systems::think
+        .after(SnakeSystem::Seek)
+        .in_set(SimulationSet::Bot)
+        .in_schedule(CoreSchedule::FixedUpdate),
+    );
+  }
+}
+
+pub mod components {
+  use bevy::prelude::Component;
+
+  /// Snake steered by a network, it falls back to seeking while the network can't be used.
+  #[derive(Debug, Component)]
+  pub struct NeuralBrain {
+    /// Weights file, relative to the config directory.
+    pub network: String,
+  }
+}
+
+pub mod resources {
+  use super::mlp::Mlp;
+  use crate::config::{self, CONFIG_DIR};
+  use bevy::prelude::{warn, Resource};
+  use std::{collections::HashMap, path::Path, sync::Arc};
+
+  /// Networks loaded so far by file, `None` for files that couldn't be used.
+  #[derive(Debug, Resource, Default)]
+  pub struct Networks(HashMap<String, Option<Arc<Mlp>>>);
+
+  impl Networks {
+    pub fn get(&mut self, network: &str) -> Option<Arc<Mlp>> {
+      self
+        .0
+        .entry(network.to_string())
+        .or_insert_with(|| {
+          let path = Path::new(CONFIG_DIR).join(network);
+          let mlp = config::load::<Mlp>(&path)?;
+          if let Err(err) = mlp.validate() {
+            warn!("Ignoring network {}: {err}", path.display());
+            return None;
+          }
+          Some(Arc::new(mlp))
+        })
+        .clone()
+    }
+
+    /// Stops using a network, its snakes go back to seeking.
+    pub fn reject(&mut self, network: &str) {
+      self.0.insert(network.to_string(), None);
+    }
+  }
+}
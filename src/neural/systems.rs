@@ -0,0 +1,74 @@
+use super::{components::NeuralBrain, resources::Networks};
+use crate::{
+  env::{
+    features::{egocentric, grid_tensor, heading},
+    ObservationKind,
+  },
+  observation::{snake_id, BoardObserver},
+  snake::{components::Direction, events::Serpentine},
+};
+use bevy::prelude::{warn, EventReader, ParamSet, Query, ResMut};
+
+pub(super) fn think(
+  mut serpentine_reader: EventReader<Serpentine>,
+  mut set: ParamSet<(Query<(&NeuralBrain, &mut Direction)>, BoardObserver)>,
+  mut networks: ResMut<Networks>,
+) {
+  let thinkers = serpentine_reader
+    .iter()
+    .map(|Serpentine(snake, _)| *snake)
+    .filter(|snake| set.p0().contains(*snake))
+    .collect::<Vec<_>>();
+  if thinkers.is_empty() {
+    return;
+  }
+
+  let state = set.p1().state();
+  let mut q_brain = set.p0();
+  for snake in thinkers {
+    let Ok((brain, mut direction)) = q_brain.get_mut(snake) else {continue};
+    let Some(mlp) = networks.get(&brain.network) else {continue};
+    let id = snake_id(snake);
+    let Some(you) = state.snakes.iter().find(|snake| snake.id == id) else {continue};
+    // Seeking may already have turned the snake, the network expects the way it last moved.
+    let mut you = you.clone();
+    you.direction = heading(&state, &you).unwrap_or(you.direction);
+    let observation = match mlp.observation {
+      ObservationKind::Grid => grid_tensor(&state, &you),
+      ObservationKind::Egocentric => egocentric(&state, &you),
+    };
+    if observation.len() != mlp.inputs() {
+      warn!(
+        "Ignoring network {}: it takes {} inputs, the board gives {}",
+        brain.network,
+        mlp.inputs(),
+        observation.len()
+      );
+      networks.reject(&brain.network);
+      continue;
+    }
+
+    let scores = mlp.forward(&observation);
+    let moves = match scores.len() {
+      3 => vec![
+        you.direction.counter_clockwise(),
+        you.direction,
+        you.direction.clockwise(),
+      ],
+      _ => vec![
+        Direction::Top,
+        Direction::Right,
+        Direction::Bottom,
+        Direction::Left,
+      ],
+    };
+    let best = moves
+      .into_iter()
+      .zip(scores)
+      .filter(|(next, _)| *next != you.direction.opposite())
+      .max_by(|(_, a), (_, b)| a.total_cmp(b));
+    if let Some((next, _)) = best {
+      *direction = next;
+    }
+  }
+}
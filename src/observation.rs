@@ -0,0 +1,125 @@
+use crate::{
+  board::{grid::Grid, resources::GameBoard},
+  food::components::Food,
+  scoreboard::components::{Name, Score, ScoreEntity},
+  snake::components::{Direction, Living, Snake, SnakeBody, SnakeSegment, Speed},
+};
+use bevy::{
+  ecs::system::SystemParam,
+  prelude::{Entity, IVec2, Query, Res, Transform, With},
+};
+use serde::{Deserialize, Serialize};
+
+/// Plain description of the board, in cells with (0, 0) at the bottom left corner.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardState {
+  pub width: i32,
+  pub height: i32,
+  pub snakes: Vec<SnakeState>,
+  pub food: Vec<FoodState>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnakeState {
+  pub id: u64,
+  pub name: String,
+  pub head: [i32; 2],
+  pub body: Vec<[i32; 2]>,
+  pub direction: Direction,
+  pub length: usize,
+  pub score: usize,
+  /// Time between two moves, lower is faster.
+  pub move_ms: u64,
+  pub living: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FoodState {
+  pub kind: Food,
+  pub cell: [i32; 2],
+}
+
+pub fn snake_id(snake: Entity) -> u64 {
+  snake.to_bits()
+}
+
+#[derive(SystemParam)]
+pub struct BoardObserver<'w, 's> {
+  q_snake: Query<
+    'w,
+    's,
+    (
+      Entity,
+      &'static Transform,
+      &'static Direction,
+      &'static SnakeBody,
+      &'static ScoreEntity,
+      &'static Speed,
+      Option<&'static Living>,
+    ),
+    With<Snake>,
+  >,
+  q_snake_segment: Query<'w, 's, &'static Transform, With<SnakeSegment>>,
+  q_food: Query<'w, 's, (&'static Food, &'static Transform)>,
+  q_name: Query<'w, 's, &'static Name>,
+  q_score: Query<'w, 's, &'static Score>,
+  game_board: Res<'w, GameBoard>,
+}
+
+impl<'w, 's> BoardObserver<'w, 's> {
+  pub fn state(&self) -> BoardState {
+    let grid = Grid::new(&self.game_board);
+    let cell = |transform: &Transform| {
+      let IVec2 { x, y } = grid.cell(transform.translation);
+      [x, y]
+    };
+    BoardState {
+      width: grid.cols,
+      height: grid.rows,
+      snakes: self
+        .q_snake
+        .iter()
+        .map(
+          |(snake, head, direction, body, score, speed, living)| SnakeState {
+            id: snake_id(snake),
+            name: self
+              .q_name
+              .get(score.0)
+              .map(|name| name.0.clone())
+              .unwrap_or_default(),
+            head: cell(head),
+            body: body
+              .segments()
+              .filter_map(|segment| self.q_snake_segment.get(segment).ok())
+              .map(cell)
+              .collect(),
+            direction: *direction,
+            length: body.len(),
+            score: self.q_score.get(score.0).map_or(0, |score| score.0),
+            move_ms: speed.duration().as_millis() as u64,
+            living: living.is_some(),
+          },
+        )
+        .collect(),
+      food: self
+        .q_food
+        .iter()
+        .map(|(food, transform)| FoodState {
+          kind: *food,
+          cell: cell(transform),
+        })
+        .collect(),
+    }
+  }
+
+  /// Cell the head of `snake` is on.
+  pub fn head(&self, snake: Entity) -> Option<[i32; 2]> {
+    let (_, head, ..) = self.q_snake.get(snake).ok()?;
+    let IVec2 { x, y } = Grid::new(&self.game_board).cell(head.translation);
+    Some([x, y])
+  }
+
+  pub fn food(&self, food: Entity) -> Option<Food> {
+    self.q_food.get(food).ok().map(|(food, _)| *food)
+  }
+}
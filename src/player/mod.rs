@@ -1,37 +1,312 @@
 mod systems;
 
-use bevy::prelude::{App, Color, Plugin};
+use crate::{
+  board::CELL_SIZE,
+  replay::resources::Playback,
+  simulation::{AddSimulationEvent, SimulationSet},
+  snake::components::Reflexes,
+};
+use bevy::{
+  ecs::schedule::common_conditions::not,
+  prelude::{
+    apply_system_buffers, resource_exists, App, Color, CoreSchedule, IntoSystemAppConfig,
+    IntoSystemAppConfigs, IntoSystemConfig, IntoSystemConfigs, Plugin,
+  },
+};
 
-pub(super) const PLAYER_COLOR: Color = Color::rgb(115. / 255., 170. / 255., 115. / 255.);
+/// Colors players get by their seat, when several share a match.
+pub const PLAYER_COLORS: [Color; 4] = [
+  Color::rgb(115. / 255., 170. / 255., 115. / 255.),
+  Color::rgb(230. / 255., 220. / 255., 110. / 255.),
+  Color::rgb(100. / 255., 130. / 255., 240. / 255.),
+  Color::rgb(200. / 255., 150. / 255., 120. / 255.),
+];
 pub(super) const INITIAL_PLAYER_LENGTH: usize = 4;
+/// Rows between the snakes of two local players when they're added.
+pub(super) const PLAYER_SPACING: f32 = 3. * CELL_SIZE;
+/// Turns a player can get ahead of their snake, besides the one for its next move.
+pub const MAX_QUEUED_TURNS: usize = 3;
+/// How the autopilot picks its moves, as carefully as the hardest enemies.
+pub const AUTOPILOT_REFLEXES: Reflexes = Reflexes {
+  planning_depth: 64,
+  reaction_delay: 0,
+  mistake_probability: 0.,
+  aggressiveness: 1.,
+};
+/// Cells an assist looks ahead to pick the roomier side, when both are safe.
+pub const ASSIST_LOOKAHEAD: usize = 16;
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
   fn build(&self, app: &mut App) {
+    let live = || not(resource_exists::<Playback>());
     app
       .add_event::<events::RespawnPlayer>()
-      .add_startup_system(systems::spawn)
-      .add_system(systems::respawn)
-      .add_system(systems::queue_input)
-      .add_system(systems::iter_input);
+      .add_simulation_event::<events::AddPlayer>()
+      .add_simulation_event::<events::SetAutopilot>()
+      .init_resource::<resources::PointerCell>()
+      // Entities are spawned in the same order every time, restored snakes depend on it.
+      .add_startup_systems((systems::spawn, systems::spawn_assist_hud).chain())
+      // Keys don't steer a replay being played back.
+      .add_system(systems::queue_input.run_if(live()))
+      .add_system(systems::request_respawn.run_if(live()))
+      .add_system(systems::request_autopilot.run_if(live()))
+      .add_system(systems::aim_pointer.run_if(live()))
+      .add_system(
+        systems::target_pointer
+          .after(systems::aim_pointer)
+          .run_if(live()),
+      )
+      .add_system(systems::configure_assists.run_if(live()))
+      .add_system(systems::update_assist_hud)
+      .add_systems(
+        (
+          systems::add,
+          systems::respawn,
+          systems::set_autopilot,
+          // The autopilot is off before the snakes seek, from the tick a player steers again.
+          apply_system_buffers,
+          systems::iter_input,
+          systems::time_autopilot,
+        )
+          .chain()
+          .in_set(SimulationSet::Player)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      )
+      // Picked once the last move is known, right before the next one. Replays record the cell
+      // aimed at, the turns are picked on playback all the same.
+      .add_system(
+        systems::follow_pointer
+          .after(systems::iter_input)
+          .in_set(SimulationSet::Player)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      )
+      // Replays record the assists players get, a move is checked on playback all the same.
+      .add_system(
+        systems::assist
+          .after(systems::follow_pointer)
+          .after(systems::time_autopilot)
+          .in_set(SimulationSet::Player)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      );
   }
 }
 
 pub mod components {
-  use crate::snake::components::Direction;
-  use bevy::prelude::Component;
+  use super::{AUTOPILOT_REFLEXES, MAX_QUEUED_TURNS};
+  use crate::{
+    enemy::components::{Personality, Target},
+    snake::components::{Direction, Reflexes, Seeker},
+  };
+  use bevy::prelude::{Component, IVec2};
+  use serde::{Deserialize, Serialize};
+  use std::collections::VecDeque;
+
+  /// Snake steered from this machine's keyboard or gamepads, by its seat among the local players.
+  #[derive(Debug, Component, Clone, Copy, PartialEq, Eq)]
+  pub struct Player(pub usize);
+
+  /// Bindings a local player steers and respawns with, see `bindings::resources::InputMap`.
+  #[derive(
+    Debug,
+    Component,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+  )]
+  #[serde(rename_all = "snake_case")]
+  pub enum KeyScheme {
+    #[default]
+    Wasd,
+    Arrows,
+    Ijkl,
+    Numpad,
+  }
+
+  impl KeyScheme {
+    pub const ALL: [Self; 4] = [Self::Wasd, Self::Arrows, Self::Ijkl, Self::Numpad];
+
+    pub fn label(&self) -> &'static str {
+      match self {
+        Self::Wasd => "wasd",
+        Self::Arrows => "arrows",
+        Self::Ijkl => "ijkl",
+        Self::Numpad => "numpad",
+      }
+    }
+  }
+
+  /// Player handed to the enemy AI, until it steers again.
+  #[derive(Debug, Component)]
+  pub struct Autopilot;
+
+  impl Autopilot {
+    /// Everything the enemy AI steers a snake with.
+    pub fn bundle() -> (Self, Personality, Reflexes, Target, Seeker) {
+      (
+        Self,
+        Personality::EATER,
+        AUTOPILOT_REFLEXES,
+        Target::default(),
+        Seeker::default(),
+      )
+    }
+  }
+
+  /// Moves into a snake a player's snake is kept from making this life, see
+  /// `InputMap::assists`.
+  #[derive(Debug, Component, Default, Clone, Copy, PartialEq, Eq)]
+  pub struct Assists {
+    pub per_life: u32,
+    pub used: u32,
+  }
+
+  impl Assists {
+    pub fn left(&self) -> u32 {
+      self.per_life.saturating_sub(self.used)
+    }
+  }
 
   #[derive(Component)]
-  pub struct Player;
+  pub struct AssistHud;
+
+  /// Cell a player's snake heads for while they steer with the pointer, see
+  /// `InputMap::steering`.
+  #[derive(Debug, Component, Default, Clone, Copy, PartialEq, Eq)]
+  pub struct PointerTarget(pub Option<IVec2>);
 
   #[derive(Debug, Component, Default)]
   pub struct DirectionQueue {
-    pub(super) previous: Direction,
-    pub(super) next: Option<Direction>,
+    /// Direction of the last move.
+    pub previous: Direction,
+    /// Turns waiting for the moves after the next one, the oldest first.
+    pub turns: VecDeque<Direction>,
   }
+
+  impl DirectionQueue {
+    /// Turns right away if the snake hasn't turned since its last move, else queues the turn. A
+    /// turn is dropped when it would go back on, or repeat, the direction the snake will have by
+    /// then, or when `MAX_QUEUED_TURNS` are already waiting.
+    pub fn steer(&mut self, direction: &mut Direction, new_direction: Direction) {
+      let then = self.heading(*direction);
+      if new_direction == then || new_direction == then.opposite() {
+        return;
+      }
+      if self.turns.is_empty() && *direction == self.previous {
+        *direction = new_direction;
+      } else if self.turns.len() < MAX_QUEUED_TURNS {
+        self.turns.push_back(new_direction);
+      }
+    }
+
+    /// Direction the snake will have once the queued turns are taken, given the one for its next
+    /// move.
+    pub fn heading(&self, direction: Direction) -> Direction {
+      self.turns.back().copied().unwrap_or(direction)
+    }
+
+    /// Takes the next queued turn once the snake moved.
+    pub fn advance(&mut self, direction: &mut Direction) {
+      self.previous = *direction;
+      // The direction may have been set from elsewhere since the turns were queued.
+      while let Some(next_direction) = self.turns.pop_front() {
+        if next_direction != direction.opposite() {
+          *direction = next_direction;
+          return;
+        }
+      }
+    }
+  }
+
+  #[cfg(test)]
+  mod tests {
+    use super::{DirectionQueue, MAX_QUEUED_TURNS};
+    use crate::snake::components::Direction::{self, *};
+
+    fn moving(direction: Direction) -> (DirectionQueue, Direction) {
+      let queue = DirectionQueue {
+        previous: direction,
+        ..Default::default()
+      };
+      (queue, direction)
+    }
+
+    #[test]
+    fn quick_u_turn_takes_one_move_per_turn() {
+      let (mut queue, mut direction) = moving(Right);
+      queue.steer(&mut direction, Top);
+      queue.steer(&mut direction, Left);
+      assert_eq!(direction, Top);
+      assert_eq!(queue.turns, [Left]);
+
+      queue.advance(&mut direction);
+      assert_eq!(direction, Left);
+      assert_eq!(queue.previous, Top);
+      queue.advance(&mut direction);
+      assert_eq!(direction, Left);
+    }
+
+    #[test]
+    fn reversal_against_queued_heading_is_dropped() {
+      let (mut queue, mut direction) = moving(Right);
+      queue.steer(&mut direction, Top);
+      // Back on the turn not taken yet, though not on the last move.
+      queue.steer(&mut direction, Bottom);
+      assert_eq!(direction, Top);
+      assert!(queue.turns.is_empty());
+
+      queue.steer(&mut direction, Left);
+      queue.steer(&mut direction, Right);
+      queue.steer(&mut direction, Left);
+      assert_eq!(queue.turns, [Left]);
+    }
+
+    #[test]
+    fn turns_past_the_queue_are_dropped() {
+      let (mut queue, mut direction) = moving(Right);
+      queue.steer(&mut direction, Top);
+      for next in [Left, Bottom, Right, Top] {
+        queue.steer(&mut direction, next);
+      }
+      assert_eq!(queue.turns.len(), MAX_QUEUED_TURNS);
+      assert_eq!(queue.turns, [Left, Bottom, Right]);
+
+      for next in [Left, Bottom, Right] {
+        queue.advance(&mut direction);
+        assert_eq!(direction, next);
+      }
+      queue.advance(&mut direction);
+      assert_eq!(direction, Right);
+    }
+  }
+}
+
+pub mod resources {
+  use bevy::prelude::{IVec2, Resource};
+
+  /// Board cell under the cursor or the latest touch, which players steering with the pointer
+  /// head for.
+  #[derive(Debug, Resource, Default)]
+  pub struct PointerCell(pub Option<IVec2>);
 }
 
 pub mod events {
-  pub struct RespawnPlayer;
+  use super::components::KeyScheme;
+
+  /// Brings back the local player of the given seat, if it's dead.
+  pub struct RespawnPlayer(pub usize);
+
+  /// Adds a local player steering with the given keys, in the next free seat.
+  pub struct AddPlayer(pub KeyScheme);
+
+  /// Hands the local player of the given seat to the autopilot, or takes it back.
+  pub struct SetAutopilot(pub usize, pub bool);
 }
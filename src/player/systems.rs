@@ -1,33 +1,79 @@
 use super::{
-  components::{DirectionQueue, Player},
-  events::RespawnPlayer,
-  INITIAL_PLAYER_LENGTH, PLAYER_COLOR,
+  components::{AssistHud, Assists, Autopilot, DirectionQueue, KeyScheme, Player, PointerTarget},
+  events::{AddPlayer, RespawnPlayer, SetAutopilot},
+  resources::PointerCell,
+  ASSIST_LOOKAHEAD, INITIAL_PLAYER_LENGTH, MAX_QUEUED_TURNS, PLAYER_COLORS, PLAYER_SPACING,
 };
 use crate::{
-  board::{components::Board, resources::GameBoard},
+  bindings::resources::{Action, Actions, InputMap, SteeringMode},
+  board::{components::Board, grid::Grid, resources::GameBoard},
   color::components::Brightness,
+  enemy::components::{Personality, Target},
+  main_camera::components::MainCamera,
+  scoreboard::components::{AutopilotTime, ScoreEntity},
+  simulation::resources::GameRng,
   snake::{
-    components::{Direction, Living, SnakeBundle, SnakeConfig, Speed},
+    components::{
+      Direction, Living, Reflexes, Seeker, Snake, SnakeBundle, SnakeConfig, SnakeSegment, Speed,
+    },
     events::Serpentine,
     utils::revive_snake,
   },
 };
-use bevy::prelude::{
-  BuildChildren, Commands, Entity, EventReader, Input, KeyCode, Query, Res, Transform, Visibility,
-  With, Without,
+use bevy::{
+  prelude::{
+    AssetServer, BuildChildren, Camera, Color, Commands, Entity, EventReader, EventWriter,
+    FixedTime, GlobalTransform, PositionType, Query, Res, ResMut, Style, Text, TextBundle,
+    TextStyle, Touches, Transform, UiRect, Val, Vec2, Visibility, With, Without,
+  },
+  window::{PrimaryWindow, Window},
 };
 
 pub(super) fn spawn(mut commands: Commands, q_board: Query<Entity, With<Board>>) {
   let Ok(board) = q_board.get_single() else {return};
+  spawn_player(&mut commands, board, 0, KeyScheme::Wasd);
+}
+
+pub(super) fn add(
+  mut commands: Commands,
+  mut add_player_reader: EventReader<AddPlayer>,
+  q_player: Query<(&Player, &KeyScheme)>,
+  q_board: Query<Entity, With<Board>>,
+) {
+  let Ok(board) = q_board.get_single() else {return};
+  let mut taken = q_player
+    .iter()
+    .map(|(player, keys)| (player.0, *keys))
+    .collect::<Vec<_>>();
+  for AddPlayer(keys) in add_player_reader.iter() {
+    if taken.iter().any(|(_, taken)| taken == keys) {
+      continue;
+    }
+    let seat = (0..PLAYER_COLORS.len()).find(|seat| taken.iter().all(|(taken, _)| taken != seat));
+    let Some(seat) = seat else {continue};
+    taken.push((seat, *keys));
+    spawn_player(&mut commands, board, seat, *keys);
+  }
+}
+
+/// The first seat keeps the name and the spot of the single player game.
+fn spawn_player(commands: &mut Commands, board: Entity, seat: usize, keys: KeyScheme) {
   let player = (
-    Player,
+    Player(seat),
+    keys,
     DirectionQueue::default(),
+    Assists::default(),
+    PointerTarget::default(),
     SnakeBundle::new(
-      &mut commands,
+      commands,
       board,
       SnakeConfig {
-        name: "Player".to_string(),
-        color: PLAYER_COLOR,
+        name: match seat {
+          0 => "Player".to_string(),
+          seat => format!("Player {}", seat + 1),
+        },
+        y: -(seat as f32) * PLAYER_SPACING,
+        color: PLAYER_COLORS[seat],
         tail_length: INITIAL_PLAYER_LENGTH,
         ..Default::default()
       },
@@ -43,17 +89,26 @@ pub(super) fn respawn(
   mut q_player: Query<
     (
       Entity,
+      &Player,
       &mut Visibility,
       &mut Transform,
       &mut Speed,
       &mut Brightness,
+      Option<&mut Assists>,
     ),
-    (With<Player>, Without<Living>),
+    Without<Living>,
   >,
   game_board: Res<GameBoard>,
+  mut rng: ResMut<GameRng>,
 ) {
-  for _ in respawn_reader.iter() {
-    let Ok((player, mut visibility, mut transform, mut speed, mut brightness)) = q_player.get_single_mut() else {return};
+  for RespawnPlayer(seat) in respawn_reader.iter() {
+    let player = q_player
+      .iter_mut()
+      .find(|(_, player, ..)| player.0 == *seat);
+    let Some((player, _, mut visibility, mut transform, mut speed, mut brightness, assists)) = player else {continue};
+    if let Some(mut assists) = assists {
+      assists.used = 0;
+    }
     revive_snake(
       &mut commands,
       (
@@ -64,51 +119,335 @@ pub(super) fn respawn(
         &mut brightness,
       ),
       &game_board,
+      &mut rng,
     );
   }
 }
 
+/// Queues a turn for each press, so quick ones aren't lost between two moves. Relative turns go
+/// from the direction the snake will have by the time they're taken.
 pub(super) fn queue_input(
-  keyboard_input: Res<Input<KeyCode>>,
-  mut q_player: Query<(&mut Direction, &mut DirectionQueue), With<Player>>,
+  actions: Res<Actions>,
+  input_map: Res<InputMap>,
+  mut q_player: Query<(&KeyScheme, &mut Direction, &mut DirectionQueue), With<Player>>,
 ) {
-  let Ok((mut direction, mut direction_queue)) = q_player.get_single_mut() else { return; };
-
-  use Direction::*;
-  let new_direction = if keyboard_input.pressed(KeyCode::W) {
-    Top
-  } else if keyboard_input.pressed(KeyCode::A) {
-    Left
-  } else if keyboard_input.pressed(KeyCode::S) {
-    Bottom
-  } else if keyboard_input.pressed(KeyCode::D) {
-    Right
-  } else {
-    return;
-  };
+  for (keys, mut direction, mut direction_queue) in &mut q_player {
+    match input_map.steering(*keys) {
+      SteeringMode::Absolute => {
+        for new_direction in actions.turns(*keys) {
+          direction_queue.steer(&mut direction, new_direction);
+        }
+      }
+      SteeringMode::Relative => {
+        for (action, clockwise) in [(Action::TurnLeft, false), (Action::TurnRight, true)] {
+          if !actions.just_pressed(Some(*keys), action) {
+            continue;
+          }
+          let heading = direction_queue.heading(*direction);
+          let new_direction = if clockwise {
+            heading.clockwise()
+          } else {
+            heading.counter_clockwise()
+          };
+          direction_queue.steer(&mut direction, new_direction);
+        }
+      }
+      SteeringMode::Pointer => {}
+    }
+  }
+}
 
-  if new_direction == direction.opposite() {
+/// Finds the board cell under the cursor, or under the latest touch, through the camera and
+/// wherever the board was moved to.
+pub(super) fn aim_pointer(
+  mut pointer: ResMut<PointerCell>,
+  q_window: Query<&Window, With<PrimaryWindow>>,
+  q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+  q_board: Query<&GlobalTransform, With<Board>>,
+  touches: Res<Touches>,
+  game_board: Res<GameBoard>,
+) {
+  let (Ok(window), Ok((camera, camera_transform)), Ok(board)) =
+    (q_window.get_single(), q_camera.get_single(), q_board.get_single()) else {return};
+  // Touches are measured from the top of the window, the cursor from its bottom.
+  let touch = touches
+    .iter()
+    .max_by_key(|touch| touch.id())
+    .map(|touch| Vec2::new(touch.position().x, window.height() - touch.position().y));
+  let Some(point) = touch.or_else(|| window.cursor_position()) else {return};
+  let Some(point) = camera.viewport_to_world_2d(camera_transform, point) else {return};
+  let position = board
+    .compute_matrix()
+    .inverse()
+    .transform_point3(point.extend(0.));
+  // Past the edges the board doesn't wrap on screen, the last cell aimed at is kept.
+  if position.x.abs() > game_board.width / 2. || position.y.abs() > game_board.height / 2. {
     return;
   }
+  pointer.0 = Some(Grid::new(&game_board).cell(position));
+}
+
+/// Aims the snakes of the players steering with the pointer at its cell, and no others.
+pub(super) fn target_pointer(
+  pointer: Res<PointerCell>,
+  input_map: Res<InputMap>,
+  mut q_player: Query<(&KeyScheme, &mut PointerTarget)>,
+) {
+  for (keys, mut target) in &mut q_player {
+    let cell = pointer
+      .0
+      .filter(|_| input_map.steering(*keys) == SteeringMode::Pointer);
+    if target.0 != cell {
+      target.0 = cell;
+    }
+  }
+}
+
+/// Turns the snakes of the players steering with the pointer the shortest way to their target
+/// that doesn't run into a snake, if there's one, ties going on straight.
+pub(super) fn follow_pointer(
+  mut q_player: Query<
+    (&PointerTarget, &Transform, &mut Direction, &DirectionQueue),
+    (With<Living>, Without<Autopilot>),
+  >,
+  q_snake: Query<&Transform, (With<Snake>, With<Living>)>,
+  q_snake_segment: Query<&Transform, With<SnakeSegment>>,
+  game_board: Res<GameBoard>,
+) {
+  let mut grid = None;
+  for (target, transform, mut direction, direction_queue) in &mut q_player {
+    let Some(target) = target.0 else {continue};
+    let grid = grid.get_or_insert_with(|| {
+      Grid::with_obstacles(
+        &game_board,
+        q_snake_segment
+          .iter()
+          .chain(&q_snake)
+          .map(|transform| transform.translation),
+      )
+    });
+    let head = grid.cell(transform.translation);
+    if head == target {
+      continue;
+    }
+    let previous = direction_queue.previous;
+    let best = [previous, previous.clockwise(), previous.counter_clockwise()]
+      .into_iter()
+      .min_by_key(|candidate| {
+        let next = grid.neighbour(head, *candidate);
+        (grid.is_blocked(next), grid.distance(next, target))
+      });
+    if let Some(best) = best {
+      *direction = best;
+    }
+  }
+}
+
+pub(super) fn request_respawn(
+  mut respawn_writer: EventWriter<RespawnPlayer>,
+  actions: Res<Actions>,
+  q_player: Query<(&Player, &KeyScheme), Without<Living>>,
+) {
+  for (player, keys) in &q_player {
+    if actions.just_pressed(Some(*keys), Action::Respawn) {
+      respawn_writer.send(RespawnPlayer(player.0));
+    }
+  }
+}
+
+/// Toggles the autopilot with its binding, turning takes the snake back from it.
+pub(super) fn request_autopilot(
+  mut autopilot_writer: EventWriter<SetAutopilot>,
+  actions: Res<Actions>,
+  q_player: Query<(&Player, &KeyScheme, Option<&Autopilot>)>,
+) {
+  for (player, keys, autopilot) in &q_player {
+    let on = autopilot.is_some();
+    let turned = Action::TURNS
+      .into_iter()
+      .any(|(action, _)| actions.just_pressed(Some(*keys), action));
+    if actions.just_pressed(Some(*keys), Action::Autopilot) {
+      autopilot_writer.send(SetAutopilot(player.0, !on));
+    } else if on && turned {
+      autopilot_writer.send(SetAutopilot(player.0, false));
+    }
+  }
+}
+
+pub(super) fn set_autopilot(
+  mut commands: Commands,
+  mut autopilot_reader: EventReader<SetAutopilot>,
+  q_player: Query<(Entity, &Player, Option<&Autopilot>)>,
+) {
+  for SetAutopilot(seat, on) in autopilot_reader.iter() {
+    let player = q_player.iter().find(|(_, player, _)| player.0 == *seat);
+    let Some((player, _, autopilot)) = player else {continue};
+    if *on == autopilot.is_some() {
+      continue;
+    }
+    if *on {
+      commands.entity(player).insert(Autopilot::bundle());
+    } else {
+      commands
+        .entity(player)
+        .remove::<(Autopilot, Personality, Reflexes, Target, Seeker)>();
+    }
+  }
+}
 
-  if *direction == direction_queue.previous {
-    *direction = new_direction;
-  } else {
-    direction_queue.next = Some(new_direction);
+/// Adds up the time each player's snake spends on autopilot, on its score.
+pub(super) fn time_autopilot(
+  mut commands: Commands,
+  q_player: Query<&ScoreEntity, (With<Autopilot>, With<Living>)>,
+  mut q_autopilot_time: Query<&mut AutopilotTime>,
+  fixed_time: Res<FixedTime>,
+) {
+  for score in &q_player {
+    match q_autopilot_time.get_mut(score.0) {
+      Ok(mut autopilot_time) => autopilot_time.0 += fixed_time.period,
+      Err(_) => {
+        commands
+          .entity(score.0)
+          .insert(AutopilotTime(fixed_time.period));
+      }
+    }
   }
 }
 
+/// Moves the queue of every snake steered through one, local or not.
 pub(super) fn iter_input(
   mut serpentine_reader: EventReader<Serpentine>,
-  mut q_player: Query<(&mut Direction, &mut DirectionQueue), With<Player>>,
+  mut q_player: Query<(&mut Direction, &mut DirectionQueue)>,
 ) {
   for snake in &mut serpentine_reader {
     let Ok((mut direction, mut direction_queue)) = q_player.get_mut(snake.0) else {continue};
-    let should_take_next = *direction == direction_queue.previous;
-    direction_queue.previous = *direction;
-    let Some(next_direction) = direction_queue.next.take() else {continue};
-    if should_take_next && next_direction != direction.opposite() {
-      *direction = next_direction;
+    direction_queue.advance(&mut direction);
+  }
+}
+
+/// Gives each player the assists per life of their key scheme.
+pub(super) fn configure_assists(
+  input_map: Res<InputMap>,
+  mut q_player: Query<(&KeyScheme, &mut Assists), With<Player>>,
+) {
+  for (keys, mut assists) in &mut q_player {
+    let per_life = input_map.assists(*keys);
+    if assists.per_life != per_life {
+      assists.per_life = per_life;
     }
   }
 }
+
+/// Keeps a player's snake from moving into a snake, while they have assists left. A turn into
+/// one waits for the next move if going on straight is safe, any move into one is nudged to
+/// a safe side otherwise, the roomier if both are. Nothing is done when every way is blocked.
+pub(super) fn assist(
+  mut q_player: Query<
+    (
+      &Transform,
+      &Speed,
+      &mut Direction,
+      &mut DirectionQueue,
+      &mut Assists,
+    ),
+    (With<Player>, With<Living>, Without<Autopilot>),
+  >,
+  q_snake: Query<&Transform, (With<Snake>, With<Living>)>,
+  q_snake_segment: Query<&Transform, With<SnakeSegment>>,
+  game_board: Res<GameBoard>,
+  fixed_time: Res<FixedTime>,
+) {
+  let mut grid = None;
+  for (transform, speed, mut direction, mut direction_queue, mut assists) in &mut q_player {
+    // Only the move made this tick is checked, the snakes around move until then.
+    if assists.left() == 0 || speed.remaining() > fixed_time.period {
+      continue;
+    }
+    let grid = grid.get_or_insert_with(|| {
+      Grid::with_obstacles(
+        &game_board,
+        q_snake_segment
+          .iter()
+          .chain(&q_snake)
+          .map(|transform| transform.translation),
+      )
+    });
+    let head = grid.cell(transform.translation);
+    let safe = |direction: Direction| !grid.is_blocked(grid.neighbour(head, direction));
+    if safe(*direction) {
+      continue;
+    }
+    let turn = *direction;
+    let previous = direction_queue.previous;
+    let turned = turn != previous && turn != previous.opposite();
+    let sides = if turned {
+      [previous, turn.opposite()]
+    } else {
+      [previous.clockwise(), previous.counter_clockwise()]
+    };
+    // Reversed so that the first side wins a tie, going on straight for a turn.
+    let side = sides
+      .into_iter()
+      .rev()
+      .filter(|side| safe(*side))
+      .max_by_key(|side| grid.open_area(grid.neighbour(head, *side), ASSIST_LOOKAHEAD));
+    let Some(side) = side else {continue};
+    if side == previous {
+      direction_queue.turns.push_front(turn);
+      direction_queue.turns.truncate(MAX_QUEUED_TURNS);
+    }
+    *direction = side;
+    assists.used += 1;
+  }
+}
+
+pub(super) fn spawn_assist_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+  commands.spawn((
+    AssistHud,
+    TextBundle::from_section(
+      "",
+      TextStyle {
+        font: asset_server.load("fonts/UbuntuMono-Regular.ttf"),
+        font_size: 16.,
+        color: Color::WHITE,
+      },
+    )
+    .with_style(Style {
+      position_type: PositionType::Absolute,
+      position: UiRect {
+        left: Val::Px(10.),
+        bottom: Val::Px(40.),
+        ..Default::default()
+      },
+      ..Default::default()
+    }),
+  ));
+}
+
+/// Lists the assists each player has left, for the players who get any.
+pub(super) fn update_assist_hud(
+  mut q_hud: Query<&mut Text, With<AssistHud>>,
+  q_player: Query<(&Player, &Assists)>,
+) {
+  let Ok(mut hud) = q_hud.get_single_mut() else {return};
+  let mut players = q_player
+    .iter()
+    .filter(|(_, assists)| assists.per_life > 0)
+    .collect::<Vec<_>>();
+  players.sort_by_key(|(player, _)| player.0);
+  let status = players
+    .into_iter()
+    .map(|(player, assists)| {
+      format!(
+        "player {} assists {}/{}",
+        player.0 + 1,
+        assists.left(),
+        assists.per_life
+      )
+    })
+    .collect::<Vec<_>>()
+    .join("\n");
+  if hud.sections[0].value != status {
+    hud.sections[0].value = status;
+  }
+}
@@ -0,0 +1,146 @@
+use crate::{
+  difficulty::resources::Difficulty, player::components::KeyScheme, snake::components::Direction,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Version written to new replays, files of any other version are refused.
+pub const REPLAY_VERSION: u32 = 5;
+
+/// A recorded match: its seed and the inputs fed to every tick, enough for the simulation to
+/// play it out again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Replay {
+  pub version: u32,
+  pub seed: u64,
+  /// External bots the match was played with, they take the same seats on playback.
+  pub bots: usize,
+  /// Ticks the recording lasted.
+  pub ticks: u64,
+  /// Ticks where an input changed, in order.
+  pub frames: Vec<Frame>,
+}
+
+/// Snake whose moves are recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Seat {
+  /// Local player, by its seat.
+  Player(usize),
+  /// External bot, by its position among the configured endpoints.
+  Bot(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Steering {
+  pub seat: Seat,
+  pub direction: Direction,
+  /// Turns waiting in the player's direction queue.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub queued: Vec<Direction>,
+  /// Assists per life the player gets, none when left out.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub assists: Option<u32>,
+  /// Cell the player steers towards with the pointer, none when they steer otherwise.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub pointer: Option<[i32; 2]>,
+}
+
+/// Inputs that changed at a tick, anything left out keeps its previous value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Frame {
+  pub tick: u64,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub steering: Vec<Steering>,
+  /// Keys of the local players that joined during the tick, they take the next free seats.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub joined: Vec<KeyScheme>,
+  /// Seats of the players whose respawn was asked for during the tick.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub respawns: Vec<usize>,
+  /// Seats of the players handed to the autopilot during the tick, or taken back from it.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub autopilot: Vec<(usize, bool)>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub playing: Option<bool>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub difficulty: Option<Difficulty>,
+  /// Whether enemies hunt as a squad.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub squad: Option<bool>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub dynamic_difficulty: Option<bool>,
+  /// Width and height of the board.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub board: Option<[f32; 2]>,
+}
+
+impl Frame {
+  pub fn is_empty(&self) -> bool {
+    self.steering.is_empty()
+      && self.joined.is_empty()
+      && self.respawns.is_empty()
+      && self.autopilot.is_empty()
+      && self.playing.is_none()
+      && self.difficulty.is_none()
+      && self.squad.is_none()
+      && self.dynamic_difficulty.is_none()
+      && self.board.is_none()
+  }
+}
+
+/// Value of every input as of the latest frame, joins, respawns and autopilot toggles only count
+/// for the tick at hand.
+#[derive(Debug, Clone, Default)]
+pub struct Inputs {
+  pub steering: BTreeMap<Seat, Steering>,
+  pub joined: Vec<KeyScheme>,
+  pub respawns: Vec<usize>,
+  pub autopilot: Vec<(usize, bool)>,
+  pub playing: Option<bool>,
+  pub difficulty: Option<Difficulty>,
+  pub squad: Option<bool>,
+  pub dynamic_difficulty: Option<bool>,
+  pub board: Option<[f32; 2]>,
+}
+
+impl Inputs {
+  /// Frame of what differs in `now`, which then becomes the latest, `None` if nothing does.
+  pub fn diff(&mut self, tick: u64, now: Inputs) -> Option<Frame> {
+    let frame = Frame {
+      tick,
+      steering: now
+        .steering
+        .values()
+        .filter(|steering| self.steering.get(&steering.seat) != Some(*steering))
+        .cloned()
+        .collect(),
+      joined: now.joined.clone(),
+      respawns: now.respawns.clone(),
+      autopilot: now.autopilot.clone(),
+      playing: now.playing.filter(|_| now.playing != self.playing),
+      difficulty: now.difficulty.filter(|_| now.difficulty != self.difficulty),
+      squad: now.squad.filter(|_| now.squad != self.squad),
+      dynamic_difficulty: now
+        .dynamic_difficulty
+        .filter(|_| now.dynamic_difficulty != self.dynamic_difficulty),
+      board: now.board.filter(|_| now.board != self.board),
+    };
+    *self = now;
+    (!frame.is_empty()).then_some(frame)
+  }
+
+  pub fn apply(&mut self, frame: &Frame) {
+    for steering in &frame.steering {
+      self.steering.insert(steering.seat, steering.clone());
+    }
+    self.joined.extend(&frame.joined);
+    self.respawns.extend(&frame.respawns);
+    self.autopilot.extend(&frame.autopilot);
+    self.playing = frame.playing.or(self.playing);
+    self.difficulty = frame.difficulty.or(self.difficulty);
+    self.squad = frame.squad.or(self.squad);
+    self.dynamic_difficulty = frame.dynamic_difficulty.or(self.dynamic_difficulty);
+    self.board = frame.board.or(self.board);
+  }
+}
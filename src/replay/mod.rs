@@ -0,0 +1,329 @@
+pub mod format;
+mod systems;
+
+use crate::{
+  config,
+  simulation::{
+    conditions::every,
+    resources::{GameRng, Seed},
+    SimulationSet,
+  },
+  snapshot::SnapshotSystem,
+};
+use bevy::prelude::{
+  apply_system_buffers, resource_exists, warn, App, CoreSchedule, CoreSet, IntoSystemAppConfig,
+  IntoSystemAppConfigs, IntoSystemConfig, IntoSystemConfigs, Plugin, StartupSet,
+};
+use std::time::Duration;
+
+/// Ticks one seek skips, five seconds of play.
+pub const SEEK_TICKS: u64 = 500;
+/// Most ticks simulated in a frame while seeking, so the window keeps responding.
+pub const SEEK_TICKS_PER_FRAME: u64 = 2_000;
+pub const MIN_PLAYBACK_SPEED: f32 = 0.125;
+pub const MAX_PLAYBACK_SPEED: f32 = 16.;
+/// Free camera speed, in pixels per second at the default zoom.
+pub const CAMERA_SPEED: f32 = 400.;
+/// Simulated time between the points playback goes back to when seeking backwards.
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Records matches with `--record <path>` and plays them back with `--replay <path>`.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+  fn build(&self, app: &mut App) {
+    let settings = resources::ReplaySettings::from_args(std::env::args());
+    if let Some(path) = settings.playback {
+      match config::load_versioned::<format::Replay>(&path, format::REPLAY_VERSION) {
+        Ok(replay) => {
+          app
+            .insert_resource(Seed(replay.seed))
+            .insert_resource(GameRng::seeded(replay.seed))
+            .insert_resource(resources::Playback::new(path, replay, settings.seek));
+        }
+        Err(err) => warn!("Couldn't play replay {path}: {err}"),
+      }
+    } else if let Some(path) = settings.record {
+      app.insert_resource(resources::Recorder::new(path));
+    }
+
+    let playing_back = resource_exists::<resources::Playback>;
+    app
+      .add_startup_system(
+        systems::prepare_playback
+          .in_base_set(StartupSet::PreStartup)
+          .run_if(playing_back()),
+      )
+      .add_startup_system(systems::spawn_hud.run_if(playing_back()))
+      .add_startup_system(
+        systems::checkpoint
+          .in_base_set(StartupSet::PostStartup)
+          .run_if(playing_back()),
+      )
+      .add_system(
+        systems::record
+          .run_if(resource_exists::<resources::Recorder>())
+          .in_set(SimulationSet::Replay)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      )
+      .add_system(
+        systems::play
+          .run_if(playing_back())
+          .in_set(SimulationSet::Replay)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      )
+      .add_systems(
+        (
+          // The tick's commands are part of the checkpoint.
+          apply_system_buffers,
+          systems::checkpoint.run_if(every(CHECKPOINT_INTERVAL)),
+        )
+          .chain()
+          .after(SnapshotSystem::Track)
+          .in_set(SimulationSet::Snapshot)
+          .distributive_run_if(playing_back())
+          .in_schedule(CoreSchedule::FixedUpdate),
+      )
+      .add_system(
+        systems::save_recording
+          .run_if(resource_exists::<resources::Recorder>())
+          .in_base_set(CoreSet::Last),
+      )
+      .add_systems(
+        (
+          systems::control_playback,
+          systems::seek,
+          systems::move_camera,
+          systems::update_hud,
+        )
+          .chain()
+          .distributive_run_if(playing_back()),
+      );
+  }
+}
+
+pub mod components {
+  use bevy::prelude::Component;
+
+  #[derive(Debug, Component)]
+  pub struct ReplayHud;
+}
+
+pub mod resources {
+  use super::format::{Inputs, Replay, REPLAY_VERSION};
+  use crate::snapshot::format::Snapshot;
+  use bevy::prelude::Resource;
+
+  #[derive(Debug, Default)]
+  pub struct ReplaySettings {
+    pub record: Option<String>,
+    pub playback: Option<String>,
+    /// Tick playback starts from.
+    pub seek: u64,
+  }
+
+  impl ReplaySettings {
+    /// Reads `--record <path>`, `--replay <path>` and `--seek <tick>` arguments.
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Self {
+      let mut settings = Self::default();
+      while let Some(arg) = args.next() {
+        match arg.as_str() {
+          "--record" => settings.record = args.next(),
+          "--replay" => settings.playback = args.next(),
+          "--seek" => {
+            if let Some(tick) = args.next().and_then(|tick| tick.parse().ok()) {
+              settings.seek = tick;
+            }
+          }
+          _ => {}
+        }
+      }
+      settings
+    }
+  }
+
+  /// Match being recorded, written out when the game closes.
+  #[derive(Debug, Resource)]
+  pub struct Recorder {
+    pub path: String,
+    pub replay: Replay,
+    pub(super) inputs: Inputs,
+  }
+
+  impl Recorder {
+    pub fn new(path: String) -> Self {
+      Self {
+        path,
+        replay: Replay {
+          version: REPLAY_VERSION,
+          ..Default::default()
+        },
+        inputs: Inputs::default(),
+      }
+    }
+  }
+
+  /// Replay being played back in place of the player's and bots' inputs.
+  #[derive(Debug, Resource)]
+  pub struct Playback {
+    pub path: String,
+    pub replay: Replay,
+    /// Tick to get to, ticks behind the current one are played again from a checkpoint.
+    pub seek: Option<u64>,
+    pub(super) inputs: Inputs,
+    pub(super) next_frame: usize,
+    /// Points seeking backwards goes back to, in tick order.
+    pub(super) checkpoints: Vec<Checkpoint>,
+  }
+
+  impl Playback {
+    pub fn new(path: String, replay: Replay, seek: u64) -> Self {
+      Self {
+        path,
+        replay,
+        seek: (seek > 0).then_some(seek),
+        inputs: Inputs::default(),
+        next_frame: 0,
+        checkpoints: Vec::new(),
+      }
+    }
+  }
+
+  /// The match at a tick of the playback, along with how far the replay was read by then.
+  #[derive(Debug, Clone)]
+  pub(super) struct Checkpoint {
+    pub tick: u64,
+    pub snapshot: Snapshot,
+    pub inputs: Inputs,
+    pub next_frame: usize,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    resources::{Playback, Recorder},
+    systems,
+  };
+  use crate::{
+    board::{components::Board, resources::GameBoard},
+    difficulty::resources::{Difficulty, DynamicDifficulty},
+    enemy::resources::SquadCoordinator,
+    observation::BoardObserver,
+    player::{
+      components::{DirectionQueue, Player},
+      events::{AddPlayer, RespawnPlayer, SetAutopilot},
+    },
+    simulation::{
+      utils::{headless_app, step},
+      SimulationSet,
+    },
+    snake::components::{Direction, SnakeBundle, SnakeConfig},
+  };
+  use bevy::{
+    ecs::system::SystemState,
+    prelude::{
+      App, BuildChildren, Commands, CoreSchedule, Entity, IntoSystemAppConfig, IntoSystemConfig,
+      Query, With,
+    },
+  };
+
+  const TICKS: u64 = 1_000;
+
+  /// Headless match with a player snake on it, started up already.
+  fn match_app(replay: impl FnOnce(&mut App)) -> (App, Entity) {
+    let mut app = headless_app(11, GameBoard::with_cells(30, 30));
+    app
+      .add_event::<AddPlayer>()
+      .add_event::<RespawnPlayer>()
+      .add_event::<SetAutopilot>();
+    replay(&mut app);
+    app.update();
+
+    let world = &mut app.world;
+    let mut system_state = SystemState::<(Commands, Query<Entity, With<Board>>)>::new(world);
+    let (mut commands, q_board) = system_state.get_mut(world);
+    let board = q_board.single();
+    let config = SnakeConfig {
+      name: "Player".to_string(),
+      tail_length: 4,
+      ..Default::default()
+    };
+    let player = (
+      Player(0),
+      DirectionQueue::default(),
+      SnakeBundle::new(&mut commands, board, config),
+    );
+    let player = commands.spawn(player).id();
+    commands.entity(board).add_child(player);
+    system_state.apply(world);
+    (app, player)
+  }
+
+  fn state(app: &mut App) -> String {
+    let mut observer = SystemState::<BoardObserver>::new(&mut app.world);
+    serde_json::to_string(&observer.get_mut(&mut app.world).state()).unwrap()
+  }
+
+  /// Records a match fed with `inputs` before every tick, then plays it back, the states both
+  /// end up in.
+  fn record_and_play(inputs: impl Fn(&mut App, Entity, u64)) -> (String, String) {
+    let (mut app, player) = match_app(|app| {
+      app
+        .insert_resource(Recorder::new(String::new()))
+        .add_system(
+          systems::record
+            .in_set(SimulationSet::Replay)
+            .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    });
+    for tick in 0..TICKS {
+      inputs(&mut app, player, tick);
+      step(&mut app);
+    }
+    let recorded = state(&mut app);
+    let replay = app.world.remove_resource::<Recorder>().unwrap().replay;
+
+    let (mut app, _) = match_app(|app| {
+      app
+        .insert_resource(Playback::new(String::new(), replay, 0))
+        .add_system(
+          systems::play
+            .in_set(SimulationSet::Replay)
+            .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    });
+    for _ in 0..TICKS {
+      step(&mut app);
+    }
+    (recorded, state(&mut app))
+  }
+
+  #[test]
+  fn playback_reaches_the_recorded_state() {
+    use Direction::*;
+    let (recorded, played) = record_and_play(|app, player, tick| {
+      if tick % 45 == 0 {
+        let turn = [Top, Right, Bottom, Left][(tick / 45) as usize % 4];
+        *app.world.get_mut::<Direction>(player).unwrap() = turn;
+      }
+      if tick == TICKS / 2 {
+        app.insert_resource(Difficulty::Insane);
+      }
+    });
+    assert_eq!(played, recorded);
+  }
+
+  #[test]
+  fn playback_follows_the_enemy_tuning() {
+    let (recorded, played) = record_and_play(|app, _, tick| {
+      if tick == TICKS / 4 {
+        app.world.resource_mut::<SquadCoordinator>().enabled = true;
+      }
+      if tick == TICKS / 2 {
+        app.world.resource_mut::<DynamicDifficulty>().enabled = true;
+      }
+    });
+    assert_eq!(played, recorded);
+  }
+}
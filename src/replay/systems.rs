@@ -0,0 +1,421 @@
+use super::{
+  components::ReplayHud,
+  format::{Inputs, Seat, Steering},
+  resources::{Checkpoint, Playback, Recorder},
+  CAMERA_SPEED, MAX_PLAYBACK_SPEED, MIN_PLAYBACK_SPEED, SEEK_TICKS, SEEK_TICKS_PER_FRAME,
+};
+use crate::{
+  board::{components::BoardSprite, resources::GameBoard},
+  bot::{
+    components::ExternalBot,
+    resources::{BotEndpoint, BotSettings},
+  },
+  config,
+  difficulty::resources::{Difficulty, DynamicDifficulty},
+  enemy::resources::SquadCoordinator,
+  main_camera::components::MainCamera,
+  player::{
+    components::{Assists, DirectionQueue, Player, PointerTarget},
+    events::{AddPlayer, RespawnPlayer, SetAutopilot},
+  },
+  simulation::{
+    resources::{Seed, SimulationClock},
+    TICK,
+  },
+  snake::components::Direction,
+  snapshot::utils::{capture, restore},
+  state::GameState,
+};
+use bevy::{
+  app::AppExit,
+  input::mouse::MouseWheel,
+  prelude::{
+    info, warn, AssetServer, Color, Commands, CoreSchedule, EventReader, EventWriter, IVec2, Input,
+    KeyCode, OrthographicProjection, Query, Res, ResMut, Sprite, State, TextBundle, TextStyle,
+    Transform, Vec2, Vec3, With, Without, World,
+  },
+  text::Text,
+  time::Time,
+  ui::{PositionType, Style, UiRect, Val},
+  window::{PrimaryWindow, Window},
+};
+
+pub(super) fn prepare_playback(
+  playback: Res<Playback>,
+  settings: Option<ResMut<BotSettings>>,
+  mut q_window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+  // Bots take their recorded seats, their moves come from the replay.
+  if let Some(mut settings) = settings {
+    settings.endpoints = vec![BotEndpoint::Replayed; playback.replay.bots];
+  }
+  // Resizing would change the board outside of the recorded ticks.
+  if let Ok(mut window) = q_window.get_single_mut() {
+    window.resizable = false;
+  }
+}
+
+pub(super) fn record(
+  mut recorder: ResMut<Recorder>,
+  mut add_player_reader: EventReader<AddPlayer>,
+  mut respawn_reader: EventReader<RespawnPlayer>,
+  mut autopilot_reader: EventReader<SetAutopilot>,
+  q_player: Query<(
+    &Player,
+    &Direction,
+    &DirectionQueue,
+    Option<&Assists>,
+    Option<&PointerTarget>,
+  )>,
+  q_bot: Query<(&ExternalBot, &Direction)>,
+  (clock, seed, game_state, game_board): (
+    Res<SimulationClock>,
+    Res<Seed>,
+    Res<State<GameState>>,
+    Res<GameBoard>,
+  ),
+  (difficulty, squad_coordinator, dynamic_difficulty): (
+    Res<Difficulty>,
+    Res<SquadCoordinator>,
+    Res<DynamicDifficulty>,
+  ),
+) {
+  let mut steering = q_bot
+    .iter()
+    .map(|(bot, direction)| Steering {
+      seat: Seat::Bot(bot.index),
+      direction: *direction,
+      queued: Vec::new(),
+      assists: None,
+      pointer: None,
+    })
+    .collect::<Vec<_>>();
+  steering.extend(
+    q_player.iter().map(
+      |(player, direction, direction_queue, assists, target)| Steering {
+        seat: Seat::Player(player.0),
+        direction: *direction,
+        queued: direction_queue.turns.iter().copied().collect(),
+        assists: assists
+          .map(|assists| assists.per_life)
+          .filter(|per_life| *per_life > 0),
+        pointer: target.and_then(|target| target.0.map(Into::into)),
+      },
+    ),
+  );
+  let now = Inputs {
+    steering: steering
+      .into_iter()
+      .map(|steering| (steering.seat, steering))
+      .collect(),
+    joined: add_player_reader
+      .iter()
+      .map(|AddPlayer(keys)| *keys)
+      .collect(),
+    respawns: respawn_reader
+      .iter()
+      .map(|RespawnPlayer(seat)| *seat)
+      .collect(),
+    autopilot: autopilot_reader
+      .iter()
+      .map(|SetAutopilot(seat, on)| (*seat, *on))
+      .collect(),
+    playing: Some(game_state.0 == GameState::Playing),
+    difficulty: Some(*difficulty),
+    squad: Some(squad_coordinator.enabled),
+    dynamic_difficulty: Some(dynamic_difficulty.enabled),
+    board: Some([game_board.width, game_board.height]),
+  };
+
+  let recorder = &mut *recorder;
+  recorder.replay.seed = seed.0;
+  recorder.replay.bots = recorder.replay.bots.max(q_bot.iter().count());
+  recorder.replay.ticks = clock.tick;
+  if let Some(frame) = recorder.inputs.diff(clock.tick, now) {
+    recorder.replay.frames.push(frame);
+  }
+}
+
+pub(super) fn save_recording(mut exit_reader: EventReader<AppExit>, recorder: Res<Recorder>) {
+  if exit_reader.iter().last().is_none() {
+    return;
+  }
+  match config::save(&recorder.path, &recorder.replay) {
+    Ok(()) => info!("Replay saved to {}", recorder.path),
+    Err(err) => warn!("Couldn't save replay to {}: {err}", recorder.path),
+  }
+}
+
+pub(super) fn play(
+  mut playback: ResMut<Playback>,
+  (mut add_player_writer, mut respawn_writer, mut autopilot_writer): (
+    EventWriter<AddPlayer>,
+    EventWriter<RespawnPlayer>,
+    EventWriter<SetAutopilot>,
+  ),
+  mut q_player: Query<(
+    &Player,
+    &mut Direction,
+    &mut DirectionQueue,
+    Option<&mut Assists>,
+    Option<&mut PointerTarget>,
+  )>,
+  mut q_bot: Query<(&ExternalBot, &mut Direction), Without<Player>>,
+  mut q_board_sprite: Query<&mut Sprite, With<BoardSprite>>,
+  (clock, mut game_state, mut game_board): (
+    Res<SimulationClock>,
+    ResMut<State<GameState>>,
+    ResMut<GameBoard>,
+  ),
+  (mut difficulty, mut squad_coordinator, mut dynamic_difficulty): (
+    ResMut<Difficulty>,
+    ResMut<SquadCoordinator>,
+    ResMut<DynamicDifficulty>,
+  ),
+) {
+  let playback = &mut *playback;
+  playback.inputs.joined.clear();
+  playback.inputs.respawns.clear();
+  playback.inputs.autopilot.clear();
+  while let Some(frame) = playback.replay.frames.get(playback.next_frame) {
+    if frame.tick > clock.tick {
+      break;
+    }
+    playback.inputs.apply(frame);
+    playback.next_frame += 1;
+  }
+
+  // Inputs are set every tick, whatever the snakes did on their own in between is undone.
+  let inputs = &playback.inputs;
+  for keys in &inputs.joined {
+    add_player_writer.send(AddPlayer(*keys));
+  }
+  for seat in &inputs.respawns {
+    respawn_writer.send(RespawnPlayer(*seat));
+  }
+  for (seat, on) in &inputs.autopilot {
+    autopilot_writer.send(SetAutopilot(*seat, *on));
+  }
+  for (player, mut direction, mut direction_queue, assists, target) in &mut q_player {
+    let Some(steering) = inputs.steering.get(&Seat::Player(player.0)) else {continue};
+    *direction = steering.direction;
+    direction_queue.turns = steering.queued.iter().copied().collect();
+    if let Some(mut assists) = assists {
+      assists.per_life = steering.assists.unwrap_or_default();
+    }
+    if let Some(mut target) = target {
+      target.0 = steering.pointer.map(IVec2::from);
+    }
+  }
+  for (bot, mut direction) in &mut q_bot {
+    let Some(steering) = inputs.steering.get(&Seat::Bot(bot.index)) else {continue};
+    *direction = steering.direction;
+  }
+  if let Some(playing) = inputs.playing {
+    let state = if playing {
+      GameState::Playing
+    } else {
+      GameState::Paused
+    };
+    if game_state.0 != state {
+      game_state.0 = state;
+    }
+  }
+  if let Some(recorded) = inputs.difficulty {
+    if *difficulty != recorded {
+      *difficulty = recorded;
+    }
+  }
+  if let Some(recorded) = inputs.squad {
+    if squad_coordinator.enabled != recorded {
+      squad_coordinator.enabled = recorded;
+    }
+  }
+  if let Some(recorded) = inputs.dynamic_difficulty {
+    if dynamic_difficulty.enabled != recorded {
+      dynamic_difficulty.enabled = recorded;
+    }
+  }
+  if let Some([width, height]) = inputs.board {
+    if game_board.width != width || game_board.height != height {
+      game_board.width = width;
+      game_board.height = height;
+      for mut board_sprite in &mut q_board_sprite {
+        board_sprite.custom_size = Some(Vec2::new(width, height));
+      }
+    }
+  }
+}
+
+pub(super) fn control_playback(
+  keyboard_input: Res<Input<KeyCode>>,
+  mut time: ResMut<Time>,
+  mut playback: ResMut<Playback>,
+  clock: Res<SimulationClock>,
+) {
+  let tick = clock.tick;
+  if keyboard_input.just_pressed(KeyCode::Space) {
+    if time.is_paused() {
+      time.unpause();
+    } else {
+      time.pause();
+    }
+  } else if keyboard_input.just_pressed(KeyCode::Up) {
+    let speed = (time.relative_speed() * 2.).min(MAX_PLAYBACK_SPEED);
+    time.set_relative_speed(speed);
+  } else if keyboard_input.just_pressed(KeyCode::Down) {
+    let speed = (time.relative_speed() / 2.).max(MIN_PLAYBACK_SPEED);
+    time.set_relative_speed(speed);
+  } else if keyboard_input.just_pressed(KeyCode::Right) {
+    playback.seek = Some(tick + SEEK_TICKS);
+  } else if keyboard_input.just_pressed(KeyCode::Left) {
+    playback.seek = Some(tick.saturating_sub(SEEK_TICKS));
+  } else if keyboard_input.just_pressed(KeyCode::Period) && time.is_paused() {
+    playback.seek = Some(tick + 1);
+  } else if keyboard_input.just_pressed(KeyCode::Home) {
+    playback.seek = Some(0);
+  }
+
+  if tick >= playback.replay.ticks && !time.is_paused() {
+    time.pause();
+  }
+}
+
+pub(super) fn seek(world: &mut World) {
+  let mut tick = world.resource::<SimulationClock>().tick;
+  let mut playback = world.resource_mut::<Playback>();
+  let Some(target) = playback.seek else {return};
+  if target < tick {
+    // Ticks can't be undone, the match goes back to the last checkpoint before the target.
+    let checkpoint = playback
+      .checkpoints
+      .iter()
+      .rev()
+      .find(|checkpoint| checkpoint.tick <= target)
+      .cloned();
+    let Some(checkpoint) = checkpoint else {
+      playback.seek = None;
+      return;
+    };
+    playback.inputs = checkpoint.inputs;
+    playback.next_frame = checkpoint.next_frame;
+    restore(world, &checkpoint.snapshot);
+    tick = checkpoint.tick;
+  }
+
+  let mut playback = world.resource_mut::<Playback>();
+  let target = target.min(playback.replay.ticks);
+  let steps = target.saturating_sub(tick).min(SEEK_TICKS_PER_FRAME);
+  if tick + steps >= target {
+    playback.seek = None;
+  }
+  for _ in 0..steps {
+    world.run_schedule(CoreSchedule::FixedUpdate);
+  }
+}
+
+/// Keeps the match as it is to seek back to, unless the tick was kept already.
+pub(super) fn checkpoint(world: &mut World) {
+  let tick = world.resource::<SimulationClock>().tick;
+  let playback = world.resource::<Playback>();
+  if playback
+    .checkpoints
+    .last()
+    .map_or(false, |checkpoint| checkpoint.tick >= tick)
+  {
+    return;
+  }
+  let snapshot = capture(world);
+  let mut playback = world.resource_mut::<Playback>();
+  let checkpoint = Checkpoint {
+    tick,
+    snapshot,
+    inputs: playback.inputs.clone(),
+    next_frame: playback.next_frame,
+  };
+  playback.checkpoints.push(checkpoint);
+}
+
+pub(super) fn move_camera(
+  keyboard_input: Res<Input<KeyCode>>,
+  mut scroll_reader: EventReader<MouseWheel>,
+  mut q_camera: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+  time: Res<Time>,
+) {
+  let Ok((mut transform, mut projection)) = q_camera.get_single_mut() else {return};
+  if keyboard_input.just_pressed(KeyCode::C) {
+    transform.translation = Vec3::new(0., 0., transform.translation.z);
+    projection.scale = 1.;
+    return;
+  }
+
+  let mut pan = Vec2::ZERO;
+  if keyboard_input.pressed(KeyCode::W) {
+    pan.y += 1.;
+  }
+  if keyboard_input.pressed(KeyCode::S) {
+    pan.y -= 1.;
+  }
+  if keyboard_input.pressed(KeyCode::A) {
+    pan.x -= 1.;
+  }
+  if keyboard_input.pressed(KeyCode::D) {
+    pan.x += 1.;
+  }
+  // Raw time, the camera moves the same whatever the playback speed, even when paused.
+  let step = CAMERA_SPEED * projection.scale * time.raw_delta_seconds();
+  transform.translation += (pan.normalize_or_zero() * step).extend(0.);
+
+  let scroll = scroll_reader.iter().map(|scroll| scroll.y).sum::<f32>();
+  if scroll != 0. {
+    projection.scale = (projection.scale * 0.9f32.powf(scroll)).clamp(0.1, 10.);
+  }
+}
+
+pub(super) fn spawn_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+  commands.spawn((
+    ReplayHud,
+    TextBundle::from_section(
+      "",
+      TextStyle {
+        font: asset_server.load("fonts/UbuntuMono-Regular.ttf"),
+        font_size: 16.,
+        color: Color::WHITE,
+      },
+    )
+    .with_style(Style {
+      position_type: PositionType::Absolute,
+      position: UiRect {
+        right: Val::Px(10.),
+        bottom: Val::Px(10.),
+        ..Default::default()
+      },
+      ..Default::default()
+    }),
+  ));
+}
+
+pub(super) fn update_hud(
+  mut q_hud: Query<&mut Text, With<ReplayHud>>,
+  playback: Res<Playback>,
+  clock: Res<SimulationClock>,
+  time: Res<Time>,
+) {
+  let Ok(mut hud) = q_hud.get_single_mut() else {return};
+  let seconds = |ticks: u64| TICK.as_secs_f32() * ticks as f32;
+  let state = if playback.seek.is_some() {
+    "seeking"
+  } else if time.is_paused() {
+    "paused"
+  } else {
+    "playing"
+  };
+  hud.sections[0].value = format!(
+    "{state} {:.1}s / {:.1}s x{}\n\
+     space pause  . step  left/right seek  home restart\n\
+     up/down speed  wasd pan  wheel zoom  c reset camera",
+    seconds(clock.tick),
+    seconds(playback.replay.ticks),
+    time.relative_speed(),
+  );
+}
@@ -0,0 +1,157 @@
+mod systems;
+
+use crate::{
+  replay::resources::{Playback, Recorder},
+  simulation::{conditions::every, SimulationSet},
+  snapshot::SnapshotSystem,
+};
+use bevy::{
+  ecs::schedule::common_conditions::not,
+  prelude::{
+    apply_system_buffers, resource_exists, App, CoreSchedule, IntoSystemAppConfigs,
+    IntoSystemConfig, IntoSystemConfigs, Plugin,
+  },
+};
+use std::time::Duration;
+
+/// Simulated time kept to go back to.
+pub const REWIND_HISTORY: Duration = Duration::from_secs(10);
+/// Simulated time between two points the match can be rewound to.
+pub const REWIND_INTERVAL: Duration = Duration::from_millis(50);
+/// How much faster than real time the match scrubs back.
+pub const REWIND_SPEED: f32 = 2.;
+/// Rewind a full meter holds in normal play.
+pub const REWIND_METER: Duration = Duration::from_secs(3);
+/// Rewind regained per second played.
+pub const REWIND_RECHARGE: f32 = 0.2;
+
+/// Keeps the last seconds of the match and plays them back in reverse while the rewind action is
+/// held, with a meter limiting how far back it goes outside of debug builds.
+pub struct RewindPlugin;
+
+impl Plugin for RewindPlugin {
+  fn build(&self, app: &mut App) {
+    // A replay plays out as recorded, and a recording can't go back on its ticks.
+    let live = || not(resource_exists::<Playback>());
+    let unrecorded = || not(resource_exists::<Recorder>());
+    app
+      .insert_resource(resources::RewindMeter::from_args(std::env::args()))
+      .init_resource::<resources::History>()
+      .add_systems(
+        (
+          // The tick's commands are part of the point kept.
+          apply_system_buffers,
+          systems::record
+            .run_if(every(REWIND_INTERVAL))
+            .run_if(live())
+            .run_if(unrecorded()),
+          systems::recharge,
+        )
+          .chain()
+          .after(SnapshotSystem::Track)
+          .in_set(SimulationSet::Snapshot)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      )
+      .add_startup_system(systems::spawn_hud)
+      .add_system(systems::forget_history)
+      .add_system(
+        systems::rewind
+          .after(systems::forget_history)
+          .run_if(live())
+          .run_if(unrecorded()),
+      )
+      .add_system(systems::update_hud.after(systems::rewind));
+  }
+}
+
+pub mod components {
+  use bevy::prelude::Component;
+
+  #[derive(Debug, Component)]
+  pub struct RewindHud;
+}
+
+pub mod resources {
+  use super::{REWIND_HISTORY, REWIND_INTERVAL, REWIND_METER};
+  use crate::snapshot::format::Snapshot;
+  use bevy::prelude::Resource;
+  use std::{collections::VecDeque, time::Duration};
+
+  /// Points the match can be rewound to, oldest first.
+  #[derive(Debug, Resource)]
+  pub struct History {
+    pub snapshots: VecDeque<Snapshot>,
+    pub capacity: usize,
+    /// Whether the rewind action is held.
+    pub rewinding: bool,
+    /// Real time scrubbed that didn't add up to a whole interval yet.
+    pub(super) scrubbed: Duration,
+  }
+
+  impl Default for History {
+    fn default() -> Self {
+      let capacity = (REWIND_HISTORY.as_nanos() / REWIND_INTERVAL.as_nanos()) as usize;
+      Self {
+        snapshots: VecDeque::with_capacity(capacity),
+        capacity,
+        rewinding: false,
+        scrubbed: Duration::ZERO,
+      }
+    }
+  }
+
+  /// Rewind left, unlimited without a capacity.
+  #[derive(Debug, Resource)]
+  pub struct RewindMeter {
+    pub charge: Duration,
+    pub capacity: Option<Duration>,
+  }
+
+  impl Default for RewindMeter {
+    fn default() -> Self {
+      // Debugging goes back as far as needed.
+      let capacity = (!cfg!(debug_assertions)).then_some(REWIND_METER);
+      Self {
+        charge: capacity.unwrap_or_default(),
+        capacity,
+      }
+    }
+  }
+
+  impl RewindMeter {
+    /// Reads a `--rewind-meter <seconds>` argument, `off` lifting the limit.
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Self {
+      let mut meter = Self::default();
+      while let Some(arg) = args.next() {
+        if arg != "--rewind-meter" {
+          continue;
+        }
+        match args.next().as_deref() {
+          Some("off") => meter.capacity = None,
+          Some(seconds) => {
+            if let Ok(seconds) = seconds.parse() {
+              meter.capacity = Some(Duration::from_secs_f32(seconds));
+            }
+          }
+          None => {}
+        }
+        meter.charge = meter.capacity.unwrap_or_default();
+      }
+      meter
+    }
+
+    pub fn is_limited(&self) -> bool {
+      self.capacity.is_some()
+    }
+
+    /// Takes `amount` off the meter, `false` when there isn't that much left.
+    pub fn spend(&mut self, amount: Duration) -> bool {
+      if !self.is_limited() {
+        return true;
+      }
+      let Some(charge) = self.charge.checked_sub(amount) else {return false};
+      self.charge = charge;
+      true
+    }
+  }
+}
@@ -0,0 +1,134 @@
+use super::{
+  components::RewindHud,
+  resources::{History, RewindMeter},
+  REWIND_INTERVAL, REWIND_RECHARGE, REWIND_SPEED,
+};
+use crate::{
+  bindings::resources::{Action, Actions},
+  snapshot::{
+    events::SnapshotLoaded,
+    utils::{capture, restore},
+  },
+};
+use bevy::{
+  prelude::{
+    AssetServer, Color, Commands, EventReader, FixedTime, Mut, Query, Res, ResMut, TextBundle,
+    TextStyle, With, World,
+  },
+  text::Text,
+  time::Time,
+  ui::{PositionType, Style, UiRect, Val},
+};
+use std::time::Duration;
+
+/// Length of the meter drawn in the HUD, in characters.
+const METER_WIDTH: usize = 10;
+
+pub(super) fn record(world: &mut World) {
+  let snapshot = capture(world);
+  let mut history = world.resource_mut::<History>();
+  if history.snapshots.len() >= history.capacity {
+    history.snapshots.pop_front();
+  }
+  history.snapshots.push_back(snapshot);
+}
+
+pub(super) fn recharge(mut meter: ResMut<RewindMeter>, fixed_time: Res<FixedTime>) {
+  let Some(capacity) = meter.capacity else {return};
+  meter.charge = (meter.charge + fixed_time.period.mul_f32(REWIND_RECHARGE)).min(capacity);
+}
+
+pub(super) fn forget_history(
+  mut loaded_reader: EventReader<SnapshotLoaded>,
+  mut history: ResMut<History>,
+) {
+  // The points kept belong to the match that was replaced.
+  if loaded_reader.iter().last().is_some() {
+    history.snapshots.clear();
+  }
+}
+
+pub(super) fn rewind(world: &mut World) {
+  let held = world.resource::<Actions>().pressed(None, Action::Rewind);
+  let delta = world.resource::<Time>().raw_delta();
+  let mut history = world.resource_mut::<History>();
+  if !held {
+    if history.rewinding {
+      history.rewinding = false;
+      world.resource_mut::<Time>().unpause();
+    }
+    return;
+  }
+  if !history.rewinding {
+    history.rewinding = true;
+    history.scrubbed = Duration::ZERO;
+    // The match holds still while it's being scrubbed.
+    world.resource_mut::<Time>().pause();
+  }
+
+  let target = world.resource_scope(|world, mut history: Mut<History>| {
+    history.scrubbed += delta.mul_f32(REWIND_SPEED);
+    let mut meter = world.resource_mut::<RewindMeter>();
+    let mut target = None;
+    while history.scrubbed >= REWIND_INTERVAL && !history.snapshots.is_empty() {
+      if !meter.spend(REWIND_INTERVAL) {
+        break;
+      }
+      history.scrubbed -= REWIND_INTERVAL;
+      target = history.snapshots.pop_back();
+    }
+    target
+  });
+  // Only the point reached is shown, those scrubbed past on the way are skipped.
+  if let Some(snapshot) = target {
+    restore(world, &snapshot);
+  }
+}
+
+pub(super) fn spawn_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+  commands.spawn((
+    RewindHud,
+    TextBundle::from_section(
+      "",
+      TextStyle {
+        font: asset_server.load("fonts/UbuntuMono-Regular.ttf"),
+        font_size: 16.,
+        color: Color::WHITE,
+      },
+    )
+    .with_style(Style {
+      position_type: PositionType::Absolute,
+      position: UiRect {
+        left: Val::Px(10.),
+        bottom: Val::Px(10.),
+        ..Default::default()
+      },
+      ..Default::default()
+    }),
+  ));
+}
+
+pub(super) fn update_hud(
+  mut q_hud: Query<&mut Text, With<RewindHud>>,
+  history: Res<History>,
+  meter: Res<RewindMeter>,
+) {
+  let Ok(mut hud) = q_hud.get_single_mut() else {return};
+  let left = match meter.capacity {
+    Some(capacity) => {
+      let filled =
+        (METER_WIDTH as f32 * meter.charge.as_secs_f32() / capacity.as_secs_f32()).round() as usize;
+      format!(
+        "[{}{}]",
+        "#".repeat(filled),
+        ".".repeat(METER_WIDTH.saturating_sub(filled))
+      )
+    }
+    None => {
+      let kept = REWIND_INTERVAL.mul_f32(history.snapshots.len() as f32);
+      format!("{:.1}s", kept.as_secs_f32())
+    }
+  };
+  let state = if history.rewinding { "<<" } else { "backspace" };
+  hud.sections[0].value = format!("rewind {left} {state}");
+}
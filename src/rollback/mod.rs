@@ -0,0 +1,351 @@
+pub mod protocol;
+mod systems;
+pub mod utils;
+
+use crate::{simulation::SimulationSet, snapshot::SnapshotSystem};
+use bevy::prelude::{
+  apply_system_buffers, resource_exists, App, CoreSchedule, IntoSystemAppConfig,
+  IntoSystemAppConfigs, IntoSystemConfig, IntoSystemConfigs, Plugin,
+};
+use std::time::Duration;
+
+pub const MIN_PEERS: usize = 2;
+pub const MAX_PEERS: usize = 4;
+/// Ticks between pressing a key and the snake turning, so inputs mostly reach the other peers in
+/// time and no rollback is needed.
+pub const INPUT_DELAY: u64 = 2;
+/// Ticks a peer may run ahead of the inputs it has from all the others before it waits for them.
+pub const MAX_PREDICTION: u64 = 8;
+/// Ticks between two checksums compared with the other peers.
+pub const CHECKSUM_INTERVAL: u64 = 10;
+/// Checksums kept to compare with those the other peers send late.
+pub const CHECKSUM_HISTORY: usize = 64;
+/// Real time between two hellos, while the peers look for each other.
+pub const HELLO_INTERVAL: Duration = Duration::from_millis(250);
+pub const MAX_DATAGRAM: usize = 65_507;
+pub const MAX_NAME_LENGTH: usize = 16;
+
+pub(super) const INITIAL_PEER_LENGTH: usize = 4;
+
+/// Plays a match between peers given with `--peers <address>,<address>[,..]`, each running the
+/// whole simulation and sending only its inputs to the others. The local peer predicts the inputs
+/// it doesn't have yet and, when they turn out different, goes back to the last tick it got right
+/// and plays the match again from there.
+pub struct RollbackPlugin;
+
+impl Plugin for RollbackPlugin {
+  fn build(&self, app: &mut App) {
+    let session = resource_exists::<resources::Session>;
+    app
+      .add_startup_system(systems::spawn_peers.run_if(session()))
+      .add_startup_system(systems::spawn_hud)
+      .add_system(
+        systems::sample_input
+          .in_set(SimulationSet::Replay)
+          .run_if(session())
+          .in_schedule(CoreSchedule::FixedUpdate),
+      )
+      .add_systems(
+        (
+          // Turns are taken after the queues moved on, as a restored snapshot has them.
+          systems::advance_queues,
+          systems::apply_inputs,
+          systems::respawn,
+        )
+          .chain()
+          .in_set(SimulationSet::Player)
+          .distributive_run_if(session())
+          .in_schedule(CoreSchedule::FixedUpdate),
+      )
+      .add_systems(
+        (
+          // The tick's commands are part of the state kept.
+          apply_system_buffers,
+          systems::save_state.run_if(session()),
+        )
+          .chain()
+          .after(SnapshotSystem::Track)
+          .in_set(SimulationSet::Snapshot)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      )
+      .add_systems(
+        (
+          systems::receive,
+          systems::rollback,
+          systems::confirm,
+          systems::send,
+          systems::stall,
+        )
+          .chain()
+          .distributive_run_if(session()),
+      )
+      .add_system(systems::update_hud.after(systems::stall));
+  }
+}
+
+pub mod components {
+  use bevy::prelude::Component;
+
+  /// Snake steered by the peer in this seat, the local one included.
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+  pub struct Peer(pub usize);
+
+  #[derive(Debug, Component)]
+  pub struct RollbackHud;
+}
+
+pub mod resources {
+  use super::{
+    protocol::{PeerInput, PeerMessage},
+    HELLO_INTERVAL, INPUT_DELAY, MAX_DATAGRAM, MAX_NAME_LENGTH, MAX_PEERS, MIN_PEERS,
+  };
+  use crate::snapshot::format::Snapshot;
+  use bevy::prelude::{info, Resource};
+  use std::{
+    collections::BTreeMap,
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::Instant,
+  };
+
+  #[derive(Debug, Resource)]
+  pub struct RollbackSettings {
+    /// Addresses of every peer of the match, by seat, the local one included.
+    pub peers: Vec<String>,
+    pub seat: usize,
+    pub name: String,
+    /// Seed of the match, picked by the first seat.
+    pub seed: Option<u64>,
+    /// Columns and rows of the board, picked by the first seat.
+    pub board: [i32; 2],
+  }
+
+  impl RollbackSettings {
+    /// Reads `--peers <address>,<address>[,..]`, `--seat <n>`, `--name <name>`, `--seed <n>`,
+    /// `--cols <n>` and `--rows <n>` arguments, the match is played alone without peers.
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Self {
+      let mut settings = Self {
+        peers: Vec::new(),
+        seat: 0,
+        name: "Player".to_string(),
+        seed: None,
+        board: [40, 40],
+      };
+      while let Some(arg) = args.next() {
+        match arg.as_str() {
+          "--peers" => {
+            if let Some(peers) = args.next() {
+              settings.peers = peers.split(',').map(str::to_string).collect();
+            }
+          }
+          "--seat" => settings.seat = args.next().and_then(|seat| seat.parse().ok()).unwrap_or(0),
+          "--name" => {
+            if let Some(name) = args.next() {
+              settings.name = name;
+            }
+          }
+          "--seed" => settings.seed = args.next().and_then(|seed| seed.parse().ok()),
+          "--cols" => {
+            if let Some(cols) = args.next().and_then(|cols| cols.parse().ok()) {
+              settings.board[0] = cols;
+            }
+          }
+          "--rows" => {
+            if let Some(rows) = args.next().and_then(|rows| rows.parse().ok()) {
+              settings.board[1] = rows;
+            }
+          }
+          _ => {}
+        }
+      }
+      settings
+    }
+
+    pub fn is_enabled(&self) -> bool {
+      !self.peers.is_empty()
+    }
+  }
+
+  /// Match shared with the other peers.
+  #[derive(Debug, Resource)]
+  pub struct Session {
+    /// Seat of the local peer.
+    pub seat: usize,
+    pub peers: Vec<SocketAddr>,
+    /// Names by seat.
+    pub names: Vec<String>,
+    pub seed: u64,
+    /// Columns and rows of the board.
+    pub board: [i32; 2],
+    /// Inputs of each seat known so far, from the first tick on.
+    pub inputs: Vec<Vec<PeerInput>>,
+    /// Inputs each tick not confirmed yet was played with, by seat.
+    pub used: BTreeMap<u64, Vec<PeerInput>>,
+    /// State after each tick from the last confirmed one on.
+    pub snapshots: BTreeMap<u64, Snapshot>,
+    /// Latest tick played with the inputs of every seat.
+    pub confirmed: u64,
+    /// Checksums of confirmed ticks.
+    pub checksums: BTreeMap<u64, u64>,
+    /// Checksums the other peers sent of ticks not confirmed here yet, by tick and seat.
+    pub remote_checksums: BTreeMap<(u64, usize), u64>,
+    /// First tick the peers were found to disagree on.
+    pub desync: Option<u64>,
+    /// Times the match was played again from an earlier tick.
+    pub rollbacks: u64,
+    /// Whether the match waits for inputs of the other peers.
+    pub stalled: bool,
+    /// Inputs each seat said it has of the local one's.
+    pub(super) acked: Vec<usize>,
+    /// Earliest tick played with an input that turned out wrong.
+    pub(super) rollback_to: Option<u64>,
+    pub(super) resimulating: bool,
+    pub(super) socket: UdpSocket,
+  }
+
+  impl Session {
+    /// Waits until every peer of `settings` is there, the first seat then picks the seed and the
+    /// board and starts the match for everyone.
+    pub fn join(settings: &RollbackSettings) -> io::Result<Self> {
+      let invalid = |error: String| io::Error::new(io::ErrorKind::InvalidInput, error);
+      if !(MIN_PEERS..=MAX_PEERS).contains(&settings.peers.len()) {
+        return Err(invalid(format!(
+          "{MIN_PEERS} to {MAX_PEERS} peers play a match"
+        )));
+      }
+      if settings.seat >= settings.peers.len() {
+        return Err(invalid(format!("there's no seat {}", settings.seat)));
+      }
+      let peers = settings
+        .peers
+        .iter()
+        .map(|peer| {
+          peer
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| invalid(format!("no address for {peer}")))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+      let socket = UdpSocket::bind(peers[settings.seat])?;
+      socket.set_read_timeout(Some(HELLO_INTERVAL))?;
+
+      let seat = settings.seat;
+      let name = settings
+        .name
+        .chars()
+        .take(MAX_NAME_LENGTH)
+        .collect::<String>();
+      let hello = serde_json::to_vec(&PeerMessage::Hello {
+        seat,
+        name: name.clone(),
+      })?;
+      let mut names = vec![None; peers.len()];
+      names[seat] = Some(name);
+      let mut buffer = vec![0; MAX_DATAGRAM];
+      let mut last_hello = None::<Instant>;
+      info!("Waiting for peers");
+      let (seed, names, board) = loop {
+        if last_hello.map_or(true, |last_hello| last_hello.elapsed() >= HELLO_INTERVAL) {
+          last_hello = Some(Instant::now());
+          for (_, peer) in peers.iter().enumerate().filter(|(i, _)| *i != seat) {
+            // Peers not up yet get the next one.
+            let _ = socket.send_to(&hello, peer);
+          }
+        }
+        if seat == 0 && names.iter().all(Option::is_some) {
+          break (
+            settings.seed.unwrap_or_else(rand::random),
+            names.into_iter().flatten().collect::<Vec<_>>(),
+            settings.board,
+          );
+        }
+        let Ok((length, address)) = socket.recv_from(&mut buffer) else {continue};
+        if !peers.contains(&address) {
+          continue;
+        }
+        match serde_json::from_slice::<PeerMessage>(&buffer[..length]) {
+          Ok(PeerMessage::Hello { seat, name }) if seat < peers.len() => {
+            names[seat] = Some(name.chars().take(MAX_NAME_LENGTH).collect());
+          }
+          Ok(PeerMessage::Start { seed, names, board }) if seat != 0 => break (seed, names, board),
+          _ => {}
+        }
+      };
+      if names.len() != peers.len() {
+        return Err(invalid("the first seat plays with other peers".to_string()));
+      }
+      info!("Starting with {}, seed {seed}", names.join(", "));
+      socket.set_nonblocking(true)?;
+
+      let seats = peers.len();
+      let session = Self {
+        seat,
+        peers,
+        names,
+        seed,
+        board,
+        // Nobody can turn before the inputs are delayed, those ticks are known to all.
+        inputs: vec![vec![PeerInput::default(); INPUT_DELAY as usize]; seats],
+        used: BTreeMap::new(),
+        snapshots: BTreeMap::new(),
+        confirmed: 0,
+        checksums: BTreeMap::new(),
+        remote_checksums: BTreeMap::new(),
+        desync: None,
+        rollbacks: 0,
+        stalled: false,
+        acked: vec![0; seats],
+        rollback_to: None,
+        resimulating: false,
+        socket,
+      };
+      if seat == 0 {
+        // Others may still be waiting for it, they say hello again until they get it.
+        session.send(&start_message(&session));
+      }
+      Ok(session)
+    }
+
+    /// Sends `message` to every other peer.
+    pub(super) fn send(&self, message: &PeerMessage) {
+      for (_, peer) in self
+        .peers
+        .iter()
+        .enumerate()
+        .filter(|(seat, _)| *seat != self.seat)
+      {
+        self.send_to(*peer, message);
+      }
+    }
+
+    pub(super) fn send_to(&self, peer: SocketAddr, message: &PeerMessage) {
+      let Ok(message) = serde_json::to_vec(message) else {return};
+      // Lost messages are made up for by the next ones.
+      let _ = self.socket.send_to(&message, peer);
+    }
+
+    /// Inputs of every seat for `tick`, predicted for the seats that didn't send it yet.
+    pub(super) fn inputs_at(&self, tick: u64) -> Vec<PeerInput> {
+      self
+        .inputs
+        .iter()
+        .map(|inputs| match inputs.get(tick as usize - 1) {
+          Some(input) => *input,
+          // Keys are mostly held for a while, the last one known likely still is.
+          None => PeerInput {
+            direction: inputs.last().and_then(|input| input.direction),
+            respawn: false,
+          },
+        })
+        .collect()
+    }
+  }
+
+  pub(super) fn start_message(session: &Session) -> PeerMessage {
+    PeerMessage::Start {
+      seed: session.seed,
+      names: session.names.clone(),
+      board: session.board,
+    }
+  }
+}
@@ -0,0 +1,46 @@
+use crate::snake::components::Direction;
+use serde::{Deserialize, Serialize};
+
+/// Sent by a peer to all the others, one JSON object per datagram.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PeerMessage {
+  /// Sent while looking for the other peers, and again until the match starts.
+  Hello { seat: usize, name: String },
+  /// Sent by the first seat once everyone said hello.
+  Start {
+    seed: u64,
+    /// Names by seat.
+    names: Vec<String>,
+    /// Columns and rows.
+    board: [i32; 2],
+  },
+  /// Sent every frame, with every input the peer it's sent to didn't acknowledge yet.
+  Inputs {
+    seat: usize,
+    /// Tick of the first input.
+    start: u64,
+    inputs: Vec<PeerInput>,
+    /// Inputs of the receiver's seat the sender has.
+    ack: usize,
+    /// Latest checksum of a tick the sender confirmed, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    checksum: Option<Checksum>,
+  },
+}
+
+/// What a peer did at a tick.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerInput {
+  /// Direction key held, if any.
+  pub direction: Option<Direction>,
+  /// Whether the respawn key was held.
+  pub respawn: bool,
+}
+
+/// Hash of the snakes and the food as a tick left them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checksum {
+  pub tick: u64,
+  pub value: u64,
+}
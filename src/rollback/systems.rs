@@ -0,0 +1,388 @@
+use super::{
+  components::{Peer, RollbackHud},
+  protocol::{Checksum, PeerInput, PeerMessage},
+  resources::{start_message, Session},
+  utils::checksum,
+  CHECKSUM_HISTORY, CHECKSUM_INTERVAL, INITIAL_PEER_LENGTH, INPUT_DELAY, MAX_DATAGRAM,
+  MAX_PREDICTION,
+};
+use crate::{
+  bindings::resources::{Action, Actions},
+  board::{components::Board, resources::GameBoard},
+  color::components::Brightness,
+  player::{
+    components::{DirectionQueue, KeyScheme},
+    PLAYER_COLORS,
+  },
+  simulation::resources::{GameRng, SimulationClock},
+  snake::{
+    components::{Direction, Living, SnakeBundle, SnakeConfig, Speed},
+    events::Serpentine,
+    utils::revive_snake,
+  },
+  snapshot::utils::{capture, restore},
+};
+use bevy::{
+  prelude::{
+    error, warn, AssetServer, BuildChildren, Color, Commands, CoreSchedule, Entity, EventReader,
+    Query, Res, ResMut, TextBundle, TextStyle, Transform, Visibility, With, Without, World,
+  },
+  text::Text,
+  time::Time,
+  ui::{PositionType, Style, UiRect, Val},
+};
+use std::io::ErrorKind;
+
+/// Lines the peers up across the board, every peer puts them in the same places without drawing
+/// from the generator.
+pub(super) fn spawn_peers(
+  mut commands: Commands,
+  session: Res<Session>,
+  q_board: Query<Entity, With<Board>>,
+  game_board: Res<GameBoard>,
+) {
+  let Ok(board) = q_board.get_single() else {return};
+  let spacing = game_board.width / (session.names.len() + 1) as f32;
+  for (seat, name) in session.names.iter().enumerate() {
+    let peer = (
+      Peer(seat),
+      DirectionQueue::default(),
+      SnakeBundle::new(
+        &mut commands,
+        board,
+        SnakeConfig {
+          name: name.clone(),
+          x: spacing * (seat + 1) as f32 - game_board.width / 2.,
+          color: PLAYER_COLORS[seat % PLAYER_COLORS.len()],
+          direction: Direction::Top,
+          tail_length: INITIAL_PEER_LENGTH,
+          ..Default::default()
+        },
+      ),
+    );
+    let peer = commands.spawn(peer).id();
+    commands.entity(board).add_child(peer);
+  }
+}
+
+/// Notes the local input for the tick `INPUT_DELAY` ahead.
+pub(super) fn sample_input(
+  mut session: ResMut<Session>,
+  actions: Res<Actions>,
+  clock: Res<SimulationClock>,
+) {
+  // Inputs played again were sampled when first played.
+  if session.resimulating {
+    return;
+  }
+  let seat = session.seat;
+  if session.inputs[seat].len() as u64 >= clock.tick + INPUT_DELAY {
+    return;
+  }
+  session.inputs[seat].push(PeerInput {
+    direction: actions.held_turn(KeyScheme::Wasd),
+    respawn: actions.pressed(Some(KeyScheme::Wasd), Action::Respawn),
+  });
+}
+
+/// Steers every peer's snake with its input for the tick, the one predicted when it's not known.
+pub(super) fn apply_inputs(
+  mut session: ResMut<Session>,
+  mut q_peer: Query<(&Peer, &mut Direction, &mut DirectionQueue)>,
+  clock: Res<SimulationClock>,
+) {
+  let inputs = session.inputs_at(clock.tick);
+  for (peer, mut direction, mut direction_queue) in &mut q_peer {
+    let Some(new_direction) = inputs.get(peer.0).and_then(|input| input.direction) else {continue};
+    direction_queue.steer(&mut direction, new_direction);
+  }
+  session.used.insert(clock.tick, inputs);
+}
+
+pub(super) fn respawn(
+  mut commands: Commands,
+  mut q_peer: Query<
+    (
+      Entity,
+      &Peer,
+      &mut Visibility,
+      &mut Transform,
+      &mut Speed,
+      &mut Brightness,
+    ),
+    Without<Living>,
+  >,
+  session: Res<Session>,
+  game_board: Res<GameBoard>,
+  clock: Res<SimulationClock>,
+  mut rng: ResMut<GameRng>,
+) {
+  let Some(inputs) = session.used.get(&clock.tick) else {return};
+  let mut peers = q_peer
+    .iter_mut()
+    .filter(|(_, peer, ..)| inputs.get(peer.0).map_or(false, |input| input.respawn))
+    .collect::<Vec<_>>();
+  // Peers may list their entities in any order, the generator has to be drawn from in the same.
+  peers.sort_by_key(|(_, peer, ..)| peer.0);
+  for (snake, _, mut visibility, mut transform, mut speed, mut brightness) in peers {
+    revive_snake(
+      &mut commands,
+      (
+        snake,
+        &mut visibility,
+        &mut transform,
+        &mut speed,
+        &mut brightness,
+      ),
+      &game_board,
+      &mut rng,
+    );
+  }
+}
+
+pub(super) fn advance_queues(
+  mut serpentine_reader: EventReader<Serpentine>,
+  mut q_peer: Query<(&mut Direction, &mut DirectionQueue), With<Peer>>,
+) {
+  for snake in &mut serpentine_reader {
+    let Ok((mut direction, mut direction_queue)) = q_peer.get_mut(snake.0) else {continue};
+    direction_queue.advance(&mut direction);
+  }
+}
+
+pub(super) fn save_state(world: &mut World) {
+  let snapshot = capture(world);
+  world
+    .resource_mut::<Session>()
+    .snapshots
+    .insert(snapshot.tick, snapshot);
+}
+
+pub(super) fn receive(mut session: ResMut<Session>) {
+  let mut buffer = vec![0; MAX_DATAGRAM];
+  loop {
+    let (length, address) = match session.socket.recv_from(&mut buffer) {
+      Ok(received) => received,
+      // Some platforms report a peer that went away on the next read, other datagrams still wait.
+      Err(err) if err.kind() == ErrorKind::ConnectionReset => continue,
+      Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+      Err(err) => {
+        warn!("Couldn't receive from peers: {err}");
+        break;
+      }
+    };
+    let Some(sender) = session.peers.iter().position(|peer| *peer == address) else {continue};
+    let Ok(message) = serde_json::from_slice::<PeerMessage>(&buffer[..length]) else {continue};
+    match message {
+      // The peer missed the start.
+      PeerMessage::Hello { .. } if session.seat == 0 => {
+        session.send_to(address, &start_message(&session));
+      }
+      PeerMessage::Inputs {
+        seat,
+        start,
+        inputs,
+        ack,
+        checksum,
+      } if seat == sender => {
+        session.acked[seat] = session.acked[seat].max(ack);
+        if let Some(checksum) = checksum {
+          session
+            .remote_checksums
+            .insert((checksum.tick, seat), checksum.value);
+        }
+        let known = session.inputs[seat].len() as u64;
+        // Inputs are kept without gaps, the ones missing come again with the next message.
+        if start == 0 || start > known + 1 {
+          continue;
+        }
+        for (tick, input) in (start..).zip(inputs).skip((known + 1 - start) as usize) {
+          // The tick was played with another input, it has to be played again.
+          if session
+            .used
+            .get(&tick)
+            .map_or(false, |used| used[seat] != input)
+          {
+            session.rollback_to = Some(session.rollback_to.map_or(tick, |from| from.min(tick)));
+          }
+          session.inputs[seat].push(input);
+        }
+      }
+      _ => {}
+    }
+  }
+}
+
+/// Goes back to the state before the first tick played with a wrong input and plays the match
+/// again up to where it was.
+pub(super) fn rollback(world: &mut World) {
+  let current = world.resource::<SimulationClock>().tick;
+  let mut session = world.resource_mut::<Session>();
+  let Some(tick) = session.rollback_to.take() else {return};
+  let Some(snapshot) = session.snapshots.get(&(tick - 1)).cloned() else {
+    error!("No state kept to roll back to tick {tick}");
+    return;
+  };
+  session.resimulating = true;
+  restore(world, &snapshot);
+  for _ in tick..=current {
+    world.run_schedule(CoreSchedule::FixedUpdate);
+  }
+  let mut session = world.resource_mut::<Session>();
+  session.resimulating = false;
+  session.rollbacks += 1;
+}
+
+/// Moves the confirmed tick up to the inputs known from every peer, and compares the checksums of
+/// the ticks confirmed with the other peers'.
+pub(super) fn confirm(mut session: ResMut<Session>, clock: Res<SimulationClock>) {
+  let confirmed = session
+    .inputs
+    .iter()
+    .map(|inputs| inputs.len() as u64)
+    .min()
+    .unwrap_or_default()
+    .min(clock.tick);
+  for tick in session.confirmed + 1..=confirmed {
+    if tick % CHECKSUM_INTERVAL != 0 {
+      continue;
+    }
+    let Some(snapshot) = session.snapshots.get(&tick) else {continue};
+    let value = checksum(snapshot);
+    session.checksums.insert(tick, value);
+  }
+  if confirmed > session.confirmed {
+    session.confirmed = confirmed;
+    // Going back never goes past the confirmed tick.
+    session.used = session.used.split_off(&(confirmed + 1));
+    session.snapshots = session.snapshots.split_off(&confirmed);
+    while session.checksums.len() > CHECKSUM_HISTORY {
+      session.checksums.pop_first();
+    }
+  }
+
+  let session = &mut *session;
+  session.remote_checksums.retain(|(tick, seat), value| {
+    if *tick > session.confirmed {
+      return true;
+    }
+    let Some(local) = session.checksums.get(tick) else {return false};
+    if *local != *value && session.desync.is_none() {
+      error!(
+        "Desync with {} at tick {tick}: {local:016x} here, {value:016x} there",
+        session.names[*seat]
+      );
+      session.desync = Some(*tick);
+    }
+    false
+  });
+}
+
+/// Sends every other peer the local inputs it didn't acknowledge yet.
+pub(super) fn send(session: Res<Session>) {
+  let seat = session.seat;
+  let inputs = &session.inputs[seat];
+  let checksum = session
+    .checksums
+    .last_key_value()
+    .map(|(tick, value)| Checksum {
+      tick: *tick,
+      value: *value,
+    });
+  for (other, peer) in session
+    .peers
+    .iter()
+    .enumerate()
+    .filter(|(other, _)| *other != seat)
+  {
+    let acked = session.acked[other].min(inputs.len());
+    let message = PeerMessage::Inputs {
+      seat,
+      start: acked as u64 + 1,
+      inputs: inputs[acked..].to_vec(),
+      ack: session.inputs[other].len(),
+      checksum,
+    };
+    session.send_to(*peer, &message);
+  }
+}
+
+/// Holds the match still while it's too far ahead of the inputs of the other peers.
+pub(super) fn stall(
+  mut session: ResMut<Session>,
+  mut time: ResMut<Time>,
+  clock: Res<SimulationClock>,
+) {
+  let stalled = clock.tick >= session.confirmed + MAX_PREDICTION;
+  if stalled == session.stalled {
+    return;
+  }
+  session.stalled = stalled;
+  if stalled {
+    time.pause();
+  } else {
+    time.unpause();
+  }
+}
+
+pub(super) fn spawn_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+  commands.spawn((
+    RollbackHud,
+    TextBundle::from_section(
+      "",
+      TextStyle {
+        font: asset_server.load("fonts/UbuntuMono-Regular.ttf"),
+        font_size: 16.,
+        color: Color::WHITE,
+      },
+    )
+    .with_style(Style {
+      position_type: PositionType::Absolute,
+      position: UiRect {
+        left: Val::Px(10.),
+        bottom: Val::Px(10.),
+        ..Default::default()
+      },
+      ..Default::default()
+    }),
+  ));
+}
+
+pub(super) fn update_hud(
+  mut q_hud: Query<&mut Text, With<RollbackHud>>,
+  q_peer: Query<(&Peer, Option<&Living>)>,
+  session: Option<Res<Session>>,
+) {
+  let Ok(mut hud) = q_hud.get_single_mut() else {return};
+  let status = match session {
+    None => "no peers".to_string(),
+    Some(session) => {
+      let living = q_peer
+        .iter()
+        .find(|(peer, _)| peer.0 == session.seat)
+        .map(|(_, living)| living.is_some());
+      let mut status = if session.stalled {
+        let waiting = session
+          .inputs
+          .iter()
+          .zip(&session.names)
+          .filter(|(inputs, _)| inputs.len() as u64 <= session.confirmed)
+          .map(|(_, name)| name.as_str())
+          .collect::<Vec<_>>();
+        format!("waiting for {}", waiting.join(", "))
+      } else if living == Some(false) {
+        format!("{}  r respawn", session.names[session.seat])
+      } else {
+        format!("{}  wasd steer", session.names[session.seat])
+      };
+      status += &format!("  {} rollbacks", session.rollbacks);
+      if let Some(tick) = session.desync {
+        status += &format!("  desync at tick {tick}");
+      }
+      status
+    }
+  };
+  if hud.sections[0].value != status {
+    hud.sections[0].value = status;
+  }
+}
@@ -0,0 +1,88 @@
+use crate::snapshot::format::Snapshot;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Hash of the snakes and the food of `snapshot`, the same on every machine whatever order the
+/// entities were spawned in.
+pub fn checksum(snapshot: &Snapshot) -> u64 {
+  let mut hashes = snapshot
+    .snakes
+    .iter()
+    .filter_map(|snake| {
+      serde_json::to_vec(&(
+        &snake.name,
+        snake.score,
+        snake.head,
+        &snake.body,
+        snake.direction,
+        snake.living,
+      ))
+      .ok()
+    })
+    .chain(
+      snapshot
+        .food
+        .iter()
+        .filter_map(|food| serde_json::to_vec(&(food.food, food.position)).ok()),
+    )
+    .map(|bytes| fnv1a(&bytes))
+    .collect::<Vec<_>>();
+  hashes.sort_unstable();
+  fnv1a(
+    &hashes
+      .iter()
+      .flat_map(|hash| hash.to_le_bytes())
+      .collect::<Vec<_>>(),
+  )
+}
+
+/// 64-bit FNV-1a, unlike the standard hashers it's specified and won't change between builds.
+fn fnv1a(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+    (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{checksum, Snapshot};
+  use crate::{
+    board::resources::GameBoard,
+    simulation::utils::{headless_app, step},
+    snapshot::utils::{capture, restore},
+  };
+
+  fn snapshot_after(ticks: usize) -> Snapshot {
+    let mut app = headless_app(7, GameBoard::with_cells(20, 20));
+    for _ in 0..ticks {
+      step(&mut app);
+    }
+    capture(&mut app.world)
+  }
+
+  #[test]
+  fn order_of_snakes_and_food_does_not_count() {
+    let snapshot = snapshot_after(30);
+    assert!(snapshot.snakes.len() > 1 && snapshot.food.len() > 1);
+    let mut reordered = snapshot.clone();
+    reordered.snakes.reverse();
+    reordered.food.rotate_left(1);
+    assert_eq!(checksum(&reordered), checksum(&snapshot));
+
+    // Restored the other way round, the entities are spawned in another order too.
+    let mut app = headless_app(7, GameBoard::with_cells(20, 20));
+    // Startup first, for the board to restore onto.
+    app.update();
+    restore(&mut app.world, &reordered);
+    assert_eq!(checksum(&capture(&mut app.world)), checksum(&snapshot));
+  }
+
+  #[test]
+  fn any_snake_changing_counts() {
+    let snapshot = snapshot_after(30);
+    let mut changed = snapshot.clone();
+    changed.snakes[1].score += 1;
+    assert_ne!(checksum(&changed), checksum(&snapshot));
+  }
+}
@@ -21,6 +21,7 @@ impl Plugin for ScoreboardPlugin {
 
 pub mod components {
   use bevy::prelude::{Component, Entity};
+  use std::time::Duration;
 
   #[derive(Component)]
   pub struct Scoreboard;
@@ -39,6 +40,11 @@ pub mod components {
 
   #[derive(Debug, Component)]
   pub struct Name(pub String);
+
+  /// Time the snake spent on autopilot, a score with any doesn't count for high scores and is
+  /// marked with a `*`.
+  #[derive(Debug, Component, Default)]
+  pub struct AutopilotTime(pub Duration);
 }
 
 pub mod events {
@@ -1,12 +1,12 @@
 use super::{
-  components::{Place, Score, ScoreValue, Scoreboard},
+  components::{AutopilotTime, Place, Score, ScoreValue, Scoreboard},
   events::ScoreUpdate,
   styles,
 };
 use bevy::{
   prelude::{
     Added, AssetServer, BuildChildren, Changed, Children, Commands, Entity, EventReader,
-    EventWriter, NodeBundle, Query, Res, With,
+    EventWriter, NodeBundle, Or, Query, Res, With,
   },
   text::Text,
   time::Time,
@@ -43,13 +43,17 @@ pub(super) fn add_score(
 
 pub(super) fn update_score(
   mut q_score_values: Query<&mut Text, With<ScoreValue>>,
-  mut q_scores: Query<(&Score, &Children), Changed<Score>>,
+  mut q_scores: Query<
+    (&Score, &Children, Option<&AutopilotTime>),
+    Or<(Changed<Score>, Added<AutopilotTime>)>,
+  >,
   mut score_update_writer: EventWriter<ScoreUpdate>,
 ) {
-  for (score, children) in &mut q_scores {
+  for (score, children, autopilot_time) in &mut q_scores {
     let Some(score_value) = children.get(1) else {return};
     let Ok(mut score_value) = q_score_values.get_mut(*score_value) else {return};
-    score_value.sections[0].value = score.0.to_string();
+    let mark = if autopilot_time.is_some() { "*" } else { "" };
+    score_value.sections[0].value = format!("{}{mark}", score.0);
     score_update_writer.send(ScoreUpdate);
   }
 }
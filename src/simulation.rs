@@ -0,0 +1,194 @@
+use bevy::{
+  ecs::{event::Event, schedule::ExecutorKind},
+  prelude::{
+    App, CoreSchedule, Events, FixedTime, IntoSystemAppConfig, IntoSystemConfig,
+    IntoSystemSetConfigs, Plugin, SystemSet,
+  },
+};
+use std::time::Duration;
+
+/// Length of a simulation step, every gameplay system advances by exactly this much per step.
+pub const TICK: Duration = Duration::from_millis(10);
+
+/// Gameplay systems of each plugin, the groups run one after the other and so do the systems
+/// within them, so a tick always plays out in the same order.
+#[derive(Debug, SystemSet, Clone, PartialEq, Eq, Hash)]
+pub enum SimulationSet {
+  Replay,
+  Player,
+  Snake,
+  Bot,
+  Food,
+  Enemy,
+  Difficulty,
+  /// Notes what the tick left for the next one, last so it sees everything sent.
+  Snapshot,
+}
+
+/// Runs the gameplay in fixed steps, one after the other, so a seed and the inputs fed to each
+/// step are enough to reproduce a match.
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+  fn build(&self, app: &mut App) {
+    // Matches get a seed of their own, so they can be replayed, unless one was given up front.
+    let seed = app
+      .world
+      .get_resource::<resources::Seed>()
+      .map_or_else(rand::random, |seed| seed.0);
+    app
+      .insert_resource(FixedTime::new(TICK))
+      .insert_resource(resources::Seed(seed))
+      .insert_resource(resources::GameRng::seeded(seed))
+      .init_resource::<resources::SimulationClock>()
+      .edit_schedule(CoreSchedule::FixedUpdate, |schedule| {
+        schedule
+          .set_executor_kind(ExecutorKind::SingleThreaded)
+          .configure_sets(
+            (
+              SimulationSet::Replay,
+              SimulationSet::Player,
+              SimulationSet::Snake,
+              SimulationSet::Bot,
+              SimulationSet::Food,
+              SimulationSet::Enemy,
+              SimulationSet::Difficulty,
+              SimulationSet::Snapshot,
+            )
+              .chain(),
+          );
+      })
+      .add_system(
+        systems::advance_clock
+          .before(SimulationSet::Replay)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      );
+  }
+}
+
+/// Registers events a tick leaves to the next one.
+pub trait AddSimulationEvent {
+  /// Like `add_event`, but the events last two ticks rather than two frames, so a frame running
+  /// no tick doesn't lose them before the next tick reads them.
+  fn add_simulation_event<T: Event>(&mut self) -> &mut Self;
+}
+
+impl AddSimulationEvent for App {
+  fn add_simulation_event<T: Event>(&mut self) -> &mut Self {
+    if !self.world.contains_resource::<Events<T>>() {
+      self.init_resource::<Events<T>>().add_system(
+        Events::<T>::update_system
+          .before(SimulationSet::Replay)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      );
+    }
+    self
+  }
+}
+
+pub mod resources {
+  use bevy::prelude::{Deref, DerefMut, Resource};
+  use rand::SeedableRng;
+  use rand_chacha::ChaCha8Rng;
+  use std::time::Duration;
+
+  /// Seed the `GameRng` of the match started from.
+  #[derive(Debug, Clone, Copy, Resource)]
+  pub struct Seed(pub u64);
+
+  /// Source of every random choice made by the simulation.
+  #[derive(Debug, Clone, Resource, Deref, DerefMut)]
+  pub struct GameRng(pub ChaCha8Rng);
+
+  impl GameRng {
+    pub fn seeded(seed: u64) -> Self {
+      Self(ChaCha8Rng::seed_from_u64(seed))
+    }
+  }
+
+  /// Marks an app stepped as fast as it can go rather than in real time.
+  #[derive(Debug, Resource)]
+  pub struct Headless;
+
+  /// Steps simulated so far and the time they add up to.
+  #[derive(Debug, Clone, Copy, Resource, Default)]
+  pub struct SimulationClock {
+    pub tick: u64,
+    pub elapsed: Duration,
+  }
+}
+
+pub mod conditions {
+  use super::{resources::SimulationClock, TICK};
+  use bevy::prelude::Res;
+  use std::time::Duration;
+
+  /// Runs once every `period` of simulated time, going by the clock so no state is kept aside.
+  pub fn every(period: Duration) -> impl FnMut(Res<SimulationClock>) -> bool + Clone {
+    move |clock: Res<SimulationClock>| {
+      clock.elapsed.as_nanos() % period.as_nanos() < TICK.as_nanos()
+    }
+  }
+}
+
+pub mod utils {
+  use super::{
+    resources::{Headless, Seed},
+    SimulationPlugin, TICK,
+  };
+  use crate::{
+    board::{resources::GameBoard, BoardPlugin},
+    difficulty::DifficultyPlugin,
+    enemy::EnemyPlugin,
+    food::FoodPlugin,
+    neural::NeuralPlugin,
+    snake::SnakePlugin,
+    snapshot::SnapshotPlugin,
+    state::GameState,
+  };
+  use bevy::{
+    prelude::{App, FixedTime, MinimalPlugins, State},
+    time::TimeUpdateStrategy,
+    utils::Instant,
+    window::WindowResized,
+  };
+
+  /// App running the gameplay without a window, time only moves forward through `step`.
+  pub fn headless_app(seed: u64, game_board: GameBoard) -> App {
+    let mut app = App::new();
+    app
+      .add_plugins(MinimalPlugins)
+      .insert_resource(TimeUpdateStrategy::ManualInstant(Instant::now()))
+      .insert_resource(Seed(seed))
+      .insert_resource(Headless)
+      .insert_resource(game_board)
+      .add_event::<WindowResized>()
+      .add_state::<GameState>()
+      .insert_resource(State(GameState::Playing))
+      .add_plugin(SimulationPlugin)
+      .add_plugin(BoardPlugin)
+      .add_plugin(EnemyPlugin)
+      .add_plugin(DifficultyPlugin)
+      .add_plugin(NeuralPlugin)
+      .add_plugin(SnakePlugin)
+      .add_plugin(FoodPlugin)
+      .add_plugin(SnapshotPlugin);
+    app
+  }
+
+  /// Advances a headless app by exactly one simulation step.
+  pub fn step(app: &mut App) {
+    app.world.resource_mut::<FixedTime>().tick(TICK);
+    app.update();
+  }
+}
+
+mod systems {
+  use super::resources::SimulationClock;
+  use bevy::prelude::{FixedTime, Res, ResMut};
+
+  pub(super) fn advance_clock(mut clock: ResMut<SimulationClock>, fixed_time: Res<FixedTime>) {
+    clock.tick += 1;
+    clock.elapsed += fixed_time.period;
+  }
+}
@@ -9,10 +9,10 @@ use bevy::{
   },
   time::{Timer, TimerMode},
 };
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::{collections::VecDeque, time::Duration};
 
-use super::utils::SNAKE_NAMES;
+use super::utils::random_name;
 
 pub struct SnakeConfig {
   pub name: String,
@@ -27,7 +27,7 @@ pub struct SnakeConfig {
 impl Default for SnakeConfig {
   fn default() -> Self {
     Self {
-      name: SNAKE_NAMES[rand::thread_rng().gen_range(0..50)].to_string(),
+      name: random_name(&mut rand::thread_rng()),
       color: Color::WHITE,
       tail_length: 4,
       serpentine_duration_ms: 100,
@@ -91,7 +91,8 @@ pub struct Speed(Timer);
 #[derive(Debug, Component)]
 pub struct Nourished(pub u32);
 
-#[derive(Debug, Component, Default, PartialEq, Clone, Copy)]
+#[derive(Debug, Component, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Direction {
   Bottom,
   Left,
@@ -111,6 +112,20 @@ impl Direction {
     }
   }
 
+  pub fn clockwise(&self) -> Self {
+    use Direction::*;
+    match *self {
+      Bottom => Left,
+      Left => Top,
+      Right => Bottom,
+      Top => Right,
+    }
+  }
+
+  pub fn counter_clockwise(&self) -> Self {
+    self.clockwise().opposite()
+  }
+
   pub fn xy(&self, x: f32, y: f32) -> (f32, f32) {
     match self {
       Direction::Bottom => (0., -y),
@@ -163,6 +178,14 @@ impl SnakeBody {
     self.0.len()
   }
 
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub fn segments(&self) -> impl Iterator<Item = Entity> + '_ {
+    self.0.iter().copied()
+  }
+
   pub fn head(&self) -> Option<Entity> {
     self.0.front().copied()
   }
@@ -186,3 +209,27 @@ impl SnakeBody {
 
 #[derive(Debug, Component, Default)]
 pub struct Seeker(pub Vec3);
+
+/// How carefully a seeking snake picks its moves.
+#[derive(Debug, Component, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Reflexes {
+  /// Free cells a move must leave open ahead of the snake, 0 disables the check.
+  pub planning_depth: usize,
+  /// Moves between re-evaluating goals.
+  pub reaction_delay: u32,
+  /// Chance of taking a random turn instead of the planned one.
+  pub mistake_probability: f32,
+  /// Scales how eagerly the snake hunts rather than flees.
+  pub aggressiveness: f32,
+}
+
+impl Default for Reflexes {
+  fn default() -> Self {
+    Self {
+      planning_depth: 0,
+      reaction_delay: 0,
+      mistake_probability: 0.,
+      aggressiveness: 1.,
+    }
+  }
+}
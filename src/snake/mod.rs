@@ -2,13 +2,16 @@ pub mod components;
 mod systems;
 pub mod utils;
 
-use bevy::{
-  prelude::{in_state, App, IntoSystemConfig, Plugin},
-  time::common_conditions::on_timer,
+use bevy::prelude::{
+  in_state, App, CoreSchedule, IntoSystemAppConfigs, IntoSystemConfig, IntoSystemConfigs, Plugin,
+  SystemSet,
 };
 use std::time::Duration;
 
-use crate::state::GameState;
+use crate::{
+  simulation::{conditions::every, AddSimulationEvent, SimulationSet},
+  state::GameState,
+};
 
 pub const MAX_SERPENTINE_DURATION_MS: u64 = 120;
 pub const MIN_SERPENTINE_DURATION_MS: u64 = 30;
@@ -19,21 +22,35 @@ pub const MAX_SERPENTINE_DURATION: Duration = Duration::from_millis(MAX_SERPENTI
 pub const SERPENTINE_DURATION: Duration = Duration::from_millis(SERPENTINE_DURATION_MS);
 pub const MIN_SERPENTINE_DURATION: Duration = Duration::from_millis(MIN_SERPENTINE_DURATION_MS);
 
+#[derive(Debug, SystemSet, Clone, PartialEq, Eq, Hash)]
+pub enum SnakeSystem {
+  /// Steers every `Seeker` towards its target.
+  Seek,
+}
+
 pub struct SnakePlugin;
 
 impl Plugin for SnakePlugin {
   fn build(&self, app: &mut App) {
     app
-      .add_event::<events::SnakeSizeChange>()
-      .add_event::<events::Serpentine>()
-      .add_system(systems::serpentine.run_if(in_state(GameState::Playing)))
-      .add_system(systems::resize)
-      .add_system(systems::grow)
-      .add_system(systems::eat)
-      .add_system(systems::update_score)
-      .add_system(systems::seek)
-      .add_system(systems::disappear.run_if(on_timer(SERPENTINE_DURATION)))
-      .add_system(systems::die);
+      .add_simulation_event::<events::SnakeSizeChange>()
+      .add_simulation_event::<events::Serpentine>()
+      .add_event::<events::SnakeDied>()
+      .add_systems(
+        (
+          systems::serpentine.run_if(in_state(GameState::Playing)),
+          systems::resize,
+          systems::grow,
+          systems::eat,
+          systems::update_score,
+          systems::seek.in_set(SnakeSystem::Seek),
+          systems::disappear.run_if(every(SERPENTINE_DURATION)),
+          systems::die,
+        )
+          .chain()
+          .in_set(SimulationSet::Snake)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      );
   }
 }
 
@@ -50,4 +67,11 @@ pub mod events {
 
   #[derive(Clone, Copy)]
   pub struct Serpentine(pub Entity, pub Vec3);
+
+  #[derive(Debug, Clone, Copy)]
+  pub struct SnakeDied {
+    pub snake: Entity,
+    /// Snake whose head or body it ran into, `None` when it hit itself or was killed outright.
+    pub killer: Option<Entity>,
+  }
 }
@@ -1,18 +1,22 @@
 use super::{
-  components::{Direction, Living, Nourished, Seeker, Snake, SnakeBody, SnakeSegment, Speed},
-  events::{BodySizeChange, Serpentine, SnakeSizeChange},
+  components::{
+    Direction, Living, Nourished, Reflexes, Seeker, Snake, SnakeBody, SnakeSegment, Speed,
+  },
+  events::{BodySizeChange, Serpentine, SnakeDied, SnakeSizeChange},
   utils::{snake_crashed, sort_direction_by_nearest},
 };
 use crate::{
-  board::{components::Board, resources::GameBoard, CELL_SIZE, HALF_CELL_SIZE},
+  board::{components::Board, grid::Grid, resources::GameBoard, CELL_SIZE, HALF_CELL_SIZE},
   collections::ExternalOps,
   food::{components::Food, events::FoodEaten},
   scoreboard::components::{Name, Score, ScoreEntity},
+  simulation::resources::GameRng,
 };
 use bevy::prelude::{
-  BuildChildren, Commands, Entity, EventReader, EventWriter, Query, Res, Sprite, Time, Transform,
-  Vec3, Visibility, With, Without,
+  info, BuildChildren, Commands, Entity, EventReader, EventWriter, FixedTime, Query, Res, ResMut,
+  Sprite, Transform, Vec3, Visibility, With, Without,
 };
+use rand::Rng;
 
 pub(super) fn serpentine(
   mut serpentine_writer: EventWriter<Serpentine>,
@@ -29,10 +33,10 @@ pub(super) fn serpentine(
   >,
   mut q_snake_segment: Query<(&mut Transform, &mut Sprite), (With<SnakeSegment>, Without<Snake>)>,
   game_board: Res<GameBoard>,
-  time: Res<Time>,
+  fixed_time: Res<FixedTime>,
 ) {
   for (snake, mut snake_head, direction, mut body, mut speed, sprite) in &mut q_snake {
-    speed.tick(time.delta());
+    speed.tick(fixed_time.period);
     if !speed.finished() {
       continue;
     }
@@ -100,7 +104,7 @@ pub(super) fn resize(
         body.push_tail(tail);
       }
       Shrink => {
-        let Some(tail) = body.pop_tail() else { return; };
+        let Some(tail) = body.pop_tail() else { continue; };
         commands.entity(tail).despawn();
       }
     }
@@ -130,7 +134,7 @@ pub(super) fn grow(
 
     if nourished_lvl.0 == 0 {
       commands.entity(snake.0).remove::<Nourished>();
-      return;
+      continue;
     }
 
     let Ok(board) = q_board.get_single() else {continue};
@@ -163,7 +167,9 @@ pub(super) fn eat(
 pub(super) fn die(
   mut commands: Commands,
   mut serpentine_reader: EventReader<Serpentine>,
+  mut snake_died_writer: EventWriter<SnakeDied>,
   q_snake_head: Query<(Entity, &Transform), (With<Snake>, With<Living>)>,
+  q_snake_body: Query<(Entity, &SnakeBody), With<Snake>>,
   q_snake_segment: Query<&Transform, With<SnakeSegment>>,
 ) {
   for Serpentine(snake_entity, snake_head) in serpentine_reader.iter().copied() {
@@ -173,7 +179,25 @@ pub(super) fn die(
       snake_entity,
       snake_head,
     ) {
+      let hit = |transform: &Transform| transform.translation.distance(snake_head) < CELL_SIZE;
+      let killer = q_snake_body
+        .iter()
+        .filter(|(snake, _)| *snake != snake_entity)
+        .find(|(snake, body)| {
+          q_snake_head
+            .get(*snake)
+            .map_or(false, |(_, head)| hit(head))
+            || body
+              .segments()
+              .filter_map(|segment| q_snake_segment.get(segment).ok())
+              .any(hit)
+        })
+        .map(|(snake, _)| snake);
       commands.entity(snake_entity).remove::<Living>();
+      snake_died_writer.send(SnakeDied {
+        snake: snake_entity,
+        killer,
+      });
       return;
     }
   }
@@ -195,7 +219,7 @@ pub(super) fn disappear(
       commands.entity(board).remove_children(&[tail]);
       commands.entity(tail).despawn();
     } else if *visibility != Visibility::Hidden {
-      println!("☠️ {}", name.0);
+      info!("☠️ {}", name.0);
       *visibility = Visibility::Hidden;
     }
   }
@@ -215,28 +239,70 @@ pub(super) fn update_score(
 
 pub(super) fn seek(
   mut serpentine_reader: EventReader<Serpentine>,
-  mut q_seeker: Query<(&Seeker, &mut Direction)>,
+  mut q_seeker: Query<(&Seeker, &mut Direction, Option<&Reflexes>)>,
   q_snake_head: Query<(Entity, &Transform), (With<Snake>, Without<SnakeSegment>)>,
   q_snake_segment: Query<&Transform, With<SnakeSegment>>,
   game_board: Res<GameBoard>,
+  mut rng: ResMut<GameRng>,
 ) {
+  let mut grid = None;
   for Serpentine(enemy_entity, head) in serpentine_reader.iter().copied() {
-    let Ok((seeker, mut direction)) = q_seeker.get_mut(enemy_entity) else { continue; };
-    for nearest in sort_direction_by_nearest(head, seeker.0, &game_board) {
-      if nearest == direction.opposite() {
-        continue;
-      }
-      let (x, y) = (head.x, head.y).add(nearest.xy(CELL_SIZE, CELL_SIZE));
-      let head = Vec3::new(x, y, 0.);
-      if !snake_crashed(
-        q_snake_head.iter().map(|h| (h.0, h.1.translation)),
-        q_snake_segment.iter().map(|h| h.translation),
-        enemy_entity,
-        head,
-      ) {
+    let Ok((seeker, mut direction, reflexes)) = q_seeker.get_mut(enemy_entity) else { continue; };
+    let reflexes = reflexes.copied().unwrap_or_default();
+    if rng.gen::<f32>() < reflexes.mistake_probability {
+      let turns = [
+        direction.clockwise(),
+        direction.counter_clockwise(),
+        *direction,
+      ];
+      *direction = turns[rng.gen_range(0..turns.len())];
+      continue;
+    }
+
+    let mut safe_moves = sort_direction_by_nearest(head, seeker.0, &game_board)
+      .into_iter()
+      .filter(|nearest| *nearest != direction.opposite())
+      .filter_map(|nearest| {
+        let (x, y) = (head.x, head.y).add(nearest.xy(CELL_SIZE, CELL_SIZE));
+        let head = Vec3::new(x, y, 0.);
+        (!snake_crashed(
+          q_snake_head.iter().map(|h| (h.0, h.1.translation)),
+          q_snake_segment.iter().map(|h| h.translation),
+          enemy_entity,
+          head,
+        ))
+        .then_some((nearest, head))
+      });
+
+    if reflexes.planning_depth == 0 {
+      if let Some((nearest, _)) = safe_moves.next() {
         *direction = nearest;
+      }
+      continue;
+    }
+
+    let grid = grid.get_or_insert_with(|| {
+      Grid::with_obstacles(
+        &game_board,
+        q_snake_segment
+          .iter()
+          .chain(q_snake_head.iter().map(|h| h.1))
+          .map(|h| h.translation),
+      )
+    });
+    let mut roomiest = None;
+    for (nearest, head) in safe_moves {
+      let area = grid.open_area(grid.cell(head), reflexes.planning_depth);
+      if area >= reflexes.planning_depth {
+        roomiest = Some((nearest, area));
         break;
       }
+      if roomiest.filter(|(_, most)| *most >= area).is_none() {
+        roomiest = Some((nearest, area));
+      }
+    }
+    if let Some((nearest, _)) = roomiest {
+      *direction = nearest;
     }
   }
 }
@@ -2,12 +2,14 @@ use crate::{
   board::{resources::GameBoard, utils::get_board_position, CELL_SIZE},
   collections::ExternalOps,
   color::components::Brightness,
+  scoreboard::components::ScoreEntity,
+  simulation::resources::GameRng,
 };
-use bevy::prelude::{Commands, Entity, Transform, Vec3, Visibility};
-use rand::random;
+use bevy::prelude::{Commands, DespawnRecursiveExt, Entity, Transform, Vec3, Visibility};
+use rand::Rng;
 
 use super::{
-  components::{Direction, Living, Nourished, Speed},
+  components::{Direction, Living, Nourished, SnakeBody, Speed},
   SERPENTINE_DURATION,
 };
 
@@ -64,6 +66,10 @@ pub const SNAKE_NAMES: [&str; 50] = [
   "Jaws",
 ];
 
+pub fn random_name<R: Rng>(rng: &mut R) -> String {
+  SNAKE_NAMES[rng.gen_range(0..SNAKE_NAMES.len())].to_string()
+}
+
 pub fn snake_crashed<H: Iterator<Item = (Entity, Vec3)>, B: Iterator<Item = Vec3>>(
   mut head_iter: H,
   mut body_iter: B,
@@ -135,13 +141,27 @@ pub fn revive_snake(
     &mut Brightness,
   ),
   game_board: &GameBoard,
+  rng: &mut GameRng,
 ) {
   transform.translation = get_board_position(
-    (random::<f32>() - 0.5) * game_board.width,
-    (random::<f32>() - 0.5) * game_board.height,
+    (rng.gen::<f32>() - 0.5) * game_board.width,
+    (rng.gen::<f32>() - 0.5) * game_board.height,
   );
   *visibility = Visibility::Visible;
   brightness.0 = 0.;
   speed.set_duration(SERPENTINE_DURATION);
   commands.entity(snake).insert(Living).insert(Nourished(4));
 }
+
+pub fn despawn_snake(
+  commands: &mut Commands,
+  snake: Entity,
+  body: &SnakeBody,
+  score: &ScoreEntity,
+) {
+  for segment in body.segments() {
+    commands.entity(segment).despawn_recursive();
+  }
+  commands.entity(score.0).despawn_recursive();
+  commands.entity(snake).despawn_recursive();
+}
@@ -0,0 +1,182 @@
+use crate::{
+  difficulty::resources::{Difficulty, DynamicDifficulty},
+  enemy::{
+    components::{Personality, SquadRole},
+    resources::{Roster, RosterEntry},
+    utils::Motive,
+  },
+  food::components::Food,
+  player::components::KeyScheme,
+  snake::components::{Direction, Reflexes},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+
+/// Version written to new snapshots, files of any other version are refused.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Complete state of a match between two ticks, enough to carry on from it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+  pub version: u32,
+  pub seed: u64,
+  pub rng: RngState,
+  pub tick: u64,
+  pub elapsed: Duration,
+  /// Width and height of the board.
+  pub board: [f32; 2],
+  pub playing: bool,
+  pub difficulty: Difficulty,
+  pub dynamic_difficulty: DynamicDifficulty,
+  pub squad: SquadState,
+  pub waves: WavesState,
+  pub roster: Roster,
+  pub roster_configured: bool,
+  pub personalities: BTreeMap<String, Personality>,
+  pub snakes: Vec<SnakeState>,
+  pub food: Vec<FoodState>,
+  pub pending: PendingState,
+}
+
+/// Position of the generator in its stream, it picks up exactly where it left off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RngState {
+  pub seed: [u8; 32],
+  pub stream: u64,
+  pub word_pos: u128,
+}
+
+/// Snake or food another entity refers to, by its index in the snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityRef {
+  Snake(usize),
+  Food(usize),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimerState {
+  pub duration: Duration,
+  pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnakeState {
+  pub name: String,
+  pub color: [f32; 4],
+  pub score: usize,
+  pub head: [f32; 3],
+  /// Segments from the neck to the tail.
+  pub body: Vec<[f32; 3]>,
+  pub direction: Direction,
+  pub speed: TimerState,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub nourished: Option<u32>,
+  pub brightness: f32,
+  pub living: bool,
+  pub visible: bool,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub seeker: Option<[f32; 3]>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub reflexes: Option<Reflexes>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub player: Option<PlayerState>,
+  /// Seat of the peer steering it, in a match played over rollback.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub peer: Option<usize>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub enemy: Option<EnemyState>,
+}
+
+/// The player's `DirectionQueue`, with the turn queued for its last move already taken.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerState {
+  pub previous: Direction,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub turns: Vec<Direction>,
+  /// Seat among the local players.
+  #[serde(default)]
+  pub seat: usize,
+  #[serde(default)]
+  pub keys: KeyScheme,
+  #[serde(default)]
+  pub autopilot: bool,
+  /// Time spent on autopilot, in milliseconds.
+  #[serde(default)]
+  pub autopilot_ms: u64,
+  #[serde(default)]
+  pub assists: u32,
+  /// Assists used since the snake last spawned.
+  #[serde(default)]
+  pub assists_used: u32,
+  /// Cell the player steers towards with the pointer.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub pointer: Option<[i32; 2]>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnemyState {
+  /// Built-in archetype the enemy plays, or `CUSTOM` for a personality from config, if any.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub archetype: Option<String>,
+  pub personality: Personality,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub goal: Option<GoalState>,
+  pub cooldown: u32,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub respawn: Option<TimerState>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub wave: Option<u32>,
+  /// Left to die out since the difficulty lowered the enemy count.
+  #[serde(default, skip_serializing_if = "is_false")]
+  pub retired: bool,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub squad_role: Option<SquadRole>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub network: Option<String>,
+  /// Seat of an external bot, its link carries over from the running game.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub bot: Option<usize>,
+}
+
+fn is_false(retired: &bool) -> bool {
+  !*retired
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GoalState {
+  pub motive: Motive,
+  pub entity: Option<EntityRef>,
+  pub target: [f32; 3],
+  pub utility: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FoodState {
+  pub food: Food,
+  pub position: [f32; 3],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SquadState {
+  pub enabled: bool,
+  pub size: usize,
+  pub prey: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WavesState {
+  pub sent: u32,
+  pub timer: TimerState,
+  pub pending: bool,
+}
+
+/// Events sent by the last tick for the next one to handle.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PendingState {
+  /// Snakes growing, or shrinking when `false`.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub size_changes: Vec<(usize, bool)>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub enemy_spawns: Vec<(RosterEntry, Option<u32>)>,
+}
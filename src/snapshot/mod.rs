@@ -0,0 +1,127 @@
+pub mod format;
+mod systems;
+pub mod utils;
+
+use crate::{replay::resources::Playback, simulation::SimulationSet};
+use bevy::{
+  ecs::schedule::common_conditions::not,
+  prelude::{
+    resource_exists, App, CoreSchedule, IntoSystemAppConfig, IntoSystemConfig, Plugin, StartupSet,
+    SystemSet,
+  },
+};
+
+pub const QUICKSAVE_FILE: &str = "snapshots/quicksave.json";
+
+#[derive(Debug, SystemSet, Clone, PartialEq, Eq, Hash)]
+pub enum SnapshotSystem {
+  /// Notes the events the tick left to the next one.
+  Track,
+}
+
+/// Keeps track of the events a tick leaves to the next one, so snapshots can carry them over.
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<resources::PendingEvents>()
+      .add_event::<events::SnapshotLoaded>()
+      .add_system(
+        systems::forget_handled
+          .before(SimulationSet::Replay)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      )
+      .add_system(
+        systems::track_pending
+          .in_set(SnapshotSystem::Track)
+          .in_set(SimulationSet::Snapshot)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      );
+  }
+}
+
+/// Saves the match and loads it back with the quicksave and quickload actions, `--load <path>`
+/// starts from a snapshot and `--snapshot <path>` picks the quicksave file.
+pub struct QuicksavePlugin;
+
+impl Plugin for QuicksavePlugin {
+  fn build(&self, app: &mut App) {
+    let settings = resources::SnapshotSettings::from_args(std::env::args());
+    let load = settings.load.is_some();
+    app
+      .insert_resource(settings)
+      .add_startup_system(
+        systems::load_at_startup
+          .in_base_set(StartupSet::PostStartup)
+          .run_if(move || load),
+      )
+      // A replay's ticks can't be swapped for a snapshot's.
+      .add_system(systems::quicksave.run_if(not(resource_exists::<Playback>())));
+  }
+}
+
+pub mod events {
+  /// The match was replaced by one loaded from a file.
+  pub struct SnapshotLoaded;
+}
+
+pub mod resources {
+  use super::QUICKSAVE_FILE;
+  use crate::{
+    enemy::{events::SpawnEnemy, resources::RosterEntry},
+    snake::events::{Serpentine, SnakeSizeChange},
+  };
+  use bevy::{
+    ecs::event::ManualEventReader,
+    prelude::{Entity, Resource},
+  };
+
+  #[derive(Debug, Resource)]
+  pub struct SnapshotSettings {
+    /// Snapshot to start the match from.
+    pub load: Option<String>,
+    pub quicksave: String,
+  }
+
+  impl Default for SnapshotSettings {
+    fn default() -> Self {
+      Self {
+        load: None,
+        quicksave: QUICKSAVE_FILE.to_string(),
+      }
+    }
+  }
+
+  impl SnapshotSettings {
+    /// Reads `--load <path>` and `--snapshot <path>` arguments.
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Self {
+      let mut settings = Self::default();
+      while let Some(arg) = args.next() {
+        match arg.as_str() {
+          "--load" => settings.load = args.next(),
+          "--snapshot" => {
+            if let Some(path) = args.next() {
+              settings.quicksave = path;
+            }
+          }
+          _ => {}
+        }
+      }
+      settings
+    }
+  }
+
+  /// Events sent by the last tick that the next one is still to handle.
+  #[derive(Default, Resource)]
+  pub struct PendingEvents {
+    /// Snakes that moved, the player takes its queued turn on the next tick.
+    pub moved: Vec<Entity>,
+    /// Snakes growing, or shrinking when `false`.
+    pub size_changes: Vec<(Entity, bool)>,
+    pub enemy_spawns: Vec<(RosterEntry, Option<u32>)>,
+    pub(super) serpentine_reader: ManualEventReader<Serpentine>,
+    pub(super) size_change_reader: ManualEventReader<SnakeSizeChange>,
+    pub(super) spawn_enemy_reader: ManualEventReader<SpawnEnemy>,
+  }
+}
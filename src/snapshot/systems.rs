@@ -0,0 +1,97 @@
+use super::{
+  events::SnapshotLoaded,
+  format::SNAPSHOT_VERSION,
+  resources::{PendingEvents, SnapshotSettings},
+  utils,
+};
+use crate::{
+  bindings::resources::{Action, Actions},
+  config,
+  enemy::events::SpawnEnemy,
+  replay::resources::Recorder,
+  snake::events::{BodySizeChange, Serpentine, SnakeSizeChange},
+};
+use bevy::prelude::{info, warn, Events, Res, ResMut, World};
+
+pub(super) fn forget_handled(
+  mut pending: ResMut<PendingEvents>,
+  serpentines: Res<Events<Serpentine>>,
+  size_changes: Res<Events<SnakeSizeChange>>,
+  enemy_spawns: Res<Events<SpawnEnemy>>,
+) {
+  // Whatever was sent before the tick is handled by it.
+  pending.moved.clear();
+  pending.size_changes.clear();
+  pending.enemy_spawns.clear();
+  pending.serpentine_reader.clear(&serpentines);
+  pending.size_change_reader.clear(&size_changes);
+  pending.spawn_enemy_reader.clear(&enemy_spawns);
+}
+
+pub(super) fn track_pending(
+  mut pending: ResMut<PendingEvents>,
+  serpentines: Res<Events<Serpentine>>,
+  size_changes: Res<Events<SnakeSizeChange>>,
+  enemy_spawns: Res<Events<SpawnEnemy>>,
+) {
+  let pending = &mut *pending;
+  pending.moved.extend(
+    pending
+      .serpentine_reader
+      .iter(&serpentines)
+      .map(|Serpentine(snake, _)| *snake),
+  );
+  pending.size_changes.extend(
+    pending
+      .size_change_reader
+      .iter(&size_changes)
+      .map(|(snake, change)| (*snake, matches!(change, BodySizeChange::Grow))),
+  );
+  pending.enemy_spawns.extend(
+    pending
+      .spawn_enemy_reader
+      .iter(&enemy_spawns)
+      .map(|spawn| (spawn.entry.clone(), spawn.wave)),
+  );
+}
+
+pub(super) fn load_at_startup(world: &mut World) {
+  let Some(path) = world.resource::<SnapshotSettings>().load.clone() else {return};
+  load(world, &path);
+}
+
+pub(super) fn quicksave(world: &mut World) {
+  let actions = world.resource::<Actions>();
+  let (save, load_back) = (
+    actions.just_pressed(None, Action::Quicksave),
+    actions.just_pressed(None, Action::Quickload),
+  );
+  let path = world.resource::<SnapshotSettings>().quicksave.clone();
+  if save {
+    let snapshot = utils::capture(world);
+    match config::save(&path, &snapshot) {
+      Ok(()) => info!("Snapshot of tick {} saved to {path}", snapshot.tick),
+      Err(err) => warn!("Couldn't save snapshot to {path}: {err}"),
+    }
+  } else if load_back {
+    load(world, &path);
+  }
+}
+
+fn load(world: &mut World, path: &str) {
+  match config::load_versioned(path, SNAPSHOT_VERSION) {
+    Ok(snapshot) => {
+      // The recording couldn't play back past the snapshot, it ends where the match was left.
+      if let Some(recorder) = world.remove_resource::<Recorder>() {
+        match config::save(&recorder.path, &recorder.replay) {
+          Ok(()) => info!("Recording stopped, replay saved to {}", recorder.path),
+          Err(err) => warn!("Couldn't save replay to {}: {err}", recorder.path),
+        }
+      }
+      utils::restore(world, &snapshot);
+      world.send_event(SnapshotLoaded);
+      info!("Snapshot of tick {} loaded from {path}", snapshot.tick);
+    }
+    Err(err) => warn!("Couldn't load snapshot {path}: {err}"),
+  }
+}
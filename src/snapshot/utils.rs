@@ -0,0 +1,636 @@
+use super::{
+  format::{
+    EnemyState, EntityRef, FoodState, GoalState, PendingState, PlayerState, RngState, SnakeState,
+    Snapshot, SquadState, TimerState, WavesState, SNAPSHOT_VERSION,
+  },
+  resources::PendingEvents,
+};
+use crate::{
+  board::{
+    components::{Board, BoardSprite},
+    resources::GameBoard,
+    utils::create_cell_bundle,
+  },
+  bot::components::ExternalBot,
+  color::components::{BaseColor, Brightness},
+  difficulty::resources::{Difficulty, DynamicDifficulty},
+  enemy::{
+    components::{
+      Custom, Eater, Enemy, Glutton, Hunter, Killer, Personality, RespawnTimer, Retired, Speedster,
+      SquadMember, Target, WaveEnemy,
+    },
+    events::SpawnEnemy,
+    resources::{
+      Personalities, Roster, SquadCoordinator, Waves, CUSTOM, EATER, GLUTTON, HUNTER, KILLER,
+      SPEEDSTER,
+    },
+    utils::{spawn_enemy, Goal},
+  },
+  food::{
+    components::Food,
+    events::{FoodEaten, SpawnFood},
+  },
+  neural::components::NeuralBrain,
+  player::{
+    components::{Assists, Autopilot, DirectionQueue, KeyScheme, Player, PointerTarget},
+    events::{AddPlayer, RespawnPlayer, SetAutopilot},
+  },
+  rollback::components::Peer,
+  scoreboard::components::{AutopilotTime, Name, Score, ScoreEntity},
+  simulation::resources::{GameRng, Seed, SimulationClock},
+  snake::{
+    components::{
+      Direction, Living, Nourished, Reflexes, Seeker, Snake, SnakeBody, SnakeBundle, SnakeConfig,
+      Speed,
+    },
+    events::{BodySizeChange, Serpentine, SnakeDied, SnakeSizeChange},
+  },
+  state::GameState,
+};
+use bevy::{
+  ecs::{event::Event, system::SystemState},
+  prelude::{
+    BuildChildren, Color, Commands, DespawnRecursiveExt, Entity, Events, IVec2, Query, Sprite,
+    SpriteBundle, State, Transform, Vec2, Vec3, Visibility, With, World,
+  },
+  time::{Timer, TimerMode},
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::{collections::HashMap, time::Duration};
+
+/// Takes down the whole match, between two ticks.
+pub fn capture(world: &mut World) -> Snapshot {
+  let snakes = world
+    .query_filtered::<Entity, With<Snake>>()
+    .iter(world)
+    .collect::<Vec<_>>();
+  let food = world
+    .query::<(Entity, &Food, &Transform)>()
+    .iter(world)
+    .map(|(entity, food, transform)| {
+      (
+        entity,
+        FoodState {
+          food: *food,
+          position: transform.translation.to_array(),
+        },
+      )
+    })
+    .collect::<Vec<_>>();
+  let indices = Indices {
+    snakes: index(&snakes),
+    food: index(&food.iter().map(|(entity, _)| *entity).collect::<Vec<_>>()),
+  };
+
+  let rng = &world.resource::<GameRng>().0;
+  let clock = world.resource::<SimulationClock>();
+  let game_board = world.resource::<GameBoard>();
+  let squad = world.resource::<SquadCoordinator>();
+  let waves = world.resource::<Waves>();
+  let roster = world.resource::<Roster>();
+  let pending = world.get_resource::<PendingEvents>();
+  Snapshot {
+    version: SNAPSHOT_VERSION,
+    seed: world.resource::<Seed>().0,
+    rng: RngState {
+      seed: rng.get_seed(),
+      stream: rng.get_stream(),
+      word_pos: rng.get_word_pos(),
+    },
+    tick: clock.tick,
+    elapsed: clock.elapsed,
+    board: [game_board.width, game_board.height],
+    playing: world.resource::<State<GameState>>().0 == GameState::Playing,
+    difficulty: *world.resource::<Difficulty>(),
+    dynamic_difficulty: world.resource::<DynamicDifficulty>().clone(),
+    squad: SquadState {
+      enabled: squad.enabled,
+      size: squad.size,
+      prey: squad
+        .prey
+        .and_then(|prey| indices.snakes.get(&prey).copied()),
+    },
+    waves: WavesState {
+      sent: waves.sent,
+      timer: timer_state(&waves.timer),
+      pending: waves.pending,
+    },
+    roster: roster.clone(),
+    roster_configured: roster.configured,
+    personalities: world.resource::<Personalities>().0.clone(),
+    snakes: snakes
+      .iter()
+      .map(|snake| {
+        let moved = pending.map_or(false, |pending| pending.moved.contains(snake));
+        snake_state(world, *snake, moved, &indices)
+      })
+      .collect(),
+    food: food.into_iter().map(|(_, food)| food).collect(),
+    pending: pending.map_or_else(Default::default, |pending| PendingState {
+      size_changes: pending
+        .size_changes
+        .iter()
+        .filter_map(|(snake, grow)| Some((*indices.snakes.get(snake)?, *grow)))
+        .collect(),
+      enemy_spawns: pending.enemy_spawns.clone(),
+    }),
+  }
+}
+
+/// Replaces the match with the snapshot's, between two ticks.
+pub fn restore(world: &mut World, snapshot: &Snapshot) {
+  // Events in flight belong to the match being replaced.
+  clear_events::<Serpentine>(world);
+  clear_events::<SnakeSizeChange>(world);
+  clear_events::<SnakeDied>(world);
+  clear_events::<FoodEaten>(world);
+  clear_events::<SpawnFood>(world);
+  clear_events::<SpawnEnemy>(world);
+  clear_events::<RespawnPlayer>(world);
+  clear_events::<AddPlayer>(world);
+  clear_events::<SetAutopilot>(world);
+
+  // Bots keep their programs, only their snakes are replaced.
+  let mut bots = HashMap::new();
+  for entity in world
+    .query_filtered::<Entity, With<ExternalBot>>()
+    .iter(world)
+    .collect::<Vec<_>>()
+  {
+    if let Some(bot) = world.entity_mut(entity).take::<ExternalBot>() {
+      bots.insert(bot.index, bot);
+    }
+  }
+  let snakes = world
+    .query::<(Entity, &SnakeBody, &ScoreEntity)>()
+    .iter(world)
+    .map(|(snake, body, score)| (snake, body.segments().collect::<Vec<_>>(), score.0))
+    .collect::<Vec<_>>();
+  for (snake, segments, score) in snakes {
+    for entity in segments.into_iter().chain([score, snake]) {
+      if let Some(entity) = world.get_entity_mut(entity) {
+        entity.despawn_recursive();
+      }
+    }
+  }
+  for food in world
+    .query_filtered::<Entity, With<Food>>()
+    .iter(world)
+    .collect::<Vec<_>>()
+  {
+    world.entity_mut(food).despawn_recursive();
+  }
+
+  let RngState {
+    seed,
+    stream,
+    word_pos,
+  } = snapshot.rng;
+  let mut rng = ChaCha8Rng::from_seed(seed);
+  rng.set_stream(stream);
+  rng.set_word_pos(word_pos);
+  world.insert_resource(GameRng(rng));
+  world.insert_resource(Seed(snapshot.seed));
+  world.insert_resource(SimulationClock {
+    tick: snapshot.tick,
+    elapsed: snapshot.elapsed,
+  });
+  let [width, height] = snapshot.board;
+  // Children are pushed back onto a board that changed, those of the same one stay where they are.
+  let mut game_board = world.resource_mut::<GameBoard>();
+  if game_board.width != width || game_board.height != height {
+    *game_board = GameBoard { width, height };
+  }
+  for mut board_sprite in world
+    .query_filtered::<&mut Sprite, With<BoardSprite>>()
+    .iter_mut(world)
+  {
+    board_sprite.custom_size = Some(Vec2::new(width, height));
+  }
+  world.resource_mut::<State<GameState>>().0 = if snapshot.playing {
+    GameState::Playing
+  } else {
+    GameState::Paused
+  };
+  world.insert_resource(snapshot.difficulty);
+  world.insert_resource(snapshot.dynamic_difficulty.clone());
+  world.insert_resource(SquadCoordinator {
+    enabled: snapshot.squad.enabled,
+    size: snapshot.squad.size,
+    prey: None,
+  });
+  world.insert_resource(Waves {
+    sent: snapshot.waves.sent,
+    timer: timer(snapshot.waves.timer, TimerMode::Repeating),
+    pending: snapshot.waves.pending,
+  });
+  world.insert_resource(Roster {
+    configured: snapshot.roster_configured,
+    ..snapshot.roster.clone()
+  });
+  world.insert_resource(Personalities(snapshot.personalities.clone()));
+
+  let mut system_state = SystemState::<(Commands, Query<Entity, With<Board>>)>::new(world);
+  let (mut commands, q_board) = system_state.get_mut(world);
+  let Ok(board) = q_board.get_single() else {return};
+  let snakes = snapshot
+    .snakes
+    .iter()
+    .map(|snake| spawn_snake(&mut commands, board, snake))
+    .collect::<Vec<_>>();
+  let food = snapshot
+    .food
+    .iter()
+    .map(|food| {
+      let position = Vec3::from_array(food.position);
+      let entity = commands
+        .spawn((
+          food.food,
+          SpriteBundle {
+            transform: Transform::from_translation(position),
+            ..create_cell_bundle(food.food.into(), position.x, position.y)
+          },
+        ))
+        .id();
+      commands.entity(board).add_child(entity);
+      entity
+    })
+    .collect::<Vec<_>>();
+  system_state.apply(world);
+
+  let entity = |entity_ref: EntityRef| match entity_ref {
+    EntityRef::Snake(index) => snakes.get(index).copied(),
+    EntityRef::Food(index) => food.get(index).copied(),
+  };
+  for (state, snake) in snapshot.snakes.iter().zip(&snakes) {
+    restore_snake(world, *snake, state, &entity, &mut bots);
+  }
+  world.resource_mut::<SquadCoordinator>().prey = snapshot
+    .squad
+    .prey
+    .and_then(|prey| entity(EntityRef::Snake(prey)));
+
+  let size_changes = snapshot
+    .pending
+    .size_changes
+    .iter()
+    .filter_map(|(snake, grow)| Some((*snakes.get(*snake)?, *grow)))
+    .collect::<Vec<_>>();
+  for (snake, grow) in &size_changes {
+    let change = if *grow {
+      BodySizeChange::Grow
+    } else {
+      BodySizeChange::Shrink
+    };
+    world.send_event::<SnakeSizeChange>((*snake, change));
+  }
+  for (entry, wave) in &snapshot.pending.enemy_spawns {
+    world.send_event(SpawnEnemy {
+      entry: entry.clone(),
+      wave: *wave,
+    });
+  }
+  // Still pending until the next tick, so capturing right away gives the snapshot back.
+  if let Some(mut pending) = world.get_resource_mut::<PendingEvents>() {
+    pending.moved.clear();
+    pending.size_changes = size_changes;
+    pending.enemy_spawns = snapshot.pending.enemy_spawns.clone();
+  }
+}
+
+struct Indices {
+  snakes: HashMap<Entity, usize>,
+  food: HashMap<Entity, usize>,
+}
+
+impl Indices {
+  fn of(&self, entity: Entity) -> Option<EntityRef> {
+    self
+      .snakes
+      .get(&entity)
+      .map(|index| EntityRef::Snake(*index))
+      .or_else(|| self.food.get(&entity).map(|index| EntityRef::Food(*index)))
+  }
+}
+
+fn index(entities: &[Entity]) -> HashMap<Entity, usize> {
+  entities
+    .iter()
+    .enumerate()
+    .map(|(i, entity)| (*entity, i))
+    .collect()
+}
+
+fn timer_state(timer: &Timer) -> TimerState {
+  TimerState {
+    duration: timer.duration(),
+    elapsed: timer.elapsed(),
+  }
+}
+
+fn timer(state: TimerState, mode: TimerMode) -> Timer {
+  let mut timer = Timer::new(state.duration, mode);
+  timer.set_elapsed(state.elapsed);
+  timer
+}
+
+fn clear_events<E: Event>(world: &mut World) {
+  if let Some(mut events) = world.get_resource_mut::<Events<E>>() {
+    events.clear();
+  }
+}
+
+fn snake_state(world: &World, entity: Entity, moved: bool, indices: &Indices) -> SnakeState {
+  let snake = world.entity(entity);
+  let position = |entity: Entity| {
+    world
+      .get::<Transform>(entity)
+      .map_or([0.; 3], |transform| transform.translation.to_array())
+  };
+  let score = snake.get::<ScoreEntity>().map(|score| score.0);
+  let player = snake.get::<DirectionQueue>().map(|direction_queue| {
+    let mut direction = snake.get::<Direction>().copied().unwrap_or_default();
+    let mut direction_queue = DirectionQueue {
+      previous: direction_queue.previous,
+      turns: direction_queue.turns.clone(),
+    };
+    // The turn is taken at the start of the next tick, the snapshot starts past it.
+    if moved {
+      direction_queue.advance(&mut direction);
+    }
+    (direction, direction_queue)
+  });
+  SnakeState {
+    name: score
+      .and_then(|score| world.get::<Name>(score))
+      .map(|name| name.0.clone())
+      .unwrap_or_default(),
+    color: snake
+      .get::<BaseColor>()
+      .map_or(Color::WHITE, |color| color.0)
+      .as_rgba_f32(),
+    score: score
+      .and_then(|score| world.get::<Score>(score))
+      .map_or(0, |score| score.0),
+    head: position(entity),
+    body: snake
+      .get::<SnakeBody>()
+      .map(|body| body.segments().map(position).collect())
+      .unwrap_or_default(),
+    direction: player.as_ref().map_or_else(
+      || snake.get::<Direction>().copied().unwrap_or_default(),
+      |(direction, _)| *direction,
+    ),
+    speed: snake
+      .get::<Speed>()
+      .map_or_else(TimerState::default, |speed| timer_state(speed)),
+    nourished: snake.get::<Nourished>().map(|nourished| nourished.0),
+    brightness: snake
+      .get::<Brightness>()
+      .map_or(0., |brightness| brightness.0),
+    living: snake.contains::<Living>(),
+    visible: snake.get::<Visibility>() != Some(&Visibility::Hidden),
+    seeker: snake.get::<Seeker>().map(|seeker| seeker.0.to_array()),
+    reflexes: snake.get::<Reflexes>().copied(),
+    player: player.map(|(_, direction_queue)| PlayerState {
+      previous: direction_queue.previous,
+      turns: direction_queue.turns.into(),
+      seat: snake.get::<Player>().map_or(0, |player| player.0),
+      keys: snake.get::<KeyScheme>().copied().unwrap_or_default(),
+      autopilot: snake.contains::<Autopilot>(),
+      autopilot_ms: score
+        .and_then(|score| world.get::<AutopilotTime>(score))
+        .map_or(0, |autopilot_time| autopilot_time.0.as_millis() as u64),
+      assists: snake.get::<Assists>().map_or(0, |assists| assists.per_life),
+      assists_used: snake.get::<Assists>().map_or(0, |assists| assists.used),
+      pointer: snake
+        .get::<PointerTarget>()
+        .and_then(|target| target.0.map(Into::into)),
+    }),
+    peer: snake.get::<Peer>().map(|peer| peer.0),
+    enemy: snake.contains::<Enemy>().then(|| {
+      let target = snake.get::<Target>();
+      EnemyState {
+        archetype: [
+          (snake.contains::<Eater>(), EATER),
+          (snake.contains::<Killer>(), KILLER),
+          (snake.contains::<Speedster>(), SPEEDSTER),
+          (snake.contains::<Glutton>(), GLUTTON),
+          (snake.contains::<Hunter>(), HUNTER),
+          (snake.contains::<Custom>(), CUSTOM),
+        ]
+        .into_iter()
+        .find_map(|(is, archetype)| is.then(|| archetype.to_string())),
+        personality: snake.get::<Personality>().copied().unwrap_or_default(),
+        goal: target.and_then(|target| target.goal).map(|goal| GoalState {
+          motive: goal.motive,
+          entity: goal.entity.and_then(|entity| indices.of(entity)),
+          target: goal.target.to_array(),
+          utility: goal.utility,
+        }),
+        cooldown: target.map_or(0, |target| target.cooldown),
+        respawn: snake
+          .get::<RespawnTimer>()
+          .map(|respawn_timer| timer_state(&respawn_timer.0)),
+        wave: snake.get::<WaveEnemy>().map(|wave| wave.0),
+        retired: snake.contains::<Retired>(),
+        squad_role: snake.get::<SquadMember>().map(|member| member.0),
+        network: snake
+          .get::<NeuralBrain>()
+          .map(|brain| brain.network.clone()),
+        bot: snake.get::<ExternalBot>().map(|bot| bot.index),
+      }
+    }),
+  }
+}
+
+fn spawn_snake(commands: &mut Commands, board: Entity, state: &SnakeState) -> Entity {
+  let [r, g, b, a] = state.color;
+  let config = SnakeConfig {
+    name: state.name.clone(),
+    x: state.head[0],
+    y: state.head[1],
+    serpentine_duration_ms: state.speed.duration.as_millis() as u64,
+    color: Color::rgba(r, g, b, a),
+    direction: state.direction,
+    tail_length: state.body.len(),
+  };
+  if let Some(enemy) = &state.enemy {
+    let reflexes = state.reflexes.unwrap_or_default();
+    return spawn_enemy((enemy.personality, reflexes), config, commands, board);
+  }
+  let snake = SnakeBundle::new(commands, board, config);
+  let snake = commands.spawn(snake).id();
+  if let Some(player) = &state.player {
+    commands.entity(snake).insert(DirectionQueue {
+      previous: player.previous,
+      turns: player.turns.iter().copied().collect(),
+    });
+    // The seeker and reflexes are restored along with the enemies'.
+    if player.autopilot {
+      commands.entity(snake).insert(Autopilot::bundle());
+    }
+    match state.peer {
+      Some(seat) => commands.entity(snake).insert(Peer(seat)),
+      None => commands.entity(snake).insert((
+        Player(player.seat),
+        player.keys,
+        Assists {
+          per_life: player.assists,
+          used: player.assists_used,
+        },
+        PointerTarget(player.pointer.map(IVec2::from)),
+      )),
+    };
+  }
+  commands.entity(board).add_child(snake);
+  snake
+}
+
+/// Sets what `SnakeBundle` and `spawn_enemy` can't be told up front.
+fn restore_snake(
+  world: &mut World,
+  entity: Entity,
+  state: &SnakeState,
+  entity_of: &impl Fn(EntityRef) -> Option<Entity>,
+  bots: &mut HashMap<usize, ExternalBot>,
+) {
+  let segments = world
+    .get::<SnakeBody>(entity)
+    .map(|body| body.segments().collect::<Vec<_>>())
+    .unwrap_or_default();
+  for (segment, position) in segments.into_iter().zip(&state.body) {
+    if let Some(mut transform) = world.get_mut::<Transform>(segment) {
+      transform.translation = Vec3::from_array(*position);
+    }
+  }
+  if let Some(score) = world.get::<ScoreEntity>(entity).map(|score| score.0) {
+    if let Some(mut score) = world.get_mut::<Score>(score) {
+      score.0 = state.score;
+    }
+    let autopilot_ms = state
+      .player
+      .as_ref()
+      .map_or(0, |player| player.autopilot_ms);
+    if autopilot_ms > 0 {
+      world
+        .entity_mut(score)
+        .insert(AutopilotTime(Duration::from_millis(autopilot_ms)));
+    }
+  }
+
+  let mut snake = world.entity_mut(entity);
+  if let Some(mut transform) = snake.get_mut::<Transform>() {
+    transform.translation = Vec3::from_array(state.head);
+  }
+  if let Some(mut speed) = snake.get_mut::<Speed>() {
+    speed.set_duration(state.speed.duration);
+    speed.set_elapsed(state.speed.elapsed);
+  }
+  if let Some(mut brightness) = snake.get_mut::<Brightness>() {
+    brightness.0 = state.brightness;
+  }
+  if !state.visible {
+    snake.insert(Visibility::Hidden);
+  }
+  if !state.living {
+    snake.remove::<Living>();
+  }
+  if let Some(nourished) = state.nourished {
+    snake.insert(Nourished(nourished));
+  }
+  match state.seeker {
+    Some(seeker) => snake.insert(Seeker(Vec3::from_array(seeker))),
+    None => snake.remove::<Seeker>(),
+  };
+  match state.reflexes {
+    Some(reflexes) => snake.insert(reflexes),
+    None => snake.remove::<Reflexes>(),
+  };
+
+  let Some(enemy) = &state.enemy else {return};
+  match enemy.archetype.as_deref() {
+    Some(EATER) => snake.insert(Eater),
+    Some(KILLER) => snake.insert(Killer),
+    Some(SPEEDSTER) => snake.insert(Speedster),
+    Some(GLUTTON) => snake.insert(Glutton),
+    Some(HUNTER) => snake.insert(Hunter),
+    Some(CUSTOM) => snake.insert(Custom),
+    _ => &mut snake,
+  };
+  snake.insert(Target {
+    goal: enemy.goal.map(|goal| Goal {
+      motive: goal.motive,
+      entity: goal.entity.and_then(entity_of),
+      target: Vec3::from_array(goal.target),
+      utility: goal.utility,
+    }),
+    cooldown: enemy.cooldown,
+  });
+  if let Some(respawn) = enemy.respawn {
+    snake.insert(RespawnTimer(timer(respawn, TimerMode::Once)));
+  }
+  if let Some(wave) = enemy.wave {
+    snake.insert(WaveEnemy(wave));
+  }
+  if enemy.retired {
+    snake.insert(Retired);
+  }
+  if let Some(role) = enemy.squad_role {
+    snake.insert(SquadMember(role));
+  }
+  if let Some(network) = &enemy.network {
+    snake.insert(NeuralBrain {
+      network: network.clone(),
+    });
+  }
+  if let Some(index) = enemy.bot {
+    snake.insert(
+      bots
+        .remove(&index)
+        .unwrap_or_else(|| ExternalBot::unlinked(index)),
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{capture, restore};
+  use crate::{
+    board::resources::GameBoard,
+    player::events::RespawnPlayer,
+    simulation::utils::{headless_app, step},
+  };
+  use bevy::prelude::App;
+
+  fn match_app(seed: u64) -> App {
+    let mut app = headless_app(seed, GameBoard::with_cells(30, 30));
+    app.add_event::<RespawnPlayer>();
+    app.update();
+    app
+  }
+
+  fn state(app: &mut App) -> String {
+    serde_json::to_string(&capture(&mut app.world)).unwrap()
+  }
+
+  #[test]
+  fn restored_matches_play_on_the_same() {
+    let mut app = match_app(5);
+    for _ in 0..300 {
+      step(&mut app);
+    }
+    let snapshot = capture(&mut app.world);
+    assert!(!snapshot.snakes.is_empty() && !snapshot.food.is_empty());
+
+    let mut restored = match_app(6);
+    restore(&mut restored.world, &snapshot);
+    assert_eq!(state(&mut restored), state(&mut app));
+    for _ in 0..300 {
+      step(&mut app);
+      step(&mut restored);
+    }
+    assert_eq!(state(&mut restored), state(&mut app));
+  }
+}
@@ -0,0 +1,125 @@
+pub mod protocol;
+mod systems;
+
+use crate::{simulation::SimulationSet, snapshot::SnapshotSystem};
+use bevy::prelude::{
+  apply_system_buffers, info, resource_exists, warn, App, CoreSchedule, IntoSystemAppConfigs,
+  IntoSystemConfig, IntoSystemConfigs, Plugin,
+};
+
+pub const DEFAULT_SPECTATOR_ADDRESS: &str = "127.0.0.1:4100";
+/// Bytes a viewer may fall behind by before it's dropped.
+pub const MAX_BACKLOG: usize = 1 << 20;
+
+/// Streams the match to viewers connecting over TCP to `--spectate [address]`, a line of JSON for
+/// each of the tick's events and one with the board as the tick left it.
+pub struct SpectatorPlugin;
+
+impl Plugin for SpectatorPlugin {
+  fn build(&self, app: &mut App) {
+    let settings = resources::SpectatorSettings::from_args(std::env::args());
+    if let Some(address) = &settings.address {
+      match resources::Spectators::bind(address) {
+        Ok(spectators) => {
+          info!("Streaming the match on {address}");
+          app.insert_resource(spectators);
+        }
+        Err(err) => warn!("Couldn't stream the match on {address}: {err}"),
+      }
+    }
+
+    let streaming = resource_exists::<resources::Spectators>;
+    app
+      .insert_resource(settings)
+      .add_system(systems::accept.run_if(streaming()))
+      .add_systems(
+        (
+          // Snakes revived during the tick are only seen once its commands are applied.
+          apply_system_buffers,
+          systems::stream.run_if(streaming()),
+        )
+          .chain()
+          .after(SnapshotSystem::Track)
+          .in_set(SimulationSet::Snapshot)
+          .in_schedule(CoreSchedule::FixedUpdate),
+      );
+  }
+}
+
+pub mod resources {
+  use super::DEFAULT_SPECTATOR_ADDRESS;
+  use bevy::prelude::Resource;
+  use std::{
+    io::{self, ErrorKind, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+  };
+
+  #[derive(Debug, Resource)]
+  pub struct SpectatorSettings {
+    /// Address to stream the match on, it isn't streamed without one.
+    pub address: Option<String>,
+  }
+
+  impl SpectatorSettings {
+    /// Reads a `--spectate [address]` argument, without an address the match is streamed on
+    /// `DEFAULT_SPECTATOR_ADDRESS`.
+    pub fn from_args<I: Iterator<Item = String>>(args: I) -> Self {
+      let mut settings = Self { address: None };
+      let mut args = args.peekable();
+      while let Some(arg) = args.next() {
+        if arg == "--spectate" {
+          let address = args.next_if(|address| !address.starts_with("--"));
+          settings.address = Some(address.unwrap_or_else(|| DEFAULT_SPECTATOR_ADDRESS.to_string()));
+        }
+      }
+      settings
+    }
+  }
+
+  #[derive(Debug)]
+  pub struct Viewer {
+    pub address: SocketAddr,
+    pub(super) stream: TcpStream,
+    /// Bytes not written yet.
+    pub(super) backlog: Vec<u8>,
+  }
+
+  impl Viewer {
+    /// Writes as much of the backlog as the connection takes without blocking.
+    pub(super) fn flush(&mut self) -> io::Result<()> {
+      while !self.backlog.is_empty() {
+        match self.stream.write(&self.backlog) {
+          Ok(0) => return Err(ErrorKind::WriteZero.into()),
+          Ok(written) => {
+            self.backlog.drain(..written);
+          }
+          Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+          Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+          Err(err) => return Err(err),
+        }
+      }
+      Ok(())
+    }
+  }
+
+  #[derive(Debug, Resource)]
+  pub struct Spectators {
+    pub viewers: Vec<Viewer>,
+    pub(super) listener: TcpListener,
+  }
+
+  impl Spectators {
+    pub fn bind(address: &str) -> io::Result<Self> {
+      let listener = TcpListener::bind(address)?;
+      listener.set_nonblocking(true)?;
+      Ok(Self {
+        viewers: Vec::new(),
+        listener,
+      })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+      self.listener.local_addr()
+    }
+  }
+}
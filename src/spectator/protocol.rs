@@ -0,0 +1,32 @@
+use crate::{food::components::Food, observation::BoardState};
+use serde::{Deserialize, Serialize};
+
+/// Sent to every viewer, one JSON object per line. A tick's events come before its state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpectatorFrame {
+  /// The whole board as the tick left it.
+  State {
+    tick: u64,
+    #[serde(flatten)]
+    board: BoardState,
+  },
+  FoodEaten {
+    tick: u64,
+    snake: u64,
+    food: Food,
+    cell: [i32; 2],
+  },
+  Died {
+    tick: u64,
+    snake: u64,
+    /// Snake it ran into, `None` when it hit itself or was killed outright.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    killer: Option<u64>,
+  },
+  Respawned {
+    tick: u64,
+    snake: u64,
+    cell: [i32; 2],
+  },
+}
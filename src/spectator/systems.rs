@@ -0,0 +1,108 @@
+use super::{
+  protocol::SpectatorFrame,
+  resources::{Spectators, Viewer},
+  MAX_BACKLOG,
+};
+use crate::{
+  food::events::FoodEaten,
+  observation::{snake_id, BoardObserver},
+  simulation::resources::SimulationClock,
+  snake::{
+    components::{Living, Snake},
+    events::SnakeDied,
+  },
+};
+use bevy::prelude::{
+  info, warn, Added, DetectChanges, Entity, EventReader, Query, Ref, Res, ResMut,
+};
+use std::io::ErrorKind;
+
+pub(super) fn accept(mut spectators: ResMut<Spectators>) {
+  loop {
+    let (stream, address) = match spectators.listener.accept() {
+      Ok(accepted) => accepted,
+      Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+      Err(err) => {
+        warn!("Couldn't accept a viewer: {err}");
+        break;
+      }
+    };
+    if let Err(err) = stream.set_nonblocking(true) {
+      warn!("Couldn't stream to {address}: {err}");
+      continue;
+    }
+    // Frames are small and frequent, they shouldn't wait for one another.
+    let _ = stream.set_nodelay(true);
+    info!("Viewer {address} connected");
+    spectators.viewers.push(Viewer {
+      address,
+      stream,
+      backlog: Vec::new(),
+    });
+  }
+}
+
+pub(super) fn stream(
+  mut spectators: ResMut<Spectators>,
+  mut food_eaten_reader: EventReader<FoodEaten>,
+  mut snake_died_reader: EventReader<SnakeDied>,
+  q_revived: Query<(Entity, Ref<Snake>), Added<Living>>,
+  observer: BoardObserver,
+  clock: Res<SimulationClock>,
+) {
+  let tick = clock.tick;
+  let mut frames = Vec::new();
+  for FoodEaten { snake, food } in food_eaten_reader.iter() {
+    let (Some(food), Some(cell)) = (observer.food(*food), observer.head(*snake)) else {continue};
+    frames.push(SpectatorFrame::FoodEaten {
+      tick,
+      snake: snake_id(*snake),
+      food,
+      cell,
+    });
+  }
+  frames.extend(
+    snake_died_reader
+      .iter()
+      .map(|SnakeDied { snake, killer }| SpectatorFrame::Died {
+        tick,
+        snake: snake_id(*snake),
+        killer: killer.map(snake_id),
+      }),
+  );
+  // Snakes coming to life along with the snake itself were only just spawned.
+  for (snake, _) in q_revived.iter().filter(|(_, snake)| !snake.is_added()) {
+    let Some(cell) = observer.head(snake) else {continue};
+    frames.push(SpectatorFrame::Respawned {
+      tick,
+      snake: snake_id(snake),
+      cell,
+    });
+  }
+  if spectators.viewers.is_empty() {
+    return;
+  }
+  frames.push(SpectatorFrame::State {
+    tick,
+    board: observer.state(),
+  });
+
+  let mut lines = Vec::new();
+  for frame in &frames {
+    let Ok(line) = serde_json::to_vec(frame) else {continue};
+    lines.extend(line);
+    lines.push(b'\n');
+  }
+  spectators.viewers.retain_mut(|viewer| {
+    viewer.backlog.extend(&lines);
+    if let Err(err) = viewer.flush() {
+      info!("Viewer {} disconnected: {err}", viewer.address);
+      return false;
+    }
+    if viewer.backlog.len() > MAX_BACKLOG {
+      info!("Viewer {} fell behind", viewer.address);
+      return false;
+    }
+    true
+  });
+}
@@ -0,0 +1,90 @@
+use super::MatchResult;
+use std::collections::BTreeMap;
+
+pub const INITIAL_RATING: f32 = 1500.;
+/// Most a rating moves in one match, split between all the opponents.
+pub const K_FACTOR: f32 = 32.;
+
+/// Elo ratings, a free for all counts as every brain playing every other one.
+#[derive(Debug, Clone, Default)]
+pub struct Ratings(BTreeMap<String, f32>);
+
+impl Ratings {
+  pub fn get(&self, brain: &str) -> f32 {
+    self.0.get(brain).copied().unwrap_or(INITIAL_RATING)
+  }
+
+  pub fn update(&mut self, result: &MatchResult) {
+    let standings = &result.standings;
+    if standings.len() < 2 {
+      return;
+    }
+    let k = K_FACTOR / (standings.len() - 1) as f32;
+    let before = standings
+      .iter()
+      .map(|standing| self.get(&standing.brain))
+      .collect::<Vec<_>>();
+    let mut after = before.clone();
+    for (i, a) in standings.iter().enumerate() {
+      for (j, b) in standings.iter().enumerate().skip(i + 1) {
+        let tied = (a.alive, a.survived_ticks, a.length) == (b.alive, b.survived_ticks, b.length);
+        let score = if tied { 0.5 } else { 1. };
+        let expected = 1. / (1. + 10f32.powf((before[j] - before[i]) / 400.));
+        let delta = k * (score - expected);
+        after[i] += delta;
+        after[j] -= delta;
+      }
+    }
+    for (standing, rating) in standings.iter().zip(after) {
+      self.0.insert(standing.brain.clone(), rating);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tournament::Standing;
+
+  fn standing(brain: &str, survived_ticks: u64) -> Standing {
+    Standing {
+      brain: brain.to_string(),
+      survived_ticks,
+      length: 4,
+      kills: 0,
+      alive: false,
+    }
+  }
+
+  fn result(standings: Vec<Standing>) -> MatchResult {
+    MatchResult {
+      seed: 0,
+      ticks: 100,
+      standings,
+    }
+  }
+
+  #[test]
+  fn winner_takes_from_the_others() {
+    let mut ratings = Ratings::default();
+    ratings.update(&result(vec![standing("a", 100), standing("b", 50)]));
+    assert_eq!(ratings.get("a"), INITIAL_RATING + K_FACTOR / 2.);
+    assert_eq!(ratings.get("b"), INITIAL_RATING - K_FACTOR / 2.);
+
+    // Beating a weaker brain earns less than the first win did.
+    ratings.update(&result(vec![standing("a", 100), standing("b", 50)]));
+    let gain = ratings.get("a") - (INITIAL_RATING + K_FACTOR / 2.);
+    assert!(gain > 0. && gain < K_FACTOR / 2.);
+    assert_eq!(ratings.get("a") + ratings.get("b"), 2. * INITIAL_RATING);
+  }
+
+  #[test]
+  fn ties_and_lone_brains_leave_ratings_alone() {
+    let mut ratings = Ratings::default();
+    ratings.update(&result(vec![standing("a", 80), standing("b", 80)]));
+    ratings.update(&result(vec![standing("c", 80)]));
+    for brain in ["a", "b", "c"] {
+      assert_eq!(ratings.get(brain), INITIAL_RATING);
+    }
+  }
+}
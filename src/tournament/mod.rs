@@ -0,0 +1,245 @@
+pub mod elo;
+
+use crate::{
+  board::resources::GameBoard,
+  difficulty::resources::Difficulty,
+  enemy::{
+    components::{Enemy, Personality, RespawnTimer},
+    resources::{Personalities, Roster, RosterEntry},
+  },
+  scoreboard::components::{Name, ScoreEntity},
+  simulation::utils::{headless_app, step},
+  snake::{
+    components::{Living, SnakeBody},
+    events::SnakeDied,
+  },
+};
+use bevy::{
+  ecs::event::ManualEventReader,
+  prelude::{App, Entity, Events, With},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// AI taking part in matches, it plays as an enemy with this personality.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Brain {
+  pub name: String,
+  pub personality: Personality,
+  /// Network weights file steering the enemy, see `RosterEntry::network`.
+  #[serde(default)]
+  pub network: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchConfig {
+  pub cols: i32,
+  pub rows: i32,
+  pub difficulty: Difficulty,
+  /// Ticks after which a match is called, ranking whoever is still alive by length.
+  pub max_ticks: u64,
+}
+
+impl Default for MatchConfig {
+  fn default() -> Self {
+    Self {
+      cols: 40,
+      rows: 40,
+      difficulty: Difficulty::Hard,
+      max_ticks: 30_000,
+    }
+  }
+}
+
+/// How a brain did in a single match.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Standing {
+  pub brain: String,
+  pub survived_ticks: u64,
+  pub length: usize,
+  pub kills: usize,
+  pub alive: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchResult {
+  pub seed: u64,
+  pub ticks: u64,
+  /// Best first: survivors, then the longest lasting, ties broken by length.
+  pub standings: Vec<Standing>,
+}
+
+impl MatchResult {
+  pub fn winner(&self) -> Option<&Standing> {
+    self.standings.first()
+  }
+}
+
+/// Plays a free for all between the brains, none of them respawns.
+pub fn play_match(config: &MatchConfig, brains: &[Brain], seed: u64) -> MatchResult {
+  let mut app = headless_app(seed, GameBoard::with_cells(config.cols, config.rows));
+  app.insert_resource(config.difficulty);
+  // Startup loads the configured roster, the match's one replaces it afterwards.
+  app.update();
+  app.world.resource_mut::<Personalities>().extend(
+    brains
+      .iter()
+      .map(|brain| (brain.name.clone(), brain.personality)),
+  );
+  app.insert_resource(Roster {
+    enemies: brains
+      .iter()
+      .map(|brain| RosterEntry {
+        name: Some(brain.name.clone()),
+        network: brain.network.clone(),
+        ..RosterEntry::of_kind(&brain.name)
+      })
+      .collect(),
+    waves: None,
+    configured: true,
+  });
+
+  let mut standings = brains
+    .iter()
+    .map(|brain| Standing {
+      brain: brain.name.clone(),
+      survived_ticks: 0,
+      length: 0,
+      kills: 0,
+      alive: true,
+    })
+    .collect::<Vec<_>>();
+  let mut snakes = BTreeMap::<Entity, usize>::new();
+  let mut snake_died_reader = ManualEventReader::<SnakeDied>::default();
+  let mut ticks = 0;
+  while ticks < config.max_ticks {
+    step(&mut app);
+    ticks += 1;
+    if snakes.len() < brains.len() {
+      enlist(&mut app, brains, &mut snakes);
+    }
+    let deaths = snake_died_reader
+      .iter(app.world.resource::<Events<SnakeDied>>())
+      .copied()
+      .collect::<Vec<_>>();
+    for SnakeDied { snake, killer } in deaths {
+      if let Some(killer) = killer.and_then(|killer| snakes.get(&killer)) {
+        standings[*killer].kills += 1;
+      }
+      let Some(&index) = snakes.get(&snake) else {continue};
+      standings[index].alive = false;
+      standings[index].survived_ticks = ticks;
+      standings[index].length = length(&app, snake);
+    }
+    let alive = standings.iter().filter(|standing| standing.alive).count();
+    if snakes.len() == brains.len() && alive <= 1 {
+      break;
+    }
+  }
+
+  for (snake, index) in &snakes {
+    if app.world.get::<Living>(*snake).is_some() {
+      standings[*index].survived_ticks = ticks;
+      standings[*index].length = length(&app, *snake);
+    }
+  }
+  standings.sort_by(|a, b| {
+    (b.alive, b.survived_ticks, b.length).cmp(&(a.alive, a.survived_ticks, a.length))
+  });
+  MatchResult {
+    seed,
+    ticks,
+    standings,
+  }
+}
+
+/// Finds the snakes spawned for each brain and stops them from respawning, each entrant gets a
+/// snake of its own even when several share a brain.
+fn enlist(app: &mut App, brains: &[Brain], snakes: &mut BTreeMap<Entity, usize>) {
+  let mut q_enemy = app
+    .world
+    .query_filtered::<(Entity, &ScoreEntity), With<Enemy>>();
+  let mut enemies = q_enemy
+    .iter(&app.world)
+    .filter(|(enemy, _)| !snakes.contains_key(enemy))
+    .filter_map(|(enemy, score)| Some((enemy, app.world.get::<Name>(score.0)?.0.clone())))
+    .collect::<Vec<_>>();
+  enemies.sort_by_key(|(enemy, _)| *enemy);
+  for (enemy, name) in enemies {
+    let Some(index) = (0..brains.len())
+      .find(|index| brains[*index].name == name && !snakes.values().any(|seat| seat == index))
+    else {continue};
+    app.world.entity_mut(enemy).remove::<RespawnTimer>();
+    snakes.insert(enemy, index);
+  }
+}
+
+fn length(app: &App, snake: Entity) -> usize {
+  app.world.get::<SnakeBody>(snake).map_or(0, SnakeBody::len)
+}
+
+/// Totals of a brain over a tournament.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Record {
+  pub brain: String,
+  pub rating: f32,
+  pub matches: usize,
+  pub wins: usize,
+  pub kills: usize,
+  pub survived_ticks: u64,
+  pub length: usize,
+}
+
+impl Record {
+  pub fn average_survival(&self) -> f32 {
+    self.survived_ticks as f32 / self.matches.max(1) as f32
+  }
+
+  pub fn average_length(&self) -> f32 {
+    self.length as f32 / self.matches.max(1) as f32
+  }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Report {
+  pub config: Option<MatchConfig>,
+  /// Best rated first.
+  pub records: Vec<Record>,
+  pub matches: Vec<MatchResult>,
+}
+
+/// Plays `matches` matches, seeded from `seed` onwards, and rates the brains as it goes.
+pub fn run_tournament(config: &MatchConfig, brains: &[Brain], seed: u64, matches: u64) -> Report {
+  let mut ratings = elo::Ratings::default();
+  let mut records = brains
+    .iter()
+    .map(|brain| Record {
+      brain: brain.name.clone(),
+      ..Default::default()
+    })
+    .collect::<Vec<_>>();
+  let results = (seed..seed + matches)
+    .map(|seed| {
+      let result = play_match(config, brains, seed);
+      ratings.update(&result);
+      for (place, standing) in result.standings.iter().enumerate() {
+        let Some(record) = records.iter_mut().find(|record| record.brain == standing.brain) else {continue};
+        record.matches += 1;
+        record.wins += (place == 0) as usize;
+        record.kills += standing.kills;
+        record.survived_ticks += standing.survived_ticks;
+        record.length += standing.length;
+      }
+      result
+    })
+    .collect();
+  for record in &mut records {
+    record.rating = ratings.get(&record.brain);
+  }
+  records.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+  Report {
+    config: Some(config.clone()),
+    records,
+    matches: results,
+  }
+}
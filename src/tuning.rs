@@ -0,0 +1,241 @@
+use crate::{
+  enemy::components::Personality,
+  tournament::{play_match, Brain, MatchConfig, Standing},
+};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Name the evolving parameter set plays under against the opponents.
+pub const CANDIDATE: &str = "candidate";
+/// Upper bound of every goal weight.
+pub const MAX_WEIGHT: f32 = 2.;
+const WEIGHTS: usize = 7;
+const SELECTION_SIZE: usize = 3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FitnessWeights {
+  /// For surviving the whole match, less the earlier it dies.
+  pub survival: f32,
+  /// Per segment at the end of the match.
+  pub length: f32,
+  pub kill: f32,
+}
+
+impl Default for FitnessWeights {
+  fn default() -> Self {
+    Self {
+      survival: 1.,
+      length: 0.05,
+      kill: 0.5,
+    }
+  }
+}
+
+impl FitnessWeights {
+  pub fn fitness(&self, standing: &Standing, max_ticks: u64) -> f32 {
+    self.survival * standing.survived_ticks as f32 / max_ticks.max(1) as f32
+      + self.length * standing.length as f32
+      + self.kill * standing.kills as f32
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct TuningConfig {
+  pub population: usize,
+  pub generations: usize,
+  /// Matches each parameter set plays per generation, every set plays the same seeds.
+  pub matches: u64,
+  /// Best sets carried over unchanged to the next generation.
+  pub elites: usize,
+  /// Chance of each parameter being nudged in a child.
+  pub mutation_rate: f32,
+  /// Largest nudge to a goal weight, planning depth moves by up to ten times as many cells.
+  pub mutation_scale: f32,
+  pub max_planning_depth: usize,
+  pub fitness: FitnessWeights,
+  pub match_config: MatchConfig,
+  pub seed: u64,
+}
+
+impl Default for TuningConfig {
+  fn default() -> Self {
+    Self {
+      population: 12,
+      generations: 10,
+      matches: 3,
+      elites: 2,
+      mutation_rate: 0.2,
+      mutation_scale: 0.3,
+      max_planning_depth: 64,
+      fitness: FitnessWeights::default(),
+      match_config: MatchConfig {
+        max_ticks: 6_000,
+        ..Default::default()
+      },
+      seed: 0,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Candidate {
+  pub personality: Personality,
+  pub fitness: f32,
+}
+
+fn weights(personality: &Personality) -> [f32; WEIGHTS] {
+  [
+    personality.regular_food,
+    personality.swiftness_food,
+    personality.extra_growth_food,
+    personality.hunt_smaller,
+    personality.flee_larger,
+    personality.stay_central,
+    personality.avoid_crowding,
+  ]
+}
+
+fn with_weights(weights: [f32; WEIGHTS], planning_depth: Option<usize>) -> Personality {
+  let [regular_food, swiftness_food, extra_growth_food, hunt_smaller, flee_larger, stay_central, avoid_crowding] =
+    weights;
+  Personality {
+    regular_food,
+    swiftness_food,
+    extra_growth_food,
+    hunt_smaller,
+    flee_larger,
+    stay_central,
+    avoid_crowding,
+    planning_depth,
+  }
+}
+
+fn random_personality<R: Rng>(rng: &mut R, config: &TuningConfig) -> Personality {
+  with_weights(
+    [(); WEIGHTS].map(|_| rng.gen_range(0. ..MAX_WEIGHT)),
+    Some(rng.gen_range(0..=config.max_planning_depth)),
+  )
+}
+
+fn crossover<R: Rng>(rng: &mut R, a: &Personality, b: &Personality) -> Personality {
+  let (a_weights, b_weights) = (weights(a), weights(b));
+  let mut child = [0.; WEIGHTS];
+  for (i, weight) in child.iter_mut().enumerate() {
+    *weight = if rng.gen() {
+      a_weights[i]
+    } else {
+      b_weights[i]
+    };
+  }
+  let planning_depth = if rng.gen() {
+    a.planning_depth
+  } else {
+    b.planning_depth
+  };
+  with_weights(child, planning_depth)
+}
+
+fn mutate<R: Rng>(rng: &mut R, personality: &Personality, config: &TuningConfig) -> Personality {
+  let scale = config.mutation_scale;
+  let weights = weights(personality).map(|weight| {
+    if rng.gen::<f32>() < config.mutation_rate {
+      (weight + rng.gen_range(-scale..=scale)).clamp(0., MAX_WEIGHT)
+    } else {
+      weight
+    }
+  });
+  let mut planning_depth = personality.planning_depth;
+  if rng.gen::<f32>() < config.mutation_rate {
+    // Sets still going by the difficulty's depth start from the middle of the range.
+    let depth = planning_depth.unwrap_or(config.max_planning_depth / 2) as i64;
+    let step = (scale * 10.).ceil() as i64;
+    planning_depth = Some(
+      (depth + rng.gen_range(-step..=step)).clamp(0, config.max_planning_depth as i64) as usize,
+    );
+  }
+  with_weights(weights, planning_depth)
+}
+
+fn evaluate(
+  personality: &Personality,
+  opponents: &[Brain],
+  config: &TuningConfig,
+  generation: usize,
+) -> f32 {
+  let mut brains = opponents.to_vec();
+  brains.push(Brain {
+    name: CANDIDATE.to_string(),
+    personality: *personality,
+    network: None,
+  });
+  let first_seed = config.seed + generation as u64 * config.matches;
+  let total = (first_seed..first_seed + config.matches)
+    .map(|seed| play_match(&config.match_config, &brains, seed))
+    .filter_map(|result| {
+      let standing = result
+        .standings
+        .iter()
+        .find(|standing| standing.brain == CANDIDATE)?;
+      Some(
+        config
+          .fitness
+          .fitness(standing, config.match_config.max_ticks),
+      )
+    })
+    .sum::<f32>();
+  total / config.matches.max(1) as f32
+}
+
+/// Evolves parameter sets against the opponents, starting from their own personalities, and
+/// returns the last generation best first. `on_generation` sees every generation once rated.
+pub fn evolve<F: FnMut(usize, &[Candidate])>(
+  config: &TuningConfig,
+  opponents: &[Brain],
+  mut on_generation: F,
+) -> Vec<Candidate> {
+  let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+  let size = config.population.max(1);
+  let mut population = opponents
+    .iter()
+    .map(|brain| brain.personality)
+    .take(size)
+    .collect::<Vec<_>>();
+  while population.len() < size {
+    population.push(random_personality(&mut rng, config));
+  }
+
+  let mut rated = Vec::new();
+  for generation in 0..config.generations.max(1) {
+    rated = population
+      .iter()
+      .map(|personality| Candidate {
+        personality: *personality,
+        fitness: evaluate(personality, opponents, config, generation),
+      })
+      .collect::<Vec<_>>();
+    rated.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+    on_generation(generation, &rated);
+    if generation + 1 >= config.generations {
+      break;
+    }
+
+    let select = |rng: &mut ChaCha8Rng| {
+      rated
+        .choose_multiple(rng, SELECTION_SIZE.min(rated.len()))
+        .max_by(|a, b| a.fitness.total_cmp(&b.fitness))
+        .map(|candidate| candidate.personality)
+        .unwrap_or_default()
+    };
+    population = rated
+      .iter()
+      .take(config.elites)
+      .map(|candidate| candidate.personality)
+      .collect();
+    while population.len() < size {
+      let (a, b) = (select(&mut rng), select(&mut rng));
+      let child = crossover(&mut rng, &a, &b);
+      population.push(mutate(&mut rng, &child, config));
+    }
+  }
+  rated
+}